@@ -1,12 +1,20 @@
-use std::path::{Path, PathBuf};
-use tauri::command;
-use dirs;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use once_cell::sync::Lazy;
+use tauri::{command, AppHandle, Emitter};
 use std::fs;
 use std::io::Write;
-use std::process::Command;
 use reqwest;
 use serde::{Deserialize, Serialize};
 
+use crate::http;
+use crate::minecraft::{file_sha1_matches, installer_command, run_installer};
+use crate::mojang;
+use crate::servers::{self, ServerEntry};
+use crate::version;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct OnlineModpack {
     pub id: String,
@@ -19,16 +27,205 @@ pub struct OnlineModpack {
     pub image: String,
     pub url: String,
     pub scopes: Option<Vec<String>>,
+    #[serde(default)]
+    pub allow_prerelease: bool,
+    #[serde(default)]
+    pub servers: Vec<ServerEntry>,
+    /// `options.txt` values (render distance, GUI scale, keybinds) applied on first install only
+    #[serde(default)]
+    pub default_options: HashMap<String, String>,
+    /// Set by staff to freeze a pack (e.g. mid-migration); launching shows this message instead
+    #[serde(default)]
+    pub locked: Option<String>,
+    /// Pins the modloader installer; the latest one is used when unset
+    #[serde(default)]
+    pub installer_version: Option<String>,
+    /// Pack file URLs of earlier versions, by version, so updates can be diffed
+    #[serde(default)]
+    pub version_files: HashMap<String, String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ModFileEntry {
+    /// Stable mod id used to match a file across versions; derived from the filename when absent
+    #[serde(default)]
+    pub id: Option<String>,
     pub filename: String,
     pub url: String,
     pub dir: String,
     pub hash: Option<String>,
     pub hashformat: Option<String>,
     pub scopes: Option<Vec<String>>,
+    pub size: Option<u64>,
+    /// Optional files (shaders, extra content) can be turned off by the player
+    #[serde(default)]
+    pub optional: bool,
+    #[serde(default = "default_enabled")]
+    pub default_enabled: bool,
+    /// Whether updates replace the installed file. Defaults to off for files under
+    /// `config/`, so shipped configs are only written on first install.
+    #[serde(default)]
+    pub overwrite: Option<bool>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl ModFileEntry {
+    fn is_config(&self) -> bool {
+        self.dir == "config" || self.dir.starts_with("config/")
+    }
+
+    /// Whether an update may replace this file once it is installed
+    fn overwrites(&self) -> bool {
+        self.overwrite.unwrap_or(!self.is_config())
+    }
+}
+
+/// The modloader version used for an install and where it came from
+/// ("specified" by the pack, or the "recommended"/"latest" promotion)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ModloaderSelection {
+    pub version: String,
+    pub source: String,
+}
+
+impl ModloaderSelection {
+    fn new(version: &str, source: &str) -> Self {
+        ModloaderSelection {
+            version: version.to_string(),
+            source: source.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiskCheck {
+    pub required_estimate: u64,
+    pub available: u64,
+    pub sufficient: bool,
+    pub warning: Option<String>,
+}
+
+/// Window over which the reported download speed is averaged
+const SPEED_WINDOW: Duration = Duration::from_secs(5);
+
+/// Payload of the `install_progress` event
+#[derive(Debug, Serialize, Clone)]
+pub struct InstallProgress {
+    pub file: String,
+    pub completed: usize,
+    pub total: usize,
+    pub bytes_downloaded: u64,
+    /// Rolling average over the last few seconds
+    pub bytes_per_sec: f64,
+}
+
+/// Stage of a modpack install
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum InstallPhase {
+    Preparing,
+    Minecraft,
+    Modloader,
+    Files,
+    Finishing,
+}
+
+/// Latest state of a running install, for windows that open after the events were sent
+#[derive(Debug, Serialize, Clone)]
+pub struct ProgressSnapshot {
+    pub phase: InstallPhase,
+    pub bytes_downloaded: u64,
+    pub percent: f64,
+    pub current_file: Option<String>,
+}
+
+/// Snapshots of the installs in progress, keyed by modpack id
+static INSTALL_PROGRESS: Lazy<Mutex<HashMap<String, ProgressSnapshot>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn set_install_progress(modpack_id: &str, snapshot: ProgressSnapshot) {
+    INSTALL_PROGRESS.lock().unwrap().insert(modpack_id.to_string(), snapshot);
+}
+
+fn set_install_phase(modpack_id: &str, phase: InstallPhase) {
+    let mut progress = INSTALL_PROGRESS.lock().unwrap();
+    let snapshot = progress.entry(modpack_id.to_string()).or_insert(ProgressSnapshot {
+        phase,
+        bytes_downloaded: 0,
+        percent: 0.0,
+        current_file: None,
+    });
+    snapshot.phase = phase;
+}
+
+/// Clears a pack's snapshot when its install ends, whether it succeeded or failed
+struct InstallProgressGuard<'a>(&'a str);
+
+impl Drop for InstallProgressGuard<'_> {
+    fn drop(&mut self) {
+        INSTALL_PROGRESS.lock().unwrap().remove(self.0);
+    }
+}
+
+/// Current progress of a modpack install, or `None` when none is running
+#[command]
+pub async fn get_install_progress(modpack_id: String) -> Result<Option<ProgressSnapshot>, String> {
+    Ok(INSTALL_PROGRESS.lock().unwrap().get(&modpack_id).cloned())
+}
+
+/// Payload of the `install_summary` event
+#[derive(Debug, Serialize, Clone)]
+pub struct InstallSummary {
+    pub total_bytes: u64,
+    pub elapsed: f64,
+    pub avg_speed: f64,
+}
+
+/// Tracks downloaded bytes to report install throughput
+struct DownloadStats {
+    started: Instant,
+    total_bytes: u64,
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl DownloadStats {
+    fn new() -> Self {
+        DownloadStats {
+            started: Instant::now(),
+            total_bytes: 0,
+            samples: VecDeque::new(),
+        }
+    }
+
+    fn record(&mut self, bytes: u64) {
+        let now = Instant::now();
+        self.total_bytes += bytes;
+        self.samples.push_back((now, bytes));
+        while let Some((at, _)) = self.samples.front() {
+            if now.duration_since(*at) <= SPEED_WINDOW {
+                break;
+            }
+            self.samples.pop_front();
+        }
+    }
+
+    fn rolling_speed(&self) -> f64 {
+        // Older samples were dropped in `record`, so this covers at most the window
+        let window_bytes: u64 = self.samples.iter().map(|(_, b)| b).sum();
+        let span = self.started.elapsed().min(SPEED_WINDOW).as_secs_f64();
+        if span > 0.0 { window_bytes as f64 / span } else { 0.0 }
+    }
+
+    fn summary(&self) -> InstallSummary {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        InstallSummary {
+            total_bytes: self.total_bytes,
+            elapsed,
+            avg_speed: if elapsed > 0.0 { self.total_bytes as f64 / elapsed } else { 0.0 },
+        }
+    }
 }
 
 /// Utility: check if any of the user's groups match the scopes (or if scopes is None, allow)
@@ -39,25 +236,470 @@ fn is_in_scope(user_groups: &[String], scopes: &Option<Vec<String>>) -> bool {
     }
 }
 
-/// Fetch and filter the modpack list by user_groups
-#[command]
-pub async fn fetch_modpack_list(list_url: &str, user_groups: Vec<String>) -> Result<Vec<OnlineModpack>, String> {
-    let resp = reqwest::get(list_url)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ModpackList {
+    pub modpacks: Vec<OnlineModpack>,
+    /// True when the list host was unreachable and the cached copy was used
+    pub stale: bool,
+}
+
+/// Last successful raw modpack list response, used when the host is unreachable
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedModpackList {
+    url: String,
+    body: String,
+}
+
+fn get_modpack_list_cache_path() -> PathBuf {
+    crate::paths::config_dir()
+        .expect("Failed to get config directory")
+        .join("wise0wl-launcher")
+        .join("modpack-list-cache.json")
+}
+
+fn load_cached_modpack_list(list_url: &str) -> Option<String> {
+    let content = fs::read_to_string(get_modpack_list_cache_path()).ok()?;
+    let cached: CachedModpackList = serde_json::from_str(&content).ok()?;
+    if cached.url == list_url {
+        Some(cached.body)
+    } else {
+        None
+    }
+}
+
+/// A pack's entry in the last synced modpack list
+fn cached_modpack_entry(modpack_id: &str) -> Option<serde_json::Value> {
+    let content = fs::read_to_string(get_modpack_list_cache_path()).ok()?;
+    let cached: CachedModpackList = serde_json::from_str(&content).ok()?;
+    let modpacks: Vec<serde_json::Value> = serde_json::from_str(&cached.body).ok()?;
+    modpacks.into_iter().find(|m| m["id"].as_str() == Some(modpack_id))
+}
+
+/// The lock message of a pack in the last synced modpack list, if staff locked it
+pub(crate) fn modpack_lock_message(modpack_id: &str) -> Option<String> {
+    cached_modpack_entry(modpack_id)?["locked"].as_str().map(str::to_string)
+}
+
+fn save_cached_modpack_list(list_url: &str, body: &str) -> Result<(), String> {
+    let cache_path = get_modpack_list_cache_path();
+    if let Some(parent) = cache_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+    }
+    let cached = CachedModpackList { url: list_url.to_string(), body: body.to_string() };
+    let content = serde_json::to_string(&cached)
+        .map_err(|e| format!("Failed to serialize modpack list cache: {}", e))?;
+    fs::write(&cache_path, content)
+        .map_err(|e| format!("Failed to write modpack list cache: {}", e))
+}
+
+async fn fetch_modpack_list_body(list_url: &str) -> Result<String, String> {
+    let resp = http::client().get(list_url).send()
         .await
         .map_err(|e| format!("Failed to fetch modpack list: {}", e))?;
-    let modpacks: Vec<OnlineModpack> = resp.json()
+    if !resp.status().is_success() {
+        return Err(format!("HTTP {} while fetching modpack list", resp.status()));
+    }
+    resp.text()
         .await
-        .map_err(|e| format!("Failed to parse modpack list: {}", e))?;
+        .map_err(|e| format!("Failed to read modpack list: {}", e))
+}
+
+/// Fetch and filter the modpack list by user_groups, falling back to the
+/// last successfully fetched list (flagged as stale) when the host is unreachable
+#[command]
+pub async fn fetch_modpack_list(list_url: &str, user_groups: Vec<String>) -> Result<ModpackList, String> {
+    let fetched = match fetch_modpack_list_body(list_url).await {
+        Ok(body) => serde_json::from_str::<Vec<OnlineModpack>>(&body)
+            .map(|modpacks| (body, modpacks))
+            .map_err(|e| format!("Failed to parse modpack list: {}", e)),
+        Err(e) => Err(e),
+    };
+    let (modpacks, stale) = match fetched {
+        Ok((body, modpacks)) => {
+            if let Err(e) = save_cached_modpack_list(list_url, &body) {
+                println!("Warning: Failed to cache modpack list: {}", e);
+            }
+            (modpacks, false)
+        }
+        Err(e) => {
+            let body = load_cached_modpack_list(list_url).ok_or(e.clone())?;
+            println!("Using cached modpack list: {}", e);
+            let modpacks: Vec<OnlineModpack> = serde_json::from_str(&body)
+                .map_err(|e| format!("Failed to parse cached modpack list: {}", e))?;
+            (modpacks, true)
+        }
+    };
     let filtered = modpacks
         .into_iter()
         .filter(|mp| is_in_scope(&user_groups, &mp.scopes))
         .collect();
-    Ok(filtered)
+    Ok(ModpackList { modpacks: filtered, stale })
+}
+
+/// Replace one pack's entry in the cached list body, keeping every other entry as cached
+fn update_cached_modpack_entry(list_url: &str, entry: &serde_json::Value) -> Result<(), String> {
+    let mut entries: Vec<serde_json::Value> = load_cached_modpack_list(list_url)
+        .and_then(|body| serde_json::from_str(&body).ok())
+        .unwrap_or_default();
+    match entries.iter_mut().find(|e| e["id"] == entry["id"]) {
+        Some(existing) => *existing = entry.clone(),
+        None => entries.push(entry.clone()),
+    }
+    let body = serde_json::to_string(&entries)
+        .map_err(|e| format!("Failed to serialize modpack list: {}", e))?;
+    save_cached_modpack_list(list_url, &body)
+}
+
+/// Re-fetch a single pack's definition and update its cached entry. The
+/// installed state (`installed-pack.json`) is left alone, so the next install
+/// still diffs against what is on disk.
+#[command]
+pub async fn refresh_modpack(modpack_id: String, list_url: String, user_groups: Vec<String>) -> Result<OnlineModpack, String> {
+    let body = fetch_modpack_list_body(&list_url).await?;
+    let entries: Vec<serde_json::Value> = serde_json::from_str(&body)
+        .map_err(|e| format!("Failed to parse modpack list: {}", e))?;
+    let entry = entries
+        .into_iter()
+        .find(|e| e["id"].as_str() == Some(modpack_id.as_str()))
+        .ok_or(format!("Modpack {} is no longer in the modpack list", modpack_id))?;
+    let modpack: OnlineModpack = serde_json::from_value(entry.clone())
+        .map_err(|e| format!("Failed to parse modpack {}: {}", modpack_id, e))?;
+    if !is_in_scope(&user_groups, &modpack.scopes) {
+        return Err(format!("Modpack {} is not available to your groups", modpack_id));
+    }
+    if let Err(e) = update_cached_modpack_entry(&list_url, &entry) {
+        println!("Warning: Failed to update cached modpack list: {}", e);
+    }
+    Ok(modpack)
+}
+
+/// Re-apply a pack's first-install-only files (its shipped configs), discarding the
+/// player's edits. Returns the reset files as "<dir>/<filename>".
+#[command]
+pub async fn reset_pack_configs(modpack_id: String, user_groups: Vec<String>) -> Result<Vec<String>, String> {
+    let instance_dir = get_instance_dir(&modpack_id);
+    let modpack = load_installed_pack(&instance_dir)
+        .ok_or(format!("Modpack {} is not installed", modpack_id))?;
+    let files = fetch_effective_files(&modpack.url, &user_groups).await?;
+    let mut managed = load_managed_files(&instance_dir);
+    let mut reset = Vec::new();
+    for entry in files.iter().filter(|e| !e.overwrites()) {
+        // Leave deselected optional files off
+        if entry.optional && !instance_dir.join(&entry.dir).join(&entry.filename).exists() {
+            continue;
+        }
+        let key = managed_file_key(entry);
+        // Drop the validators so the download isn't skipped as unchanged
+        managed.remove(&key);
+        if let Err(e) = download_and_verify(entry, &instance_dir, &mut managed).await {
+            let _ = save_managed_files(&instance_dir, &managed);
+            return Err(e);
+        }
+        reset.push(key);
+    }
+    save_managed_files(&instance_dir, &managed)?;
+    println!("Reset {} shipped config files for {}", reset.len(), modpack_id);
+    Ok(reset)
+}
+
+/// A file present in both compared versions whose contents changed
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ModUpdate {
+    pub id: String,
+    pub from: ModFileEntry,
+    pub to: ModFileEntry,
+}
+
+/// What changed in a pack's files between two versions
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ModDiff {
+    pub added: Vec<ModFileEntry>,
+    pub removed: Vec<ModFileEntry>,
+    pub updated: Vec<ModUpdate>,
+}
+
+/// Identity of a pack file across versions: "<dir>/<id>", taking the id from the
+/// filename up to its version number when not given ("jei-1.20.1-forge.jar" -> "jei")
+fn mod_identity(entry: &ModFileEntry) -> String {
+    let name = match &entry.id {
+        Some(id) => id.to_lowercase(),
+        None => {
+            let stem = entry.filename.to_lowercase();
+            let cut = stem
+                .char_indices()
+                .find(|(i, c)| (*c == '-' || *c == '_') && stem[i + 1..].starts_with(|n: char| n.is_ascii_digit()))
+                .map(|(i, _)| i);
+            match cut {
+                Some(i) => stem[..i].to_string(),
+                None => stem.trim_end_matches(".jar").to_string(),
+            }
+        }
+    };
+    format!("{}/{}", entry.dir, name)
+}
+
+/// Whether two entries for the same mod point at different files
+fn mod_changed(from: &ModFileEntry, to: &ModFileEntry) -> bool {
+    match (&from.hash, &to.hash) {
+        (Some(a), Some(b)) => !a.eq_ignore_ascii_case(b),
+        _ => from.filename != to.filename || from.url != to.url,
+    }
+}
+
+fn diff_mod_lists(from: &[ModFileEntry], to: &[ModFileEntry]) -> ModDiff {
+    let old: HashMap<String, &ModFileEntry> = from.iter().map(|e| (mod_identity(e), e)).collect();
+    let new: HashMap<String, &ModFileEntry> = to.iter().map(|e| (mod_identity(e), e)).collect();
+    let mut diff = ModDiff::default();
+    for entry in to {
+        let id = mod_identity(entry);
+        match old.get(&id) {
+            None => diff.added.push(entry.clone()),
+            Some(previous) if mod_changed(previous, entry) => diff.updated.push(ModUpdate {
+                id,
+                from: (*previous).clone(),
+                to: entry.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+    diff.removed = from.iter().filter(|e| !new.contains_key(&mod_identity(e))).cloned().collect();
+    diff
+}
+
+/// The pack file URL of a given version of a pack
+fn pack_file_url(modpack: &OnlineModpack, version: &str) -> Result<String, String> {
+    if modpack.version == version {
+        return Ok(modpack.url.clone());
+    }
+    modpack.version_files.get(version).cloned().ok_or(format!(
+        "The modpack list has no pack file for {} version {}",
+        modpack.id, version
+    ))
+}
+
+/// Compare the files of two versions of a pack as `user_groups` would get them,
+/// for the update dialog
+#[command]
+pub async fn diff_modpack_versions(
+    modpack_id: String,
+    from_version: String,
+    to_version: String,
+    user_groups: Vec<String>,
+) -> Result<ModDiff, String> {
+    let entry = cached_modpack_entry(&modpack_id)
+        .ok_or(format!("Modpack {} is not in the modpack list", modpack_id))?;
+    let modpack: OnlineModpack = serde_json::from_value(entry)
+        .map_err(|e| format!("Failed to parse modpack {}: {}", modpack_id, e))?;
+    let from = fetch_effective_files(&pack_file_url(&modpack, &from_version)?, &user_groups).await?;
+    let to = fetch_effective_files(&pack_file_url(&modpack, &to_version)?, &user_groups).await?;
+    let diff = diff_mod_lists(&from, &to);
+    println!(
+        "{} {} -> {}: {} added, {} removed, {} updated",
+        modpack_id, from_version, to_version, diff.added.len(), diff.removed.len(), diff.updated.len()
+    );
+    Ok(diff)
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LintSeverity {
+    /// The list would break installs for players
+    Error,
+    /// Probably a mistake, but the list still works
+    Warning,
+}
+
+/// One problem found by `lint_modpack_list`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LintFinding {
+    pub severity: LintSeverity,
+    /// The pack the finding is about, when it concerns a single entry
+    pub modpack_id: Option<String>,
+    pub field: Option<String>,
+    pub message: String,
+}
+
+impl LintFinding {
+    fn new(severity: LintSeverity, modpack_id: Option<&str>, field: Option<&str>, message: String) -> Self {
+        LintFinding {
+            severity,
+            modpack_id: modpack_id.map(str::to_string),
+            field: field.map(str::to_string),
+            message,
+        }
+    }
+}
+
+/// Fields every list entry needs, and whether an empty string is acceptable
+/// (an empty `modloader_version` means "pick the latest")
+const REQUIRED_LIST_FIELDS: &[(&str, bool)] = &[
+    ("id", false),
+    ("name", false),
+    ("description", true),
+    ("version", false),
+    ("minecraft_version", false),
+    ("modloader", false),
+    ("modloader_version", true),
+    ("image", false),
+    ("url", false),
+];
+
+/// Modloaders the installer knows how to set up
+const SUPPORTED_MODLOADERS: &[&str] = &["forge", "fabric", "neoforge", "quilt"];
+
+/// Edit distance between two short strings, for spotting scope typos
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Why a URL in the list can't be fetched, if it can't
+async fn check_reachable(url: &str) -> Option<String> {
+    let client = http::client();
+    let resp = match client.head(url).send().await {
+        // Some hosts don't allow HEAD
+        Ok(resp) if resp.status() == reqwest::StatusCode::METHOD_NOT_ALLOWED => client.get(url).send().await,
+        other => other,
+    };
+    match resp {
+        Ok(resp) if resp.status().is_success() => None,
+        Ok(resp) => Some(format!("{} returned HTTP {}", url, resp.status())),
+        Err(e) => Some(format!("{} is unreachable: {}", url, e)),
+    }
+}
+
+/// Check a modpack list (a URL or a local file) for mistakes before publishing:
+/// duplicate ids, missing fields, unsupported modloaders, unreachable URLs and
+/// scopes that look like typos of another scope
+#[command]
+pub async fn lint_modpack_list(list_url_or_path: String) -> Result<Vec<LintFinding>, String> {
+    let body = if list_url_or_path.starts_with("http://") || list_url_or_path.starts_with("https://") {
+        fetch_modpack_list_body(&list_url_or_path).await?
+    } else {
+        fs::read_to_string(&list_url_or_path)
+            .map_err(|e| format!("Failed to read {}: {}", list_url_or_path, e))?
+    };
+    let list: serde_json::Value = serde_json::from_str(&body)
+        .map_err(|e| format!("Failed to parse modpack list: {}", e))?;
+    let entries = list.as_array().ok_or("The modpack list must be a JSON array".to_string())?;
+
+    let mut findings = Vec::new();
+    let mut seen_ids: HashMap<String, usize> = HashMap::new();
+    // Who uses each scope, as (id, label) pairs
+    let mut scope_uses: HashMap<String, Vec<(Option<String>, String)>> = HashMap::new();
+    for (index, entry) in entries.iter().enumerate() {
+        let id = entry["id"].as_str().filter(|id| !id.is_empty());
+        let label = id.map(str::to_string).unwrap_or_else(|| format!("entry #{}", index + 1));
+        if !entry.is_object() {
+            findings.push(LintFinding::new(LintSeverity::Error, None, None, format!("{} is not an object", label)));
+            continue;
+        }
+
+        for (field, allow_empty) in REQUIRED_LIST_FIELDS {
+            let message = match entry.get(*field) {
+                None | Some(serde_json::Value::Null) => format!("{} is missing {}", label, field),
+                Some(serde_json::Value::String(value)) if value.trim().is_empty() && !allow_empty => {
+                    format!("{} has an empty {}", label, field)
+                }
+                Some(serde_json::Value::String(_)) => continue,
+                Some(_) => format!("{} has a non-string {}", label, field),
+            };
+            findings.push(LintFinding::new(LintSeverity::Error, id, Some(field), message));
+        }
+
+        if let Some(id) = id {
+            if let Some(first) = seen_ids.insert(id.to_string(), index) {
+                findings.push(LintFinding::new(
+                    LintSeverity::Error,
+                    Some(id),
+                    Some("id"),
+                    format!("id {} is used by entries #{} and #{}", id, first + 1, index + 1),
+                ));
+            }
+        }
+
+        if let Some(modloader) = entry["modloader"].as_str().filter(|m| !m.is_empty()) {
+            if !SUPPORTED_MODLOADERS.contains(&modloader.to_lowercase().as_str()) {
+                findings.push(LintFinding::new(
+                    LintSeverity::Error,
+                    id,
+                    Some("modloader"),
+                    format!("{} uses unsupported modloader '{}' (expected one of {})", label, modloader, SUPPORTED_MODLOADERS.join(", ")),
+                ));
+            }
+        }
+
+        // The pack file must be reachable; images may also be bundled paths like "/images/x.png"
+        for (field, severity) in [("url", LintSeverity::Error), ("image", LintSeverity::Warning)] {
+            let Some(url) = entry[field].as_str().filter(|u| !u.is_empty()) else {
+                continue;
+            };
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                if field == "url" {
+                    findings.push(LintFinding::new(severity, id, Some(field), format!("{} has a pack file URL that isn't http(s): {}", label, url)));
+                }
+                continue;
+            }
+            if let Err(e) = reqwest::Url::parse(url) {
+                findings.push(LintFinding::new(severity, id, Some(field), format!("{} has an invalid {} URL: {}", label, field, e)));
+            } else if let Some(problem) = check_reachable(url).await {
+                findings.push(LintFinding::new(severity, id, Some(field), problem));
+            }
+        }
+
+        if let Some(scopes) = entry["scopes"].as_array() {
+            for scope in scopes {
+                match scope.as_str() {
+                    Some(scope) if !scope.trim().is_empty() => {
+                        scope_uses.entry(scope.to_string()).or_default().push((id.map(str::to_string), label.clone()));
+                    }
+                    _ => findings.push(LintFinding::new(
+                        LintSeverity::Error,
+                        id,
+                        Some("scopes"),
+                        format!("{} has an empty or non-string scope", label),
+                    )),
+                }
+            }
+        }
+    }
+
+    // A rarely used scope a small edit away from a more common one is most likely a typo
+    for (scope, users) in &scope_uses {
+        let max_distance = if scope.len() <= 4 { 1 } else { 2 };
+        let likely = scope_uses
+            .iter()
+            .filter(|(other, other_users)| *other != scope && other_users.len() > users.len())
+            .find(|(other, _)| edit_distance(&other.to_lowercase(), &scope.to_lowercase()) <= max_distance);
+        if let Some((intended, _)) = likely {
+            for (id, label) in users {
+                findings.push(LintFinding::new(
+                    LintSeverity::Warning,
+                    id.as_deref(),
+                    Some("scopes"),
+                    format!("{} uses scope '{}'; did you mean '{}'?", label, scope, intended),
+                ));
+            }
+        }
+    }
+
+    println!("Linted {} modpack list entries: {} findings", entries.len(), findings.len());
+    Ok(findings)
 }
 
 /// Fetch and filter the modpack file (mods/resources) by user_groups
 pub async fn fetch_modpack_file(url: &str, user_groups: &[String]) -> Result<Vec<ModFileEntry>, String> {
-    let resp = reqwest::get(url)
+    let resp = http::client().get(url).send()
         .await
         .map_err(|e| format!("Failed to fetch modpack file: {}", e))?;
     let files: Vec<ModFileEntry> = resp.json()
@@ -70,65 +712,582 @@ pub async fn fetch_modpack_file(url: &str, user_groups: &[String]) -> Result<Vec
     Ok(filtered)
 }
 
-/// Download and verify a file
-async fn download_and_verify(entry: &ModFileEntry, dest_dir: &Path) -> Result<(), String> {
-    let resp = reqwest::get(&entry.url)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EffectiveModList {
+    pub files: Vec<ModFileEntry>,
+    pub total_size: u64,
+}
+
+/// When several entries install to the same path, keep a single one: an entry
+/// restricted to specific groups overrides an unscoped one, otherwise the later entry wins
+fn resolve_conflicts(files: Vec<ModFileEntry>) -> Vec<ModFileEntry> {
+    let mut resolved: Vec<ModFileEntry> = Vec::new();
+    for entry in files {
+        let key = managed_file_key(&entry);
+        match resolved.iter().position(|e| managed_file_key(e) == key) {
+            Some(i) => {
+                if entry.scopes.is_some() || resolved[i].scopes.is_none() {
+                    println!("Pack file {} is listed more than once, using {}", key, entry.url);
+                    resolved[i] = entry;
+                }
+            }
+            None => resolved.push(entry),
+        }
+    }
+    resolved
+}
+
+/// Fetch the pack file and reduce it to what `user_groups` will actually install
+async fn fetch_effective_files(url: &str, user_groups: &[String]) -> Result<Vec<ModFileEntry>, String> {
+    let files = fetch_modpack_file(url, user_groups).await?;
+    Ok(resolve_conflicts(files))
+}
+
+/// Preview the exact set of files a user with `user_groups` would get for a modpack
+#[command]
+pub async fn effective_mod_list(modpack: OnlineModpack, user_groups: Vec<String>) -> Result<EffectiveModList, String> {
+    let mut files = fetch_effective_files(&modpack.url, &user_groups).await?;
+    for entry in files.iter_mut().filter(|e| e.size.is_none()) {
+        // Fall back to the server-reported size for entries without a declared one,
+        // asking for the raw file so Content-Length isn't a compressed size
+        let request = http::client().head(&entry.url).header(reqwest::header::ACCEPT_ENCODING, "identity");
+        if let Ok(resp) = request.send().await {
+            if resp.status().is_success() {
+                entry.size = resp.content_length();
+            }
+        }
+    }
+    let total_size = files.iter().filter_map(|f| f.size).sum();
+    Ok(EffectiveModList { files, total_size })
+}
+
+/// HTTP validators remembered for a downloaded pack file
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ManagedFile {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// Pack file hash the file was installed with, used to skip unchanged files on update
+    #[serde(default)]
+    pub hash: Option<String>,
+}
+
+/// Files installed from the pack file, keyed by "<dir>/<filename>"
+type ManagedFiles = HashMap<String, ManagedFile>;
+
+/// Difference between the pack file and what is installed, by `<dir>/<filename>`
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PackDiff {
+    pub added: Vec<String>,
+    pub changed: Vec<String>,
+    pub removed: Vec<String>,
+    pub unchanged: usize,
+}
+
+/// Whether a file is installed with the same hash the pack file now lists
+fn is_unchanged(entry: &ModFileEntry, managed: &ManagedFiles, instance_dir: &Path) -> bool {
+    let installed_hash = managed.get(&managed_file_key(entry)).and_then(|m| m.hash.as_ref());
+    match (installed_hash, &entry.hash) {
+        (Some(installed), Some(expected)) => {
+            installed.eq_ignore_ascii_case(expected)
+                && instance_dir.join(&entry.dir).join(&entry.filename).exists()
+        }
+        _ => false,
+    }
+}
+
+/// Compare the pack file against the managed-files manifest. Files without a
+/// hash count as changed; their conditional GET decides whether to re-download.
+fn diff_pack_files(files: &[ModFileEntry], managed: &ManagedFiles, instance_dir: &Path) -> PackDiff {
+    let mut diff = PackDiff::default();
+    for entry in files {
+        let key = managed_file_key(entry);
+        if is_unchanged(entry, managed, instance_dir) {
+            diff.unchanged += 1;
+        } else if managed.contains_key(&key) {
+            diff.changed.push(key);
+        } else {
+            diff.added.push(key);
+        }
+    }
+    let listed: std::collections::HashSet<String> = files.iter().map(managed_file_key).collect();
+    diff.removed = managed.keys().filter(|key| !listed.contains(*key)).cloned().collect();
+    diff.removed.sort();
+    diff
+}
+
+/// `relative` under `dir`, or None when it is absolute or climbs out with `..`
+fn contained_path(dir: &Path, relative: &Path) -> Option<PathBuf> {
+    relative
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+        .then(|| dir.join(relative))
+}
+
+/// Path of a managed file, refusing keys that point outside the instance
+fn managed_file_path(instance_dir: &Path, key: &str) -> Option<PathBuf> {
+    // Files in the instance root have an empty dir, so their key starts with '/'
+    let path = contained_path(instance_dir, Path::new(key.trim_start_matches('/')))?;
+    // Follow symlinks too, as delete_screenshot does
+    if path.exists() {
+        let resolved = path.canonicalize().ok()?;
+        let root = instance_dir.canonicalize().ok()?;
+        if !resolved.starts_with(&root) {
+            return None;
+        }
+    }
+    Some(path)
+}
+
+/// Delete files the pack no longer lists, including disabled optional files,
+/// and forget them
+fn remove_dropped_files(removed: &[String], managed: &mut ManagedFiles, instance_dir: &Path) -> Result<(), String> {
+    for key in removed {
+        managed.remove(key);
+        let Some(path) = managed_file_path(instance_dir, key) else {
+            println!("Warning: Not removing {}: it is outside the instance directory", key);
+            continue;
+        };
+        let mut disabled = path.clone().into_os_string();
+        disabled.push(".disabled");
+        for path in [path, PathBuf::from(disabled)] {
+            if path.exists() {
+                fs::remove_file(&path)
+                    .map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn managed_files_path(instance_dir: &Path) -> PathBuf {
+    instance_dir.join("managed-files.json")
+}
+
+fn load_managed_files(instance_dir: &Path) -> ManagedFiles {
+    fs::read_to_string(managed_files_path(instance_dir))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_managed_files(instance_dir: &Path, files: &ManagedFiles) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(files)
+        .map_err(|e| format!("Failed to serialize managed files: {}", e))?;
+    fs::write(managed_files_path(instance_dir), content)
+        .map_err(|e| format!("Failed to write managed files: {}", e))
+}
+
+/// The pack definition an instance was last installed from
+fn installed_pack_path(instance_dir: &Path) -> PathBuf {
+    instance_dir.join("installed-pack.json")
+}
+
+fn load_installed_pack(instance_dir: &Path) -> Option<OnlineModpack> {
+    fs::read_to_string(installed_pack_path(instance_dir))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+}
+
+fn save_installed_pack(instance_dir: &Path, modpack: &OnlineModpack) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(modpack)
+        .map_err(|e| format!("Failed to serialize installed pack: {}", e))?;
+    fs::write(installed_pack_path(instance_dir), content)
+        .map_err(|e| format!("Failed to write installed pack: {}", e))
+}
+
+fn header_string(resp: &reqwest::Response, name: reqwest::header::HeaderName) -> Option<String> {
+    resp.headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string())
+}
+
+/// Hex digest of `bytes` in the given format (`sha1`, `sha256` or `md5`)
+pub(crate) fn hex_digest(format: &str, bytes: &[u8]) -> Option<String> {
+    use sha2::Digest;
+    let digest = match format {
+        "sha1" => sha1::Sha1::digest(bytes).to_vec(),
+        "sha256" => sha2::Sha256::digest(bytes).to_vec(),
+        "md5" => md5::Md5::digest(bytes).to_vec(),
+        _ => return None,
+    };
+    Some(digest.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// The hash format of an entry, inferred from the hash length when `hashformat` is absent
+fn entry_hash_format(entry: &ModFileEntry) -> Option<String> {
+    if let Some(format) = &entry.hashformat {
+        return Some(format.to_lowercase());
+    }
+    let hash = entry.hash.as_ref()?;
+    if !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    match hash.len() {
+        40 => Some("sha1".to_string()),
+        64 => Some("sha256".to_string()),
+        32 => Some("md5".to_string()),
+        _ => None,
+    }
+}
+
+/// Check downloaded bytes against the entry's hash. Entries without a hash, or with
+/// one whose format cannot be determined, are accepted unverified.
+fn verify_entry_hash(entry: &ModFileEntry, bytes: &[u8]) -> Result<(), String> {
+    let Some(expected) = &entry.hash else {
+        return Ok(());
+    };
+    let Some(format) = entry_hash_format(entry) else {
+        println!("Warning: cannot determine hash format for {}, skipping verification", entry.filename);
+        return Ok(());
+    };
+    let Some(actual) = hex_digest(&format, bytes) else {
+        println!("Warning: unsupported hash format '{}' for {}, skipping verification", format, entry.filename);
+        return Ok(());
+    };
+    if !actual.eq_ignore_ascii_case(expected) {
+        return Err(format!(
+            "{} hash mismatch for {}: expected {}, got {}",
+            format, entry.filename, expected, actual
+        ));
+    }
+    Ok(())
+}
+
+fn managed_file_key(entry: &ModFileEntry) -> String {
+    format!("{}/{}", entry.dir, entry.filename)
+}
+
+/// Reject pack entries that would be written outside the instance
+fn check_entry_path(entry: &ModFileEntry, instance_dir: &Path) -> Result<(), String> {
+    contained_path(instance_dir, &Path::new(&entry.dir).join(&entry.filename))
+        .map(|_| ())
+        .ok_or(format!("Pack file {} points outside the instance directory", managed_file_key(entry)))
+}
+
+/// Download and verify a pack file, returning the bytes transferred. Entries
+/// without a hash are fetched with a conditional GET using the ETag/Last-Modified
+/// stored in `managed`, so an unchanged file is skipped and counts as 0 bytes.
+async fn download_and_verify(entry: &ModFileEntry, dest_dir: &Path, managed: &mut ManagedFiles) -> Result<u64, String> {
+    check_entry_path(entry, dest_dir)?;
+    let key = managed_file_key(entry);
+    let existing_path = dest_dir.join(&entry.dir).join(&entry.filename);
+    let mut request = http::client().get(&entry.url);
+    if entry.hash.is_none() && existing_path.exists() {
+        if let Some(validators) = managed.get(&key) {
+            if let Some(etag) = &validators.etag {
+                request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &validators.last_modified {
+                request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+    }
+    let resp = request.send()
         .await
         .map_err(|e| format!("Failed to download {}: {}", entry.filename, e))?;
+    if resp.status() == reqwest::StatusCode::NOT_MODIFIED {
+        println!("{} is unchanged, skipping download", key);
+        return Ok(0);
+    }
+    if !resp.status().is_success() {
+        return Err(format!("HTTP {} while downloading {}", resp.status(), entry.filename));
+    }
+    let validators = ManagedFile {
+        etag: header_string(&resp, reqwest::header::ETAG),
+        last_modified: header_string(&resp, reqwest::header::LAST_MODIFIED),
+        hash: entry.hash.clone(),
+    };
     let bytes = resp.bytes()
         .await
         .map_err(|e| format!("Failed to read bytes for {}: {}", entry.filename, e))?;
+    verify_entry_hash(entry, &bytes)?;
     let target_dir = dest_dir.join(&entry.dir);
     fs::create_dir_all(&target_dir).map_err(|e| format!("Failed to create dir {}: {}", target_dir.display(), e))?;
     let file_path = target_dir.join(&entry.filename);
     let mut file = fs::File::create(&file_path).map_err(|e| format!("Failed to create file {}: {}", file_path.display(), e))?;
     file.write_all(&bytes).map_err(|e| format!("Failed to write file {}: {}", file_path.display(), e))?;
-    // TODO: Verify hash if provided
+    managed.insert(key, validators);
+    Ok(bytes.len() as u64)
+}
+
+/// Whether an optional file should be installed given the player's selection
+/// (a list of filenames), or its `default_enabled` flag when nothing was chosen
+fn is_optional_enabled(entry: &ModFileEntry, enabled_optional: &Option<Vec<String>>) -> bool {
+    match enabled_optional {
+        Some(selected) => selected.contains(&entry.filename),
+        None => entry.default_enabled,
+    }
+}
+
+/// Keep a deselected optional file around as `<name>.disabled` so it can be re-enabled
+fn disable_optional_file(entry: &ModFileEntry, dest_dir: &Path) -> Result<(), String> {
+    let path = dest_dir.join(&entry.dir).join(&entry.filename);
+    if path.exists() {
+        let disabled = dest_dir.join(&entry.dir).join(format!("{}.disabled", entry.filename));
+        fs::rename(&path, &disabled)
+            .map_err(|e| format!("Failed to disable {}: {}", entry.filename, e))?;
+    }
     Ok(())
 }
 
-/// Get the latest modloader version if not specified
-async fn get_latest_modloader_version(modloader: &str, mc_version: &str) -> Result<String, String> {
+/// Restore a previously disabled optional file before it is synced
+fn enable_optional_file(entry: &ModFileEntry, dest_dir: &Path) -> Result<(), String> {
+    let path = dest_dir.join(&entry.dir).join(&entry.filename);
+    let disabled = dest_dir.join(&entry.dir).join(format!("{}.disabled", entry.filename));
+    if disabled.exists() && !path.exists() {
+        fs::rename(&disabled, &path)
+            .map_err(|e| format!("Failed to enable {}: {}", entry.filename, e))?;
+    }
+    Ok(())
+}
+
+/// List the optional files of a pack so the UI can offer them as checkboxes
+#[command]
+pub async fn get_optional_mods(modpack: OnlineModpack, user_groups: Vec<String>) -> Result<Vec<ModFileEntry>, String> {
+    let files = fetch_effective_files(&modpack.url, &user_groups).await?;
+    Ok(files.into_iter().filter(|f| f.optional).collect())
+}
+
+/// Maximum size accepted for a modpack icon (2 MiB)
+const MAX_ICON_SIZE: u64 = 2 * 1024 * 1024;
+
+/// Get the directory where modpack icons are cached
+fn get_icon_cache_dir() -> PathBuf {
+    crate::paths::cache_dir()
+        .or_else(crate::paths::config_dir)
+        .expect("Failed to get cache directory")
+        .join("wise0wl-launcher")
+        .join("icons")
+}
+
+/// Find a previously cached icon for a modpack, whatever its extension
+fn find_cached_icon(modpack_id: &str) -> Option<PathBuf> {
+    let entries = fs::read_dir(get_icon_cache_dir()).ok()?;
+    entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .find(|p| p.is_file() && p.file_stem().is_some_and(|s| s == modpack_id))
+}
+
+/// Map an image content type to the file extension used in the cache
+fn icon_extension(content_type: &str) -> Option<&'static str> {
+    let mime = content_type.split(';').next().unwrap_or("").trim().to_lowercase();
+    match mime.as_str() {
+        "image/png" => Some("png"),
+        "image/jpeg" | "image/jpg" => Some("jpg"),
+        "image/gif" => Some("gif"),
+        "image/webp" => Some("webp"),
+        "image/svg+xml" => Some("svg"),
+        "image/x-icon" | "image/vnd.microsoft.icon" => Some("ico"),
+        _ => None,
+    }
+}
+
+async fn download_icon(modpack_id: &str, url: &str) -> Result<PathBuf, String> {
+    let resp = http::client().get(url).send()
+        .await
+        .map_err(|e| format!("Failed to download icon: {}", e))?;
+    if !resp.status().is_success() {
+        return Err(format!("HTTP {} while downloading icon", resp.status()));
+    }
+    let content_type = resp.headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+    let extension = icon_extension(&content_type)
+        .ok_or(format!("Icon has unsupported content type '{}'", content_type))?;
+    if resp.content_length().is_some_and(|len| len > MAX_ICON_SIZE) {
+        return Err(format!("Icon exceeds the {} byte size limit", MAX_ICON_SIZE));
+    }
+    let bytes = resp.bytes()
+        .await
+        .map_err(|e| format!("Failed to read icon bytes: {}", e))?;
+    if bytes.len() as u64 > MAX_ICON_SIZE {
+        return Err(format!("Icon exceeds the {} byte size limit", MAX_ICON_SIZE));
+    }
+
+    let cache_dir = get_icon_cache_dir();
+    fs::create_dir_all(&cache_dir).map_err(|e| format!("Failed to create icon cache dir: {}", e))?;
+    // Drop any stale icon stored under a different extension
+    if let Some(old) = find_cached_icon(modpack_id) {
+        let _ = fs::remove_file(old);
+    }
+    let icon_path = cache_dir.join(format!("{}.{}", modpack_id, extension));
+    fs::write(&icon_path, &bytes).map_err(|e| format!("Failed to write icon {}: {}", icon_path.display(), e))?;
+    Ok(icon_path)
+}
+
+/// Download a modpack's icon into the local cache and return its path.
+/// Falls back to the cached copy when the icon can't be fetched (e.g. offline).
+#[command]
+pub async fn cache_modpack_icon(modpack_id: String, url: String) -> Result<PathBuf, String> {
+    if modpack_id.is_empty() || modpack_id.contains(['/', '\\']) || modpack_id.contains("..") {
+        return Err(format!("Invalid modpack id '{}'", modpack_id));
+    }
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err(format!("Icon URL '{}' is not a remote URL", url));
+    }
+    match download_icon(&modpack_id, &url).await {
+        Ok(path) => Ok(path),
+        Err(e) => match find_cached_icon(&modpack_id) {
+            Some(path) => {
+                println!("Using cached icon for {}: {}", modpack_id, e);
+                Ok(path)
+            }
+            None => Err(e),
+        },
+    }
+}
+
+/// NeoForge versions drop the leading "1." of the Minecraft version,
+/// so MC 1.20.4 maps to "20.4.x" and MC 1.21 to "21.0.x"
+fn neoforge_version_prefix(mc_version: &str) -> String {
+    let trimmed = mc_version.strip_prefix("1.").unwrap_or(mc_version);
+    if trimmed.contains('.') {
+        format!("{}.", trimmed)
+    } else {
+        format!("{}.0.", trimmed)
+    }
+}
+
+/// Extract every `<version>` entry from a Maven `maven-metadata.xml`
+fn parse_maven_versions(xml: &str) -> Vec<String> {
+    let mut versions = Vec::new();
+    for line in xml.lines() {
+        if let Some(start) = line.find("<version>") {
+            if let Some(end) = line.find("</version>") {
+                versions.push(line[start + 9..end].to_string());
+            }
+        }
+    }
+    versions
+}
+
+/// Keep the NeoForge versions built for `mc_version`
+fn neoforge_versions_for(versions: &[String], mc_version: &str) -> Vec<String> {
+    let prefix = neoforge_version_prefix(mc_version);
+    versions
+        .iter()
+        .filter(|v| v.starts_with(&prefix) || v.starts_with(mc_version))
+        .cloned()
+        .collect()
+}
+
+async fn fetch_maven_versions(url: &str, name: &str) -> Result<Vec<String>, String> {
+    let resp = http::client().get(url).send().await.map_err(|e| format!("Failed to fetch {} maven metadata: {}", name, e))?;
+    let xml = resp.text().await.map_err(|e| format!("Failed to read {} maven metadata: {}", name, e))?;
+    Ok(parse_maven_versions(&xml))
+}
+
+/// Loader versions from a Fabric-style meta API (Fabric and Quilt share the format)
+async fn fetch_loader_meta_versions(url: &str, name: &str, include_unstable: bool) -> Result<Vec<String>, String> {
+    let resp = http::client().get(url).send().await.map_err(|e| format!("Failed to fetch {} loader meta: {}", name, e))?;
+    let arr: serde_json::Value = resp.json().await.map_err(|e| format!("Failed to parse {} loader meta: {}", name, e))?;
+    let loaders = arr.as_array().ok_or(format!("Invalid {} loader meta format", name))?;
+    Ok(loaders.iter()
+        .filter(|v| include_unstable || v["loader"]["stable"].as_bool().unwrap_or(true))
+        .filter_map(|v| v["loader"]["version"].as_str())
+        .map(|v| v.to_string())
+        .collect())
+}
+
+/// The Fabric installer to use: the pinned version, or the latest stable release
+pub(crate) async fn fabric_installer_version(pinned: Option<&str>) -> Result<String, String> {
+    if let Some(version) = pinned {
+        return Ok(version.to_string());
+    }
+    // Fabric meta API: https://meta.fabricmc.net/v2/versions/installer (newest first)
+    let url = "https://meta.fabricmc.net/v2/versions/installer";
+    let resp = http::client().get(url).send().await.map_err(|e| format!("Failed to fetch Fabric installer versions: {}", e))?;
+    let arr: serde_json::Value = resp.json().await.map_err(|e| format!("Failed to parse Fabric installer versions: {}", e))?;
+    let installers = arr.as_array().ok_or("Invalid Fabric installer meta format".to_string())?;
+    installers.iter()
+        .find(|v| v["stable"].as_bool().unwrap_or(false))
+        .or_else(|| installers.first())
+        .and_then(|v| v["version"].as_str())
+        .map(|v| v.to_string())
+        .ok_or("No Fabric installer version found".to_string())
+}
+
+/// List every available version of a modloader for a Minecraft version, newest first
+#[command]
+pub async fn list_modloader_versions(modloader: String, mc_version: String) -> Result<Vec<String>, String> {
+    let mut versions = match modloader.to_lowercase().as_str() {
+        "fabric" => {
+            let url = format!("https://meta.fabricmc.net/v2/versions/loader/{}", mc_version);
+            fetch_loader_meta_versions(&url, "Fabric", true).await?
+        },
+        "quilt" => {
+            // Quilt meta API: https://meta.quiltmc.org/v3/versions/loader/{mc_version}
+            let url = format!("https://meta.quiltmc.org/v3/versions/loader/{}", mc_version);
+            fetch_loader_meta_versions(&url, "Quilt", true).await?
+        },
+        "forge" => {
+            // Forge maven versions look like "{mc_version}-{forge_version}"
+            let url = "https://maven.minecraftforge.net/net/minecraftforge/forge/maven-metadata.xml";
+            let prefix = format!("{}-", mc_version);
+            fetch_maven_versions(url, "Forge").await?
+                .iter()
+                .filter_map(|v| v.strip_prefix(&prefix))
+                .map(|v| v.to_string())
+                .collect()
+        },
+        "neoforge" => {
+            let url = "https://maven.neoforged.net/releases/net/neoforged/neoforge/maven-metadata.xml";
+            neoforge_versions_for(&fetch_maven_versions(url, "NeoForge").await?, &mc_version)
+        },
+        _ => return Err(format!("Version listing not implemented for modloader '{}'", modloader)),
+    };
+    versions.sort_by(|a, b| version::compare_versions(b, a));
+    versions.dedup();
+    Ok(versions)
+}
+
+/// Get the latest modloader version if not specified.
+/// Pre-release (beta/rc) versions are skipped unless `allow_prerelease` is set,
+/// and for Forge `prefer_recommended` picks the recommended promotion over the latest one.
+async fn get_latest_modloader_version(
+    modloader: &str,
+    mc_version: &str,
+    allow_prerelease: bool,
+    prefer_recommended: bool,
+) -> Result<ModloaderSelection, String> {
     match modloader.to_lowercase().as_str() {
         "fabric" => {
             // Fabric meta API: https://meta.fabricmc.net/v2/versions/loader/{mc_version}
             let url = format!("https://meta.fabricmc.net/v2/versions/loader/{}", mc_version);
-            let resp = reqwest::get(&url).await.map_err(|e| format!("Failed to fetch Fabric loader meta: {}", e))?;
-            let arr: serde_json::Value = resp.json().await.map_err(|e| format!("Failed to parse Fabric loader meta: {}", e))?;
-            let loader_version = arr.as_array()
-                .and_then(|a| a.first())
-                .and_then(|v| v["loader"]["version"].as_str())
-                .ok_or("No Fabric loader version found")?;
-            Ok(loader_version.to_string())
+            let candidates = fetch_loader_meta_versions(&url, "Fabric", allow_prerelease).await?;
+            version::select_highest(candidates.iter().map(String::as_str), allow_prerelease)
+                .map(|v| ModloaderSelection::new(&v, "latest"))
+                .ok_or("No Fabric loader version found".to_string())
         },
         "forge" => {
             // Forge meta API: https://files.minecraftforge.net/net/minecraftforge/forge/promotions_slim.json
             let url = "https://files.minecraftforge.net/net/minecraftforge/forge/promotions_slim.json";
-            let resp = reqwest::get(url).await.map_err(|e| format!("Failed to fetch Forge promotions: {}", e))?;
+            let resp = http::client().get(url).send().await.map_err(|e| format!("Failed to fetch Forge promotions: {}", e))?;
             let json: serde_json::Value = resp.json().await.map_err(|e| format!("Failed to parse Forge promotions: {}", e))?;
-            let key = format!("{}.latest", mc_version);
-            let latest = json["promos"][&key].as_str().ok_or("No Forge latest version found for this Minecraft version")?;
-            Ok(latest.to_string())
+            let recommended = json["promos"][format!("{}-recommended", mc_version)].as_str();
+            let latest = json["promos"][format!("{}-latest", mc_version)].as_str();
+            // Not every Minecraft version has a recommended build, so fall back to latest
+            let selection = match (prefer_recommended, recommended, latest) {
+                (true, Some(v), _) => ModloaderSelection::new(v, "recommended"),
+                (_, _, Some(v)) => ModloaderSelection::new(v, "latest"),
+                (false, Some(v), None) => ModloaderSelection::new(v, "recommended"),
+                _ => return Err("No Forge version found for this Minecraft version".to_string()),
+            };
+            println!("Selected Forge {} ({} promotion)", selection.version, selection.source);
+            Ok(selection)
         },
         "neoforge" => {
             // NeoForge maven metadata: https://maven.neoforged.net/releases/net/neoforged/neoforge/maven-metadata.xml
-            // We'll parse the XML to get the latest version for the given mc_version
+            // We'll parse the XML to get the highest version for the given mc_version
             let url = "https://maven.neoforged.net/releases/net/neoforged/neoforge/maven-metadata.xml";
-            let resp = reqwest::get(url).await.map_err(|e| format!("Failed to fetch NeoForge maven metadata: {}", e))?;
-            let xml = resp.text().await.map_err(|e| format!("Failed to read NeoForge maven metadata: {}", e))?;
-            // Find the latest version for the given mc_version prefix
-            let mut latest: Option<String> = None;
-            for line in xml.lines() {
-                if let Some(start) = line.find("<version>") {
-                    if let Some(end) = line.find("</version>") {
-                        let version = &line[start + 9..end];
-                        if version.starts_with(mc_version) {
-                            latest = Some(version.to_string());
-                        }
-                    }
-                }
-            }
-            latest.ok_or("No NeoForge latest version found for this Minecraft version".to_string())
+            let candidates = neoforge_versions_for(&fetch_maven_versions(url, "NeoForge").await?, mc_version);
+            version::select_highest(candidates.iter().map(String::as_str), allow_prerelease)
+                .map(|v| ModloaderSelection::new(&v, "latest"))
+                .ok_or("No NeoForge version found for this Minecraft version".to_string())
         },
         _ => Err("Latest version lookup not implemented for this modloader".to_string()),
     }
@@ -137,87 +1296,540 @@ async fn get_latest_modloader_version(modloader: &str, mc_version: &str) -> Resu
 /// Main function to download and set up a modpack instance with group scoping
 #[command]
 pub async fn download_modpack_with_groups(
+    app: AppHandle,
     modpack: OnlineModpack,
-    user_groups: Vec<String>
-) -> Result<(), String> {
+    user_groups: Vec<String>,
+    enabled_optional: Option<Vec<String>>,
+) -> Result<ModloaderSelection, String> {
+    set_install_phase(&modpack.id, InstallPhase::Preparing);
+    let _progress_guard = InstallProgressGuard(&modpack.id);
+
+    // Fetched once here and reused by the install steps below
+    let version_json = mojang::fetch_version_json(&modpack.minecraft_version).await?;
+    let files = fetch_effective_files(&modpack.url, &user_groups).await?;
+
+    // Warn up front rather than leaving the player with IO errors halfway through
+    match disk_check_for(&modpack, &version_json, &files) {
+        Ok(disk_check) => {
+            if let Some(warning) = &disk_check.warning {
+                println!("Warning: {}", warning);
+                let _ = app.emit("disk_space_warning", disk_check);
+            }
+        }
+        Err(e) => println!("Warning: Could not check free disk space: {}", e),
+    }
+
     let instance_dir = get_instance_dir(&modpack.id);
     fs::create_dir_all(&instance_dir).map_err(|e| format!("Failed to create instance dir: {}", e))?;
+    let first_install = load_installed_pack(&instance_dir).is_none();
 
     // Step 1: Determine modloader version
-    let modloader_version = if modpack.modloader_version.is_empty() {
-        get_latest_modloader_version(&modpack.modloader, &modpack.minecraft_version).await?
+    let selection = if modpack.modloader_version.is_empty() {
+        let prefer_recommended = crate::settings::load_settings()
+            .map(|s| s.prefer_recommended_forge)
+            .unwrap_or(true);
+        get_latest_modloader_version(
+            &modpack.modloader,
+            &modpack.minecraft_version,
+            modpack.allow_prerelease,
+            prefer_recommended,
+        ).await?
     } else {
-        modpack.modloader_version.clone()
+        ModloaderSelection::new(&modpack.modloader_version, "specified")
     };
+    let modloader_version = selection.version.clone();
 
     // Step 2: Download Minecraft
-    download_minecraft(&modpack.minecraft_version, &instance_dir).await?;
+    set_install_phase(&modpack.id, InstallPhase::Minecraft);
+    download_minecraft(&modpack.minecraft_version, &version_json, &instance_dir).await?;
 
     // Step 3: Install mod loader
+    set_install_phase(&modpack.id, InstallPhase::Modloader);
+    let installer_version = modpack.installer_version.as_deref();
     match modpack.modloader.to_lowercase().as_str() {
-        "forge" => install_forge(&modloader_version, &modpack.minecraft_version, &instance_dir).await?,
-        "fabric" => install_fabric(&modloader_version, &modpack.minecraft_version, &instance_dir).await?,
-        "neoforge" => install_neoforge(&modloader_version, &modpack.minecraft_version, &instance_dir).await?,
+        "forge" => install_forge(&modloader_version, &modpack.minecraft_version, installer_version, &instance_dir).await?,
+        "fabric" => install_fabric(&modloader_version, &modpack.minecraft_version, installer_version, &instance_dir).await?,
+        "neoforge" => install_neoforge(&modloader_version, &modpack.minecraft_version, installer_version, &instance_dir).await?,
         _ => return Err("Unknown modloader".to_string()),
     }
 
-    // Step 4: Fetch and filter modpack file
-    let files = fetch_modpack_file(&modpack.url, &user_groups).await?;
-    for entry in files.iter() {
-        download_and_verify(entry, &instance_dir).await?;
+    // Step 4: Sync the filtered modpack files
+    set_install_phase(&modpack.id, InstallPhase::Files);
+    for entry in &files {
+        check_entry_path(entry, &instance_dir)?;
     }
+    let mut managed = load_managed_files(&instance_dir);
+    let diff = diff_pack_files(&files, &managed, &instance_dir);
+    println!(
+        "Pack diff for {}: {} added, {} changed, {} removed, {} unchanged",
+        modpack.id, diff.added.len(), diff.changed.len(), diff.removed.len(), diff.unchanged
+    );
+    remove_dropped_files(&diff.removed, &mut managed, &instance_dir)?;
+    let _ = app.emit("install_diff", diff);
+    let mut stats = DownloadStats::new();
+    for (index, entry) in files.iter().enumerate() {
+        if entry.optional {
+            if !is_optional_enabled(entry, &enabled_optional) {
+                disable_optional_file(entry, &instance_dir)?;
+                continue;
+            }
+            enable_optional_file(entry, &instance_dir)?;
+        }
+        let installed = instance_dir.join(&entry.dir).join(&entry.filename).exists();
+        let result = if is_unchanged(entry, &managed, &instance_dir) || (installed && !entry.overwrites()) {
+            // First-install-only files (shipped configs) keep the player's edits
+            Ok(0)
+        } else {
+            download_and_verify(entry, &instance_dir, &mut managed).await
+        };
+        match result {
+            Ok(bytes) => stats.record(bytes),
+            Err(e) => {
+                // Keep the validators gathered so far so the next sync can skip them
+                let _ = save_managed_files(&instance_dir, &managed);
+                return Err(e);
+            }
+        }
+        set_install_progress(&modpack.id, ProgressSnapshot {
+            phase: InstallPhase::Files,
+            bytes_downloaded: stats.total_bytes,
+            percent: (index + 1) as f64 * 100.0 / files.len() as f64,
+            current_file: Some(entry.filename.clone()),
+        });
+        let _ = app.emit("install_progress", InstallProgress {
+            file: entry.filename.clone(),
+            completed: index + 1,
+            total: files.len(),
+            bytes_downloaded: stats.total_bytes,
+            bytes_per_sec: stats.rolling_speed(),
+        });
+    }
+    save_managed_files(&instance_dir, &managed)?;
 
-    Ok(())
+    let summary = stats.summary();
+    println!(
+        "Downloaded {} bytes in {:.1}s ({:.0} B/s)",
+        summary.total_bytes, summary.elapsed, summary.avg_speed
+    );
+    let _ = app.emit("install_summary", summary);
+
+    set_install_phase(&modpack.id, InstallPhase::Finishing);
+
+    // Step 5: Add the pack's server list alongside any servers the player saved
+    let added = servers::merge_servers(&instance_dir, &modpack.servers)?;
+    if added > 0 {
+        println!("Added {} servers to {}", added, modpack.id);
+    }
+
+    // Step 6: Seed the pack's default options, leaving anything the player already set
+    if first_install && !modpack.default_options.is_empty() {
+        crate::instance::preset_options(&instance_dir, &modpack.default_options)?;
+    }
+
+    // Remember what was installed so later updates can be classified
+    save_installed_pack(&instance_dir, &modpack)?;
+
+    Ok(selection)
+}
+
+/// How heavy an update to an installed pack is
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum UpdateKind {
+    /// Only the mods list changed; the existing install can be synced
+    MinorModsOnly,
+    /// The modloader or its version changed and must be reinstalled
+    ModloaderChange,
+    /// The Minecraft version changed, requiring a full reinstall
+    MinecraftChange,
+}
+
+/// Compare the installed pack with its new remote definition. Instances installed
+/// before install records were kept are treated as needing a full reinstall.
+#[command]
+pub async fn classify_update(modpack_id: String, new_version: OnlineModpack) -> Result<UpdateKind, String> {
+    let installed = match load_installed_pack(&get_instance_dir(&modpack_id)) {
+        Some(installed) => installed,
+        None => return Ok(UpdateKind::MinecraftChange),
+    };
+    let kind = update_kind(&installed, &new_version);
+    println!("Update of {} from {} to {}: {:?}", modpack_id, installed.version, new_version.version, kind);
+    Ok(kind)
+}
+
+/// Decide how heavy moving from the installed pack to its new definition is
+fn update_kind(installed: &OnlineModpack, new_version: &OnlineModpack) -> UpdateKind {
+    if installed.minecraft_version != new_version.minecraft_version {
+        UpdateKind::MinecraftChange
+    } else if !installed.modloader.eq_ignore_ascii_case(&new_version.modloader)
+        || installed.modloader_version != new_version.modloader_version
+    {
+        UpdateKind::ModloaderChange
+    } else {
+        UpdateKind::MinorModsOnly
+    }
+}
+
+/// Estimate the bytes still needed to install a modpack (client jar, libraries,
+/// assets and pack files) from the sizes declared in the version JSON and pack
+/// file. Anything already in the instance with a matching hash is left out, so
+/// updating an installed pack only counts what will actually be downloaded.
+fn estimate_install_size(version_json: &serde_json::Value, files: &[ModFileEntry], instance_dir: &Path) -> u64 {
+    let version = version_json["id"].as_str().unwrap_or_default();
+    let mut total = 0;
+    let client = &version_json["downloads"]["client"];
+    let client_jar = instance_dir.join("versions").join(version).join(format!("{}.jar", version));
+    if !client["sha1"].as_str().is_some_and(|sha1| file_sha1_matches(&client_jar, sha1)) {
+        total += client["size"].as_u64().unwrap_or(0);
+    }
+    // Assets are shared between instances; an existing index means they were fetched before
+    let assets_dir = crate::settings::assets_directory(&get_minecraft_dir());
+    let asset_index_id = version_json["assetIndex"]["id"].as_str().unwrap_or_default();
+    if !assets_dir.join("indexes").join(format!("{}.json", asset_index_id)).exists() {
+        total += version_json["assetIndex"]["totalSize"].as_u64().unwrap_or(0);
+    }
+    if let Some(libraries) = version_json["libraries"].as_array() {
+        for library in libraries {
+            let artifact = &library["downloads"]["artifact"];
+            let present = match (artifact["path"].as_str(), artifact["sha1"].as_str()) {
+                (Some(path), Some(sha1)) => file_sha1_matches(&instance_dir.join("libraries").join(path), sha1),
+                _ => false,
+            };
+            if !present {
+                total += artifact["size"].as_u64().unwrap_or(0);
+            }
+        }
+    }
+    let managed = load_managed_files(instance_dir);
+    total += files
+        .iter()
+        .filter(|f| !is_unchanged(f, &managed, instance_dir))
+        .filter_map(|f| f.size)
+        .sum::<u64>();
+    total
+}
+
+/// Free space on the drive holding `path`, using its nearest existing ancestor
+fn available_space_for(path: &Path) -> Result<u64, String> {
+    let existing = path.ancestors()
+        .find(|p| p.exists())
+        .ok_or(format!("No existing parent directory for {}", path.display()))?;
+    fs2::available_space(existing)
+        .map_err(|e| format!("Failed to query free space for {}: {}", existing.display(), e))
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DirCheck {
+    pub path: PathBuf,
+    pub writable: bool,
+    pub available: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// Check that a directory could be created and written to without creating it:
+/// a probe file is written to the directory, or to its nearest existing ancestor
+/// when the directory doesn't exist yet
+pub(crate) fn check_game_directory(path: &Path) -> DirCheck {
+    let result = match path.ancestors().find(|p| p.exists()) {
+        Some(existing) if existing.is_dir() => {
+            let probe = existing.join(format!(".wise0wl-write-test-{}", std::process::id()));
+            fs::write(&probe, b"ok")
+                .and_then(|_| fs::remove_file(&probe))
+                .map_err(|e| format!("{} is not writable: {}", path.display(), e))
+        }
+        Some(existing) => Err(format!("{} is not writable: {} is not a directory", path.display(), existing.display())),
+        None => Err(format!("{} is not writable: no existing parent directory", path.display())),
+    };
+    DirCheck {
+        path: path.to_path_buf(),
+        writable: result.is_ok(),
+        available: available_space_for(path).ok(),
+        error: result.err(),
+    }
+}
+
+/// Report whether a game directory is writable and how much space is free there
+#[command]
+pub async fn validate_game_directory(path: PathBuf) -> Result<DirCheck, String> {
+    Ok(check_game_directory(&path))
+}
+
+/// Compare the estimated install size of a modpack with the free space on its target drive
+fn disk_check_for(modpack: &OnlineModpack, version_json: &serde_json::Value, files: &[ModFileEntry]) -> Result<DiskCheck, String> {
+    let instance_dir = get_instance_dir(&modpack.id);
+    let required_estimate = estimate_install_size(version_json, files, &instance_dir);
+    let available = available_space_for(&instance_dir)?;
+    let sufficient = available >= required_estimate;
+    let warning = if sufficient {
+        None
+    } else {
+        Some(format!(
+            "{} may not fit on this drive: about {} MB needed but only {} MB available",
+            modpack.name,
+            required_estimate / (1024 * 1024),
+            available / (1024 * 1024)
+        ))
+    };
+    Ok(DiskCheck { required_estimate, available, sufficient, warning })
+}
+
+/// Report whether a modpack is likely to fit on its target drive
+#[command]
+pub async fn check_disk_space(modpack: OnlineModpack, user_groups: Vec<String>) -> Result<DiskCheck, String> {
+    let version_json = mojang::fetch_version_json(&modpack.minecraft_version).await?;
+    let files = fetch_effective_files(&modpack.url, &user_groups).await?;
+    disk_check_for(&modpack, &version_json, &files)
 }
 
 /// Get the user's Minecraft directory (e.g., ~/.minecraft or %APPDATA%\.minecraft)
-fn get_minecraft_dir() -> PathBuf {
-    // On Windows, dirs::data_dir() returns %APPDATA%, on Linux ~/.local/share, on macOS ~/Library/Application Support
+pub(crate) fn get_minecraft_dir() -> PathBuf {
+    if let Some(root) = crate::settings::storage_root() {
+        return root;
+    }
+    // On Windows, data_dir() returns %APPDATA%, on Linux ~/.local/share, on macOS ~/Library/Application Support
     // Minecraft uses %APPDATA%\.minecraft on Windows, ~/.minecraft on Linux/macOS
     if cfg!(target_os = "windows") {
-        dirs::data_dir()
+        crate::paths::data_dir()
             .expect("Failed to get data dir")
             .join(".minecraft")
     } else {
-        dirs::home_dir()
+        crate::paths::home_dir()
             .expect("Failed to get home dir")
             .join(".minecraft")
     }
 }
 
+/// Directory holding every modpack instance
+pub(crate) fn get_instances_dir() -> PathBuf {
+    get_minecraft_dir().join("instances")
+}
+
 /// Get the instance directory for a modpack
-fn get_instance_dir(modpack_id: &str) -> PathBuf {
-    get_minecraft_dir().join("instances").join(modpack_id)
+pub(crate) fn get_instance_dir(modpack_id: &str) -> PathBuf {
+    get_instances_dir().join(modpack_id)
 }
 
-/// Download the vanilla Minecraft jar for the given version
-async fn download_minecraft(version: &str, dest_dir: &Path) -> Result<(), String> {
-    // Mojang version manifest URL
-    let manifest_url = "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json";
-    let manifest_resp = reqwest::get(manifest_url)
-        .await
-        .map_err(|e| format!("Failed to fetch version manifest: {}", e))?;
-    let manifest_json: serde_json::Value = manifest_resp.json()
-        .await
-        .map_err(|e| format!("Failed to parse version manifest: {}", e))?;
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RepairReport {
+    pub files_updated: Vec<String>,
+    pub paths_rewritten: usize,
+}
 
-    // Find the version object
-    let versions = manifest_json["versions"].as_array().ok_or("Invalid manifest format")?;
-    let version_obj = versions.iter().find(|v| v["id"] == version)
-        .ok_or(format!("Version {} not found in manifest", version))?;
-    let version_url = version_obj["url"].as_str().ok_or("Missing version URL")?;
+/// If `value` is an absolute path containing the `marker` segments, re-root
+/// everything after the marker onto `new_root`
+fn rebase_path(value: &str, marker: &[&str], new_root: &Path) -> Option<String> {
+    let is_absolute = value.starts_with('/')
+        || value.starts_with('\\')
+        || value.get(1..3).is_some_and(|s| s == ":\\" || s == ":/");
+    if !is_absolute {
+        return None;
+    }
+    let segments: Vec<&str> = value.split(['/', '\\']).collect();
+    let start = segments.windows(marker.len()).rposition(|w| w == marker)?;
+    let mut rebased = new_root.to_path_buf();
+    for segment in &segments[start + marker.len()..] {
+        if !segment.is_empty() {
+            rebased.push(segment);
+        }
+    }
+    if Path::new(value) == rebased.as_path() {
+        None
+    } else {
+        Some(rebased.to_string_lossy().to_string())
+    }
+}
 
-    // Fetch the version details
-    let version_resp = reqwest::get(version_url)
-        .await
-        .map_err(|e| format!("Failed to fetch version details: {}", e))?;
-    let version_json: serde_json::Value = version_resp.json()
-        .await
-        .map_err(|e| format!("Failed to parse version details: {}", e))?;
+/// Rewrite every string in a JSON document that points into a moved directory
+fn rebase_json_paths(value: &mut serde_json::Value, roots: &[(Vec<&str>, PathBuf)]) -> usize {
+    match value {
+        serde_json::Value::String(s) => {
+            for (marker, root) in roots {
+                if let Some(rebased) = rebase_path(s, marker, root) {
+                    *s = rebased;
+                    return 1;
+                }
+            }
+            0
+        }
+        serde_json::Value::Array(items) => items.iter_mut().map(|v| rebase_json_paths(v, roots)).sum(),
+        serde_json::Value::Object(map) => map.values_mut().map(|v| rebase_json_paths(v, roots)).sum(),
+        _ => 0,
+    }
+}
+
+/// Rewrite absolute paths baked into an instance's generated version and profile
+/// JSONs so they point at the current Minecraft and instance directories again
+#[command]
+pub async fn repair_instance_paths(modpack_id: String) -> Result<RepairReport, String> {
+    let instance_dir = get_instance_dir(&modpack_id);
+    if !instance_dir.exists() {
+        return Err(format!("Instance '{}' is not installed", modpack_id));
+    }
+    // The instance marker must come first so it wins over the shared .minecraft one
+    let roots = vec![
+        (vec![".minecraft", "instances", modpack_id.as_str()], instance_dir.clone()),
+        (vec![".minecraft"], get_minecraft_dir()),
+    ];
+
+    let mut json_files = vec![
+        instance_dir.join("launcher_profiles.json"),
+        instance_dir.join("launcher_profiles_microsoft_store.json"),
+    ];
+    if let Ok(versions) = fs::read_dir(instance_dir.join("versions")) {
+        for version_dir in versions.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| p.is_dir()) {
+            if let Ok(files) = fs::read_dir(&version_dir) {
+                json_files.extend(
+                    files.filter_map(|e| e.ok())
+                        .map(|e| e.path())
+                        .filter(|p| p.extension().is_some_and(|ext| ext == "json")),
+                );
+            }
+        }
+    }
+
+    let mut report = RepairReport::default();
+    for path in json_files.iter().filter(|p| p.exists()) {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let mut json: serde_json::Value = match serde_json::from_str(&content) {
+            Ok(json) => json,
+            Err(e) => {
+                println!("Skipping unparsable JSON {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        let rewritten = rebase_json_paths(&mut json, &roots);
+        if rewritten > 0 {
+            let content = serde_json::to_string_pretty(&json)
+                .map_err(|e| format!("Failed to serialize {}: {}", path.display(), e))?;
+            fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+            report.paths_rewritten += rewritten;
+            report.files_updated.push(path.to_string_lossy().to_string());
+        }
+    }
+
+    // Natives and assets are always derived from the current directories
+    fs::create_dir_all(instance_dir.join("natives"))
+        .map_err(|e| format!("Failed to create natives directory: {}", e))?;
+    fs::create_dir_all(crate::settings::assets_directory(&get_minecraft_dir()))
+        .map_err(|e| format!("Failed to create assets directory: {}", e))?;
+
+    println!("Repaired {} paths in {} files for instance {}", report.paths_rewritten, report.files_updated.len(), modpack_id);
+    Ok(report)
+}
+
+/// Extensions of in-progress downloads
+const PARTIAL_EXTENSIONS: &[&str] = &["part"];
+/// Artifacts that are never legitimately empty, so a zero-byte one is a broken download
+const NONEMPTY_EXTENSIONS: &[&str] = &["jar", "zip", "json"];
+
+fn is_partial_download(path: &Path, len: u64) -> bool {
+    let extension = match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => ext.to_lowercase(),
+        None => return false,
+    };
+    PARTIAL_EXTENSIONS.contains(&extension.as_str())
+        || (len == 0 && NONEMPTY_EXTENSIONS.contains(&extension.as_str()))
+}
+
+fn collect_partial_downloads(dir: &Path, found: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        // Don't follow links out of the launcher's directories
+        let metadata = match fs::symlink_metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        if metadata.is_dir() {
+            collect_partial_downloads(&path, found);
+        } else if metadata.is_file() && is_partial_download(&path, metadata.len()) {
+            found.push(path);
+        }
+    }
+}
+
+/// Directories the launcher downloads into
+fn download_dirs() -> Vec<PathBuf> {
+    let minecraft_dir = get_minecraft_dir();
+    let mut dirs = vec![
+        crate::settings::assets_directory(&minecraft_dir),
+        get_icon_cache_dir(),
+        crate::java::runtime_root(),
+    ];
+    // A custom assets directory lives outside .minecraft and needs its own scan
+    dirs.retain(|dir| !dir.starts_with(&minecraft_dir));
+    dirs.insert(0, minecraft_dir);
+    dirs
+}
+
+/// Find `.part` files and zero-byte artifacts left behind by interrupted downloads
+#[command]
+pub async fn find_partial_downloads() -> Result<Vec<PathBuf>, String> {
+    let mut found = Vec::new();
+    for dir in download_dirs() {
+        collect_partial_downloads(&dir, &mut found);
+    }
+    Ok(found)
+}
+
+/// Remove everything `find_partial_downloads` reports, returning the removed paths
+#[command]
+pub async fn clean_partial_downloads() -> Result<Vec<PathBuf>, String> {
+    let mut removed = Vec::new();
+    for path in find_partial_downloads().await? {
+        match fs::remove_file(&path) {
+            Ok(()) => removed.push(path),
+            Err(e) => println!("Warning: Failed to remove {}: {}", path.display(), e),
+        }
+    }
+    println!("Removed {} partial downloads", removed.len());
+    Ok(removed)
+}
+
+/// Temp folders the launch-time modloader installers download into
+const INSTALLER_TEMP_DIRS: &[&str] = &["forge-installer", "fabric-installer", "neoforge-installer"];
+
+fn is_installer_artifact(file_name: &str) -> bool {
+    file_name.contains("installer") && (file_name.ends_with(".jar") || file_name.ends_with(".jar.log"))
+}
+
+/// Remove modloader installer jars (and their logs) left in an instance, plus
+/// the temp installer folders. Returns the paths that were removed.
+#[command]
+pub async fn clean_installer_artifacts(modpack_id: String) -> Result<Vec<PathBuf>, String> {
+    let mut removed = Vec::new();
+    let instance_dir = get_instance_dir(&modpack_id);
+    if let Ok(entries) = fs::read_dir(&instance_dir) {
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if path.is_file() && is_installer_artifact(&entry.file_name().to_string_lossy()) {
+                fs::remove_file(&path)
+                    .map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+                removed.push(path);
+            }
+        }
+    }
+    for name in INSTALLER_TEMP_DIRS {
+        let dir = std::env::temp_dir().join(name);
+        if dir.exists() {
+            fs::remove_dir_all(&dir)
+                .map_err(|e| format!("Failed to remove {}: {}", dir.display(), e))?;
+            removed.push(dir);
+        }
+    }
+    println!("Removed {} installer artifacts for {}", removed.len(), modpack_id);
+    Ok(removed)
+}
+
+/// Download the vanilla Minecraft jar for the given version
+async fn download_minecraft(version: &str, version_json: &serde_json::Value, dest_dir: &Path) -> Result<(), String> {
     let client_url = version_json["downloads"]["client"]["url"].as_str().ok_or("Missing client jar URL")?;
 
     // Download the client jar
-    let client_resp = reqwest::get(client_url)
+    let client_resp = http::client().get(client_url).send()
         .await
         .map_err(|e| format!("Failed to download client jar: {}", e))?;
     let client_bytes = client_resp.bytes()
@@ -235,13 +1847,12 @@ async fn download_minecraft(version: &str, dest_dir: &Path) -> Result<(), String
 }
 
 /// Download and install Forge for the given version
-async fn install_forge(forge_version: &str, mc_version: &str, dest_dir: &Path) -> Result<(), String> {
+async fn install_forge(forge_version: &str, mc_version: &str, installer_version: Option<&str>, dest_dir: &Path) -> Result<(), String> {
     // Forge installer URL pattern (official):
     // https://maven.minecraftforge.net/net/minecraftforge/forge/{mc_version}-{forge_version}/forge-{mc_version}-{forge_version}-installer.jar
-    let installer_url = format!(
-        "https://maven.minecraftforge.net/net/minecraftforge/forge/{mc_version}-{forge_version}/forge-{mc_version}-{forge_version}-installer.jar"
-    );
-    let installer_resp = reqwest::get(&installer_url)
+    let forge_version = version::strip_mc_prefix(mc_version, forge_version);
+    let installer_url = version::forge_installer_url(mc_version, forge_version, installer_version);
+    let installer_resp = http::client().get(&installer_url).send()
         .await
         .map_err(|e| format!("Failed to download Forge installer: {}", e))?;
     let installer_bytes = installer_resp.bytes()
@@ -252,29 +1863,22 @@ async fn install_forge(forge_version: &str, mc_version: &str, dest_dir: &Path) -
     file.write_all(&installer_bytes).map_err(|e| format!("Failed to write Forge installer file: {}", e))?;
 
     // Run the installer with Java
-    let status = Command::new("java")
-        .arg("-jar")
-        .arg(&installer_path)
+    let mut command = installer_command(&installer_path);
+    command
         .arg("--installServer")
-        .current_dir(dest_dir)
-        .status()
-        .map_err(|e| format!("Failed to run Forge installer: {}", e))?;
-    if !status.success() {
-        return Err("Forge installer failed".to_string());
-    }
+        .current_dir(dest_dir);
+    run_installer(command, "Forge")?;
+    // The installer is only needed once
+    let _ = fs::remove_file(&installer_path);
     Ok(())
 }
 
 /// Download and install Fabric for the given version
-async fn install_fabric(fabric_version: &str, mc_version: &str, dest_dir: &Path) -> Result<(), String> {
-    // Fabric installer URL (universal jar): https://maven.fabricmc.net/net/fabricmc/fabric-installer/0.11.2/fabric-installer-0.11.2.jar
-    // We'll use the latest stable installer version (can be parameterized if needed)
-    let fabric_installer_version = "0.11.2";
-    let installer_url = format!(
-        "https://maven.fabricmc.net/net/fabricmc/fabric-installer/{0}/fabric-installer-{0}.jar",
-        fabric_installer_version
-    );
-    let installer_resp = reqwest::get(&installer_url)
+async fn install_fabric(fabric_version: &str, mc_version: &str, installer_version: Option<&str>, dest_dir: &Path) -> Result<(), String> {
+    let fabric_installer_version = fabric_installer_version(installer_version).await?;
+    println!("Using Fabric installer {}", fabric_installer_version);
+    let installer_url = version::fabric_installer_url(&fabric_installer_version);
+    let installer_resp = http::client().get(&installer_url).send()
         .await
         .map_err(|e| format!("Failed to download Fabric installer: {}", e))?;
     let installer_bytes = installer_resp.bytes()
@@ -285,32 +1889,28 @@ async fn install_fabric(fabric_version: &str, mc_version: &str, dest_dir: &Path)
     file.write_all(&installer_bytes).map_err(|e| format!("Failed to write Fabric installer file: {}", e))?;
 
     // Run the installer with Java
-    let status = Command::new("java")
-        .arg("-jar")
-        .arg(&installer_path)
+    let mut command = installer_command(&installer_path);
+    command
         .arg("client")
         .arg("-dir")
         .arg(dest_dir)
         .arg("-mcversion")
         .arg(mc_version)
         .arg("-loader")
-        .arg(fabric_version)
-        .status()
-        .map_err(|e| format!("Failed to run Fabric installer: {}", e))?;
-    if !status.success() {
-        return Err("Fabric installer failed".to_string());
-    }
+        .arg(fabric_version);
+    run_installer(command, "Fabric")?;
+    // The installer is only needed once
+    let _ = fs::remove_file(&installer_path);
     Ok(())
 }
 
 /// Download and install NeoForge for the given version
-async fn install_neoforge(neoforge_version: &str, mc_version: &str, dest_dir: &Path) -> Result<(), String> {
+async fn install_neoforge(neoforge_version: &str, mc_version: &str, installer_version: Option<&str>, dest_dir: &Path) -> Result<(), String> {
     // NeoForge installer URL pattern:
-    // https://maven.neoforged.net/releases/net/neoforged/neoforge/{mc_version}-{neoforge_version}/neoforge-{mc_version}-{neoforge_version}-installer.jar
-    let installer_url = format!(
-        "https://maven.neoforged.net/releases/net/neoforged/neoforge/{mc_version}-{neoforge_version}/neoforge-{mc_version}-{neoforge_version}-installer.jar"
-    );
-    let installer_resp = reqwest::get(&installer_url)
+    // https://maven.neoforged.net/releases/net/neoforged/neoforge/{neoforge_version}/neoforge-{neoforge_version}-installer.jar
+    let neoforge_version = version::strip_mc_prefix(mc_version, neoforge_version);
+    let installer_url = version::neoforge_installer_url(mc_version, neoforge_version, installer_version);
+    let installer_resp = http::client().get(&installer_url).send()
         .await
         .map_err(|e| format!("Failed to download NeoForge installer: {}", e))?;
     let installer_bytes = installer_resp.bytes()
@@ -321,15 +1921,171 @@ async fn install_neoforge(neoforge_version: &str, mc_version: &str, dest_dir: &P
     file.write_all(&installer_bytes).map_err(|e| format!("Failed to write NeoForge installer file: {}", e))?;
 
     // Run the installer with Java
-    let status = Command::new("java")
-        .arg("-jar")
-        .arg(&installer_path)
+    let mut command = installer_command(&installer_path);
+    command
         .arg("--installServer")
-        .current_dir(dest_dir)
-        .status()
-        .map_err(|e| format!("Failed to run NeoForge installer: {}", e))?;
-    if !status.success() {
-        return Err("NeoForge installer failed".to_string());
-    }
+        .current_dir(dest_dir);
+    run_installer(command, "NeoForge")?;
+    // The installer is only needed once
+    let _ = fs::remove_file(&installer_path);
     Ok(())
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_instance() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("wise0wl-instance-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join("mods")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn contained_path_rejects_escaping_paths() {
+        let root = Path::new("/instances/pack");
+        assert_eq!(contained_path(root, Path::new("mods/a.jar")), Some(root.join("mods/a.jar")));
+        assert!(contained_path(root, Path::new("../other/a.jar")).is_none());
+        assert!(contained_path(root, Path::new("mods/../../a.jar")).is_none());
+        assert!(contained_path(root, Path::new("/etc/passwd")).is_none());
+    }
+
+    #[test]
+    fn dropped_files_outside_the_instance_are_kept() {
+        let parent = temp_instance();
+        let instance_dir = parent.join("pack");
+        fs::create_dir_all(instance_dir.join("mods")).unwrap();
+        fs::write(parent.join("outside.jar"), b"keep").unwrap();
+        let key = "mods/../../outside.jar".to_string();
+        let mut managed = ManagedFiles::from([(key.clone(), ManagedFile::default())]);
+
+        remove_dropped_files(&[key.clone()], &mut managed, &instance_dir).unwrap();
+        assert!(parent.join("outside.jar").exists());
+        assert!(!managed.contains_key(&key));
+        let _ = fs::remove_dir_all(&parent);
+    }
+
+    #[test]
+    fn dropped_optional_file_is_removed_with_its_disabled_copy() {
+        let instance_dir = temp_instance();
+        fs::write(instance_dir.join("mods/shader.jar"), b"on").unwrap();
+        fs::write(instance_dir.join("mods/shader.jar.disabled"), b"off").unwrap();
+        let key = "mods/shader.jar".to_string();
+        let mut managed = ManagedFiles::from([(key.clone(), ManagedFile::default())]);
+
+        remove_dropped_files(&[key], &mut managed, &instance_dir).unwrap();
+        assert!(!instance_dir.join("mods/shader.jar").exists());
+        assert!(!instance_dir.join("mods/shader.jar.disabled").exists());
+        assert!(managed.is_empty());
+        let _ = fs::remove_dir_all(&instance_dir);
+    }
+
+    #[test]
+    fn icon_extension_follows_the_content_type() {
+        assert_eq!(icon_extension("image/png"), Some("png"));
+        assert_eq!(icon_extension("image/jpg"), Some("jpg"));
+        assert_eq!(icon_extension("IMAGE/JPEG; charset=binary"), Some("jpg"));
+        assert_eq!(icon_extension("image/vnd.microsoft.icon"), Some("ico"));
+    }
+
+    #[test]
+    fn non_image_content_types_are_rejected() {
+        assert_eq!(icon_extension("text/html"), None);
+        assert_eq!(icon_extension(""), None);
+    }
+
+    #[test]
+    fn neoforge_prefix_drops_the_leading_one() {
+        assert_eq!(neoforge_version_prefix("1.20.4"), "20.4.");
+        assert_eq!(neoforge_version_prefix("1.21"), "21.0.");
+    }
+
+    #[test]
+    fn neoforge_versions_are_filtered_by_minecraft_version() {
+        let versions: Vec<String> = ["20.4.80", "20.4.237", "20.6.1", "21.0.3"].iter().map(|v| v.to_string()).collect();
+        assert_eq!(neoforge_versions_for(&versions, "1.20.4"), ["20.4.80", "20.4.237"]);
+        assert_eq!(neoforge_versions_for(&versions, "1.21"), ["21.0.3"]);
+    }
+
+    #[test]
+    fn maven_metadata_versions_are_extracted_in_order() {
+        let xml = r#"<metadata>
+  <versioning>
+    <versions>
+      <version>20.4.80</version>
+      <version>20.4.237</version>
+    </versions>
+  </versioning>
+</metadata>"#;
+        assert_eq!(parse_maven_versions(xml), ["20.4.80", "20.4.237"]);
+        assert!(parse_maven_versions("<metadata></metadata>").is_empty());
+    }
+
+    #[test]
+    fn rebase_path_moves_everything_after_the_marker() {
+        let new_root = Path::new("/data/launcher");
+        assert_eq!(
+            rebase_path("/home/old/.minecraft/libraries/org/lib.jar", &[".minecraft"], new_root),
+            Some(new_root.join("libraries").join("org").join("lib.jar").to_string_lossy().to_string())
+        );
+        assert_eq!(
+            rebase_path("C:\\Users\\old\\.minecraft\\assets", &[".minecraft"], new_root),
+            Some(new_root.join("assets").to_string_lossy().to_string())
+        );
+    }
+
+    #[test]
+    fn rebase_path_leaves_other_values_alone() {
+        let new_root = Path::new("/data/launcher");
+        // Relative paths, paths without the marker and already rebased paths
+        assert_eq!(rebase_path("libraries/org/lib.jar", &[".minecraft"], new_root), None);
+        assert_eq!(rebase_path("/opt/java/bin/java", &[".minecraft"], new_root), None);
+        assert_eq!(rebase_path("/data/launcher", &["data", "launcher"], new_root), None);
+    }
+
+    #[test]
+    fn part_files_are_partial_downloads() {
+        assert!(is_partial_download(Path::new("mods/sodium.jar.part"), 1024));
+        assert!(is_partial_download(Path::new("mods/sodium.jar.PART"), 0));
+    }
+
+    #[test]
+    fn only_empty_artifacts_count_as_broken() {
+        assert!(is_partial_download(Path::new("libraries/lib.jar"), 0));
+        assert!(!is_partial_download(Path::new("libraries/lib.jar"), 1024));
+        assert!(!is_partial_download(Path::new("config/empty.toml"), 0));
+        assert!(!is_partial_download(Path::new("README"), 0));
+    }
+
+    #[test]
+    fn installer_jars_and_logs_are_artifacts() {
+        assert!(is_installer_artifact("forge-1.20.1-47.2.0-installer.jar"));
+        assert!(is_installer_artifact("forge-1.20.1-47.2.0-installer.jar.log"));
+        assert!(!is_installer_artifact("forge-1.20.1-47.2.0-universal.jar"));
+        assert!(!is_installer_artifact("installer-notes.txt"));
+    }
+
+    fn pack(version: &str, minecraft_version: &str, modloader: &str, modloader_version: &str) -> OnlineModpack {
+        serde_json::from_value(serde_json::json!({
+            "id": "pack",
+            "name": "Pack",
+            "description": "",
+            "version": version,
+            "minecraft_version": minecraft_version,
+            "modloader": modloader,
+            "modloader_version": modloader_version,
+            "image": "",
+            "url": "",
+            "scopes": null
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn update_kind_orders_minecraft_before_modloader_changes() {
+        let installed = pack("1.0.0", "1.20.1", "forge", "47.2.0");
+        assert_eq!(update_kind(&installed, &pack("1.1.0", "1.20.1", "Forge", "47.2.0")), UpdateKind::MinorModsOnly);
+        assert_eq!(update_kind(&installed, &pack("1.1.0", "1.20.1", "forge", "47.3.0")), UpdateKind::ModloaderChange);
+        assert_eq!(update_kind(&installed, &pack("1.1.0", "1.20.1", "neoforge", "47.2.0")), UpdateKind::ModloaderChange);
+        assert_eq!(update_kind(&installed, &pack("2.0.0", "1.21", "neoforge", "21.0.3")), UpdateKind::MinecraftChange);
+    }
+}
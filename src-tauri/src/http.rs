@@ -0,0 +1,60 @@
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::Path;
+use std::sync::Mutex;
+use once_cell::sync::Lazy;
+use reqwest::{Certificate, Client};
+
+/// Identifies the launcher to Mojang, Maven repositories and mirrors
+pub const USER_AGENT: &str = concat!(
+    "wise0wl-launcher/",
+    env!("CARGO_PKG_VERSION"),
+    " (+https://github.com/Wise0wl-server/wise0wl-launcher)"
+);
+
+// Shared HTTP client, rebuilt whenever the network settings change
+static CLIENT: Lazy<Mutex<Option<Client>>> = Lazy::new(|| Mutex::new(None));
+
+fn build_client() -> Client {
+    let settings = crate::settings::load_settings().unwrap_or_default();
+    // Version manifests and asset indexes are large JSON that compresses well;
+    // advertise gzip/brotli/deflate and decode responses transparently
+    let mut builder = Client::builder()
+        .user_agent(USER_AGENT)
+        .gzip(true)
+        .brotli(true)
+        .deflate(true);
+    if settings.force_ipv4 {
+        // Binding to an IPv4 address makes the connector skip AAAA records
+        builder = builder.local_address(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+    }
+    if let Some(ca_path) = &settings.custom_ca_cert {
+        // Trusted in addition to the system roots, for self-hosted mirrors
+        match load_certificate(ca_path) {
+            Ok(cert) => builder = builder.add_root_certificate(cert),
+            Err(e) => println!("Warning: {}", e),
+        }
+    }
+    builder.build().unwrap_or_else(|e| {
+        println!("Warning: Failed to build HTTP client from settings: {}", e);
+        Client::builder().user_agent(USER_AGENT).build().unwrap_or_default()
+    })
+}
+
+fn load_certificate(path: &Path) -> Result<Certificate, String> {
+    let pem = fs::read(path)
+        .map_err(|e| format!("Failed to read CA certificate {}: {}", path.display(), e))?;
+    Certificate::from_pem(&pem)
+        .map_err(|e| format!("Invalid CA certificate {}: {}", path.display(), e))
+}
+
+/// Get the shared HTTP client configured from the current settings
+pub fn client() -> Client {
+    let mut client = CLIENT.lock().unwrap();
+    client.get_or_insert_with(build_client).clone()
+}
+
+/// Drop the cached client so the next request picks up changed settings
+pub fn reset_client() {
+    *CLIENT.lock().unwrap() = None;
+}
@@ -0,0 +1,638 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use tauri::command;
+
+use crate::downloader::{get_instance_dir, get_instances_dir};
+use crate::http;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ResourcePack {
+    pub file_name: String,
+    pub name: String,
+    pub description: String,
+    pub pack_format: Option<u64>,
+    pub path: PathBuf,
+}
+
+/// Flatten a `pack.mcmeta` description, which may be a plain string or a text component
+fn description_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Array(parts) => parts.iter().map(description_text).collect(),
+        serde_json::Value::Object(obj) => {
+            let mut text = obj.get("text").and_then(|t| t.as_str()).unwrap_or("").to_string();
+            if let Some(extra) = obj.get("extra") {
+                text.push_str(&description_text(extra));
+            }
+            text
+        }
+        _ => String::new(),
+    }
+}
+
+fn parse_mcmeta(content: &str) -> Result<(String, Option<u64>), String> {
+    let json: serde_json::Value = serde_json::from_str(content)
+        .map_err(|e| format!("Invalid pack.mcmeta: {}", e))?;
+    let pack = json.get("pack").ok_or("pack.mcmeta has no 'pack' section")?;
+    let description = pack.get("description").map(description_text).unwrap_or_default();
+    Ok((description, pack["pack_format"].as_u64()))
+}
+
+/// Read `pack.mcmeta` from the root of a zipped resource pack
+fn read_zip_mcmeta<R: Read + std::io::Seek>(reader: R) -> Result<String, String> {
+    let mut archive = zip::ZipArchive::new(reader)
+        .map_err(|e| format!("Resource pack is not a valid zip: {}", e))?;
+    let mut file = archive.by_name("pack.mcmeta")
+        .map_err(|_| "Resource pack has no pack.mcmeta".to_string())?;
+    let mut content = String::new();
+    file.read_to_string(&mut content)
+        .map_err(|e| format!("Failed to read pack.mcmeta: {}", e))?;
+    Ok(content)
+}
+
+fn read_resource_pack(path: &Path) -> Result<ResourcePack, String> {
+    let content = if path.is_dir() {
+        fs::read_to_string(path.join("pack.mcmeta"))
+            .map_err(|e| format!("Failed to read pack.mcmeta: {}", e))?
+    } else {
+        let file = fs::File::open(path)
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        read_zip_mcmeta(file)?
+    };
+    let (description, pack_format) = parse_mcmeta(&content)?;
+    let file_name = path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let name = file_name.trim_end_matches(".zip").to_string();
+    Ok(ResourcePack {
+        file_name,
+        name,
+        description,
+        pack_format,
+        path: path.to_path_buf(),
+    })
+}
+
+/// List the resource packs installed in an instance's `resourcepacks/` folder
+#[command]
+pub async fn list_resource_packs(modpack_id: String) -> Result<Vec<ResourcePack>, String> {
+    let packs_dir = get_instance_dir(&modpack_id).join("resourcepacks");
+    if !packs_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let entries = fs::read_dir(&packs_dir)
+        .map_err(|e| format!("Failed to read resource packs directory: {}", e))?;
+    let mut packs = Vec::new();
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let is_zip = path.extension().is_some_and(|ext| ext == "zip");
+        if !is_zip && !path.join("pack.mcmeta").exists() {
+            continue;
+        }
+        match read_resource_pack(&path) {
+            Ok(pack) => packs.push(pack),
+            Err(e) => println!("Skipping resource pack {}: {}", path.display(), e),
+        }
+    }
+    packs.sort_by_key(|pack| pack.name.to_lowercase());
+    Ok(packs)
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScreenshotInfo {
+    pub path: PathBuf,
+    /// Unix timestamp (seconds) of when the screenshot was saved
+    pub taken_at: u64,
+    pub size: u64,
+}
+
+const SCREENSHOT_EXTENSIONS: [&str; 3] = ["png", "jpg", "jpeg"];
+
+fn is_screenshot_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| SCREENSHOT_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// List the screenshots in an instance's `screenshots/` folder, newest first
+#[command]
+pub async fn list_screenshots(modpack_id: String) -> Result<Vec<ScreenshotInfo>, String> {
+    let screenshots_dir = get_instance_dir(&modpack_id).join("screenshots");
+    if !screenshots_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let entries = fs::read_dir(&screenshots_dir)
+        .map_err(|e| format!("Failed to read screenshots directory: {}", e))?;
+    let mut screenshots = Vec::new();
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if !is_screenshot_file(&path) {
+            continue;
+        }
+        let metadata = match entry.metadata() {
+            Ok(metadata) if metadata.is_file() => metadata,
+            _ => continue,
+        };
+        let taken_at = metadata.modified()
+            .ok()
+            .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_secs());
+        screenshots.push(ScreenshotInfo { path, taken_at, size: metadata.len() });
+    }
+    screenshots.sort_by_key(|screenshot| std::cmp::Reverse(screenshot.taken_at));
+    Ok(screenshots)
+}
+
+/// Delete a screenshot. Only image files directly inside an instance's
+/// `screenshots/` folder can be deleted, so a crafted path can't escape it.
+#[command]
+pub async fn delete_screenshot(path: PathBuf) -> Result<(), String> {
+    let resolved = path.canonicalize()
+        .map_err(|e| format!("Screenshot {} not found: {}", path.display(), e))?;
+    let instances_dir = get_instances_dir().canonicalize()
+        .map_err(|e| format!("Failed to resolve instances directory: {}", e))?;
+    let screenshots_dir = resolved.parent()
+        .filter(|dir| dir.file_name().is_some_and(|name| name == "screenshots"));
+    let inside_instance = screenshots_dir
+        .and_then(|dir| dir.parent())
+        .and_then(|instance| instance.parent())
+        .is_some_and(|parent| parent == instances_dir);
+    if !inside_instance || !resolved.is_file() || !is_screenshot_file(&resolved) {
+        return Err(format!("{} is not an instance screenshot", path.display()));
+    }
+    fs::remove_file(&resolved)
+        .map_err(|e| format!("Failed to delete screenshot {}: {}", path.display(), e))
+}
+
+/// Install a resource pack into an instance from a local file or a URL
+#[command]
+pub async fn add_resource_pack(modpack_id: String, path_or_url: String) -> Result<ResourcePack, String> {
+    let (bytes, file_name) = if path_or_url.starts_with("http://") || path_or_url.starts_with("https://") {
+        let resp = http::client().get(&path_or_url).send()
+            .await
+            .map_err(|e| format!("Failed to download resource pack: {}", e))?;
+        if !resp.status().is_success() {
+            return Err(format!("HTTP {} while downloading resource pack", resp.status()));
+        }
+        let bytes = resp.bytes()
+            .await
+            .map_err(|e| format!("Failed to read resource pack bytes: {}", e))?;
+        let file_name = path_or_url
+            .split(['?', '#'])
+            .next()
+            .and_then(|u| u.rsplit('/').next())
+            .unwrap_or("")
+            .to_string();
+        (bytes.to_vec(), file_name)
+    } else {
+        let path = PathBuf::from(&path_or_url);
+        let bytes = fs::read(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let file_name = path.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        (bytes, file_name)
+    };
+
+    // Reject anything that isn't a zip with a pack.mcmeta before touching the instance
+    parse_mcmeta(&read_zip_mcmeta(Cursor::new(&bytes))?)?;
+
+    let file_name = match file_name.as_str() {
+        "" => "resourcepack.zip".to_string(),
+        name if name.ends_with(".zip") => name.to_string(),
+        name => format!("{}.zip", name),
+    };
+    let packs_dir = get_instance_dir(&modpack_id).join("resourcepacks");
+    fs::create_dir_all(&packs_dir)
+        .map_err(|e| format!("Failed to create resource packs directory: {}", e))?;
+    let target = packs_dir.join(&file_name);
+    fs::write(&target, &bytes)
+        .map_err(|e| format!("Failed to write resource pack {}: {}", target.display(), e))?;
+    println!("Installed resource pack {} into {}", file_name, modpack_id);
+    read_resource_pack(&target)
+}
+
+/// Files and folders from a shared game directory that belong to an instance
+const LEGACY_INSTANCE_ITEMS: &[&str] = &[
+    "saves",
+    "config",
+    "options.txt",
+    "optionsof.txt",
+    "servers.dat",
+    "resourcepacks",
+    "shaderpacks",
+    "screenshots",
+];
+
+/// Written into the legacy directory once its contents have been migrated
+const MIGRATION_MARKER: &str = ".migrated-to-instances";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MigrationAction {
+    pub kind: String,
+    pub source: PathBuf,
+    pub target: PathBuf,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MigrationReport {
+    pub dry_run: bool,
+    pub legacy_dir: PathBuf,
+    pub actions: Vec<MigrationAction>,
+    pub errors: Vec<String>,
+}
+
+fn copy_dir_all(source: &Path, target: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(target)?;
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.is_dir() {
+            copy_dir_all(&path, &target.join(entry.file_name()))?;
+        } else {
+            fs::copy(&path, target.join(entry.file_name()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Move a file or directory, copying across drives when a rename isn't possible
+fn move_path(source: &Path, target: &Path) -> Result<(), String> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    if fs::rename(source, target).is_ok() {
+        return Ok(());
+    }
+    let result = if source.is_dir() {
+        copy_dir_all(source, target).and_then(|_| fs::remove_dir_all(source))
+    } else {
+        fs::copy(source, target).and_then(|_| fs::remove_file(source))
+    };
+    result.map_err(|e| format!("Failed to move {} to {}: {}", source.display(), target.display(), e))
+}
+
+fn link_path(source: &Path, target: &Path) -> Result<(), String> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    #[cfg(unix)]
+    let result = std::os::unix::fs::symlink(source, target);
+    #[cfg(windows)]
+    let result = if source.is_dir() {
+        std::os::windows::fs::symlink_dir(source, target)
+    } else {
+        std::os::windows::fs::symlink_file(source, target)
+    };
+    result.map_err(|e| format!("Failed to link {} to {}: {}", target.display(), source.display(), e))
+}
+
+/// Move worlds and configs from the old shared game directory into the
+/// per-instance layout. The first modpack (or `target_modpack_id`) receives the
+/// files and every other modpack gets links to them. With `dry_run` set only the
+/// planned actions are returned.
+#[command]
+pub async fn migrate_to_instances(dry_run: bool, target_modpack_id: Option<String>) -> Result<MigrationReport, String> {
+    let settings = crate::settings::load_settings()?;
+    let legacy_dir = settings.game_directory;
+    let mut report = MigrationReport {
+        dry_run,
+        legacy_dir: legacy_dir.clone(),
+        actions: Vec::new(),
+        errors: Vec::new(),
+    };
+    if legacy_dir.join(MIGRATION_MARKER).exists() {
+        println!("Legacy directory {} was already migrated", legacy_dir.display());
+        return Ok(report);
+    }
+
+    let items: Vec<&str> = LEGACY_INSTANCE_ITEMS
+        .iter()
+        .copied()
+        .filter(|item| legacy_dir.join(item).exists())
+        .collect();
+    if items.is_empty() {
+        return Ok(report);
+    }
+
+    let mut modpack_ids: Vec<String> = crate::modpack::get_all_modpacks()?
+        .into_iter()
+        .map(|m| m.id)
+        .collect();
+    if let Some(target) = target_modpack_id {
+        modpack_ids.retain(|id| *id != target);
+        modpack_ids.insert(0, target);
+    }
+    let (primary, others) = match modpack_ids.split_first() {
+        Some(split) => split,
+        None => return Err("No modpacks to migrate into".to_string()),
+    };
+
+    let primary_dir = get_instance_dir(primary);
+    for item in &items {
+        let source = legacy_dir.join(item);
+        let target = primary_dir.join(item);
+        if target.exists() {
+            report.errors.push(format!("{} already exists, leaving {} in place", target.display(), source.display()));
+            continue;
+        }
+        report.actions.push(MigrationAction { kind: "move".to_string(), source, target });
+    }
+    let moved: Vec<PathBuf> = report.actions.iter().map(|a| a.target.clone()).collect();
+    for id in others {
+        let instance_dir = get_instance_dir(id);
+        for source in &moved {
+            let target = instance_dir.join(source.file_name().unwrap_or_default());
+            if !target.exists() {
+                report.actions.push(MigrationAction { kind: "link".to_string(), source: source.clone(), target });
+            }
+        }
+    }
+
+    if dry_run {
+        return Ok(report);
+    }
+
+    for action in &report.actions {
+        let result = match action.kind.as_str() {
+            "move" => move_path(&action.source, &action.target),
+            _ => link_path(&action.source, &action.target),
+        };
+        if let Err(e) = result {
+            report.errors.push(e);
+        }
+    }
+    if report.errors.is_empty() {
+        fs::write(legacy_dir.join(MIGRATION_MARKER), primary)
+            .map_err(|e| format!("Failed to write migration marker: {}", e))?;
+    }
+    println!("Migrated {} items from {} ({} errors)", report.actions.len(), legacy_dir.display(), report.errors.len());
+    Ok(report)
+}
+
+/// Read the `key:value` lines of a game directory's `options.txt`, in file order
+pub(crate) fn read_options(game_dir: &Path) -> Vec<(String, String)> {
+    fs::read_to_string(game_dir.join("options.txt"))
+        .map(|content| {
+            content
+                .lines()
+                .filter_map(|line| line.split_once(':'))
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub(crate) fn write_options(game_dir: &Path, options: &[(String, String)]) -> Result<(), String> {
+    fs::create_dir_all(game_dir)
+        .map_err(|e| format!("Failed to create game directory: {}", e))?;
+    let content: String = options.iter().map(|(k, v)| format!("{}:{}\n", k, v)).collect();
+    fs::write(game_dir.join("options.txt"), content)
+        .map_err(|e| format!("Failed to write options.txt: {}", e))
+}
+
+/// Set an `options.txt` key only if the player hasn't chosen a value yet
+pub(crate) fn preset_option(game_dir: &Path, key: &str, value: &str) -> Result<(), String> {
+    let mut options = read_options(game_dir);
+    if options.iter().any(|(k, _)| k == key) {
+        return Ok(());
+    }
+    options.push((key.to_string(), value.to_string()));
+    write_options(game_dir, &options)
+}
+
+/// Add several options at once, skipping keys `options.txt` already has
+pub(crate) fn preset_options(game_dir: &Path, presets: &HashMap<String, String>) -> Result<(), String> {
+    let mut options = read_options(game_dir);
+    let mut keys: Vec<&String> = presets.keys().collect();
+    keys.sort();
+    for key in keys {
+        if !options.iter().any(|(k, _)| k == key) {
+            options.push((key.clone(), presets[key].clone()));
+        }
+    }
+    write_options(game_dir, &options)
+}
+
+/// Minecraft holds an exclusive lock on `session.lock` while a world is open
+fn is_world_in_use(world_dir: &Path) -> bool {
+    use fs2::FileExt;
+    let lock_path = world_dir.join("session.lock");
+    let file = match fs::OpenOptions::new().read(true).write(true).open(&lock_path) {
+        Ok(file) => file,
+        Err(_) => return false,
+    };
+    match file.try_lock_exclusive() {
+        Ok(()) => {
+            let _ = file.unlock();
+            false
+        }
+        Err(_) => true,
+    }
+}
+
+fn add_dir_to_zip<W: std::io::Write + std::io::Seek>(
+    zip: &mut zip::ZipWriter<W>,
+    dir: &Path,
+    prefix: &str,
+) -> Result<(), String> {
+    let options = zip::write::FileOptions::default();
+    let entries = fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let name = format!("{}/{}", prefix, entry.file_name().to_string_lossy());
+        if path.is_dir() {
+            zip.add_directory(name.as_str(), options)
+                .map_err(|e| format!("Failed to add {} to backup: {}", name, e))?;
+            add_dir_to_zip(zip, &path, &name)?;
+        } else {
+            // The lock file is held by a running game and is recreated on load
+            if entry.file_name() == "session.lock" {
+                continue;
+            }
+            let bytes = fs::read(&path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            zip.start_file(name.as_str(), options)
+                .map_err(|e| format!("Failed to add {} to backup: {}", name, e))?;
+            std::io::Write::write_all(zip, &bytes)
+                .map_err(|e| format!("Failed to write {} to backup: {}", name, e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Find the world folder name inside a backup, i.e. the top-level folder holding `level.dat`
+fn backup_world_name<R: Read + std::io::Seek>(archive: &mut zip::ZipArchive<R>) -> Result<String, String> {
+    (0..archive.len())
+        .filter_map(|i| archive.by_index(i).ok().and_then(|f| f.enclosed_name().map(|p| p.to_path_buf())))
+        .find_map(|path| {
+            let mut components = path.components();
+            let world = components.next()?.as_os_str().to_string_lossy().to_string();
+            let rest = components.as_path();
+            (rest == Path::new("level.dat")).then_some(world)
+        })
+        .ok_or_else(|| "Backup does not contain a world (no level.dat found)".to_string())
+}
+
+/// Zip a single world from an instance's `saves/` into `backups/worlds/`
+#[command]
+pub async fn backup_world(modpack_id: String, world_name: String) -> Result<PathBuf, String> {
+    if world_name.is_empty() || world_name.contains(['/', '\\']) || world_name == ".." {
+        return Err(format!("Invalid world name: {}", world_name));
+    }
+    let instance_dir = get_instance_dir(&modpack_id);
+    let world_dir = instance_dir.join("saves").join(&world_name);
+    if !world_dir.join("level.dat").exists() {
+        return Err(format!("World {} not found in {}", world_name, modpack_id));
+    }
+
+    let backups_dir = instance_dir.join("backups").join("worlds");
+    fs::create_dir_all(&backups_dir)
+        .map_err(|e| format!("Failed to create backups directory: {}", e))?;
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let backup_path = backups_dir.join(format!("{}-{}.zip", world_name, timestamp));
+
+    let file = fs::File::create(&backup_path)
+        .map_err(|e| format!("Failed to create {}: {}", backup_path.display(), e))?;
+    let mut zip = zip::ZipWriter::new(file);
+    let result = add_dir_to_zip(&mut zip, &world_dir, &world_name)
+        .and_then(|_| zip.finish().map(|_| ()).map_err(|e| format!("Failed to finish backup: {}", e)));
+    if let Err(e) = result {
+        let _ = fs::remove_file(&backup_path);
+        return Err(e);
+    }
+    println!("Backed up world {} of {} to {}", world_name, modpack_id, backup_path.display());
+    Ok(backup_path)
+}
+
+/// Restore a world backup into an instance's `saves/`, replacing the existing
+/// world of the same name unless the game currently has it open
+#[command]
+pub async fn restore_world(modpack_id: String, backup_path: PathBuf) -> Result<PathBuf, String> {
+    let file = fs::File::open(&backup_path)
+        .map_err(|e| format!("Failed to open {}: {}", backup_path.display(), e))?;
+    let mut archive = zip::ZipArchive::new(file)
+        .map_err(|e| format!("Backup is not a valid zip: {}", e))?;
+    let world_name = backup_world_name(&mut archive)?;
+
+    let saves_dir = get_instance_dir(&modpack_id).join("saves");
+    let world_dir = saves_dir.join(&world_name);
+    if is_world_in_use(&world_dir) {
+        return Err(format!("World {} is open in a running game; close it before restoring", world_name));
+    }
+
+    // Extract next to the world first so a failed restore leaves it untouched
+    let staging_dir = saves_dir.join(format!(".{}.restoring", world_name));
+    if staging_dir.exists() {
+        fs::remove_dir_all(&staging_dir)
+            .map_err(|e| format!("Failed to clear {}: {}", staging_dir.display(), e))?;
+    }
+    fs::create_dir_all(&staging_dir)
+        .map_err(|e| format!("Failed to create {}: {}", staging_dir.display(), e))?;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)
+            .map_err(|e| format!("Failed to read backup entry: {}", e))?;
+        let relative = match entry.enclosed_name().and_then(|p| p.strip_prefix(&world_name).ok()) {
+            Some(p) => p.to_path_buf(),
+            None => continue,
+        };
+        let out_path = staging_dir.join(relative);
+        if entry.is_dir() {
+            fs::create_dir_all(&out_path)
+                .map_err(|e| format!("Failed to create {}: {}", out_path.display(), e))?;
+            continue;
+        }
+        if let Some(parent) = out_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let mut out = fs::File::create(&out_path)
+            .map_err(|e| format!("Failed to create {}: {}", out_path.display(), e))?;
+        std::io::copy(&mut entry, &mut out)
+            .map_err(|e| format!("Failed to extract {}: {}", out_path.display(), e))?;
+    }
+
+    if world_dir.exists() {
+        fs::remove_dir_all(&world_dir)
+            .map_err(|e| format!("Failed to remove existing world {}: {}", world_dir.display(), e))?;
+    }
+    fs::rename(&staging_dir, &world_dir)
+        .map_err(|e| format!("Failed to move restored world into place: {}", e))?;
+    println!("Restored world {} of {} from {}", world_name, modpack_id, backup_path.display());
+    Ok(world_dir)
+}
+
+/// Throwaway game directories that haven't been cleaned up yet
+fn get_ephemeral_tracking_path() -> PathBuf {
+    crate::paths::config_dir()
+        .expect("Failed to get config directory")
+        .join("wise0wl-launcher")
+        .join("ephemeral-instances.json")
+}
+
+fn load_ephemeral_instances() -> Vec<PathBuf> {
+    fs::read_to_string(get_ephemeral_tracking_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_ephemeral_instances(dirs: &[PathBuf]) -> Result<(), String> {
+    let path = get_ephemeral_tracking_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+    let content = serde_json::to_string_pretty(dirs)
+        .map_err(|e| format!("Failed to serialize ephemeral instances: {}", e))?;
+    fs::write(&path, content)
+        .map_err(|e| format!("Failed to write ephemeral instances: {}", e))
+}
+
+/// Whether `dir` is a tracked temporary game directory
+pub(crate) fn is_ephemeral_instance(dir: &Path) -> bool {
+    load_ephemeral_instances().iter().any(|d| d == dir)
+}
+
+/// Create and track a temporary game directory for a throwaway launch
+pub(crate) fn create_ephemeral_instance() -> Result<PathBuf, String> {
+    let dir = std::env::temp_dir().join(format!("wise0wl-ephemeral-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create temporary instance: {}", e))?;
+    let mut tracked = load_ephemeral_instances();
+    tracked.push(dir.clone());
+    save_ephemeral_instances(&tracked)?;
+    Ok(dir)
+}
+
+/// Delete a temporary game directory, keeping it tracked if it can't be removed yet
+pub(crate) fn remove_ephemeral_instance(dir: &Path) {
+    if dir.exists() {
+        if let Err(e) = fs::remove_dir_all(dir) {
+            println!("Warning: Failed to remove temporary instance {}: {}", dir.display(), e);
+            return;
+        }
+    }
+    let mut tracked = load_ephemeral_instances();
+    tracked.retain(|d| d != dir);
+    if let Err(e) = save_ephemeral_instances(&tracked) {
+        println!("Warning: {}", e);
+    }
+}
+
+/// Remove temporary instances left behind when the launcher exited before the game did
+pub fn cleanup_ephemeral_instances() {
+    for dir in load_ephemeral_instances() {
+        println!("Removing orphaned temporary instance {}", dir.display());
+        remove_ephemeral_instance(&dir);
+    }
+}
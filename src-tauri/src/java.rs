@@ -1,9 +1,14 @@
 use std::path::{Path, PathBuf};
 use std::fs;
-use std::io::Cursor;
+use std::io::Write;
+use std::process::Command;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
 
-use dirs::data_dir;
-use reqwest::Client;
+use crate::paths::data_dir;
+
+use crate::http;
 
 #[cfg(target_os = "windows")]
 const PLATFORM: &str = "windows";
@@ -12,20 +17,185 @@ const PLATFORM: &str = "linux";
 #[cfg(target_os = "macos")]
 const PLATFORM: &str = "mac";
 
-#[cfg(target_arch = "x86_64")]
-const ARCH: &str = "x64";
-#[cfg(target_arch = "aarch64")]
-const ARCH: &str = "aarch64";
+/// Adoptium's name for a Rust target architecture. Some differ: 32-bit x86 is
+/// `x32` and POWER is `ppc64le` or `ppc64` depending on endianness.
+fn adoptium_arch(rust_arch: &'static str) -> &'static str {
+    match rust_arch {
+        "x86_64" => "x64",
+        "x86" => "x32",
+        "powerpc64" if cfg!(target_endian = "little") => "ppc64le",
+        "powerpc64" => "ppc64",
+        // aarch64, arm, s390x and riscv64 are spelled the same
+        other => other,
+    }
+}
+
+/// Architecture of this build, as the Adoptium API names it
+fn arch() -> &'static str {
+    adoptium_arch(std::env::consts::ARCH)
+}
+
+/// Architecture to try when Adoptium has no native build. Windows on ARM runs
+/// x64 Java under emulation.
+#[cfg(all(target_os = "windows", target_arch = "aarch64"))]
+const FALLBACK_ARCH: Option<&str> = Some("x64");
+#[cfg(not(all(target_os = "windows", target_arch = "aarch64")))]
+const FALLBACK_ARCH: Option<&str> = None;
+
+/// Adoptium vendor used unless settings choose another
+pub(crate) const DEFAULT_JAVA_VENDOR: &str = "eclipse";
+
+/// Which Adoptium image to install. Players get a JRE; mod developers can ask for
+/// a JDK to get tools such as `jstack` and `jmap`.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageType {
+    #[default]
+    Jre,
+    Jdk,
+}
+
+impl ImageType {
+    fn as_str(self) -> &'static str {
+        match self {
+            ImageType::Jre => "jre",
+            ImageType::Jdk => "jdk",
+        }
+    }
+}
+
+/// A Java runtime to look up or install
+#[derive(Debug, Clone)]
+pub struct JavaRequest {
+    pub major: u32,
+    pub image_type: ImageType,
+    /// Adoptium API `vendor`, e.g. `eclipse`
+    pub vendor: String,
+}
+
+impl JavaRequest {
+    /// Java `major` with the vendor and image type chosen in settings
+    pub fn from_settings(major: u32) -> Self {
+        let settings = crate::settings::load_settings().unwrap_or_default();
+        JavaRequest { major, image_type: settings.java_image_type, vendor: settings.java_vendor }
+    }
+}
+
+/// Attempts made against the Adoptium API before giving up on transient failures
+const ADOPTIUM_ATTEMPTS: u32 = 3;
+
+/// Why an Adoptium release lookup failed
+enum AdoptiumError {
+    /// Adoptium has no build for this version/platform; retrying won't help
+    NoBuild,
+    /// Network or server trouble that may go away on retry
+    Transient(String),
+    /// The API answered with something we can't use
+    Invalid(String),
+}
+
+/// Query the Adoptium API once and return the first release asset
+async fn query_adoptium(api_url: &str) -> Result<serde_json::Value, AdoptiumError> {
+    let resp = http::client().get(api_url).send()
+        .await
+        .map_err(|e| AdoptiumError::Transient(format!("Failed to query Adoptium API: {}", e)))?;
+    let status = resp.status();
+    // Adoptium answers 404 when no build matches the query
+    if status == reqwest::StatusCode::NOT_FOUND {
+        return Err(AdoptiumError::NoBuild);
+    }
+    if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return Err(AdoptiumError::Transient(format!("Adoptium API returned HTTP {}", status)));
+    }
+    if !status.is_success() {
+        return Err(AdoptiumError::Invalid(format!("Adoptium API returned HTTP {}", status)));
+    }
+    let json: serde_json::Value = resp.json()
+        .await
+        .map_err(|e| AdoptiumError::Transient(format!("Failed to parse Adoptium API response: {}", e)))?;
+    let assets = json.as_array()
+        .ok_or_else(|| AdoptiumError::Invalid("Adoptium API response is not a list of releases".to_string()))?;
+    let asset = assets.first().ok_or(AdoptiumError::NoBuild)?;
+    let has_binary = asset.get("binaries")
+        .and_then(|b| b.as_array())
+        .is_some_and(|binaries| !binaries.is_empty());
+    if !has_binary {
+        return Err(AdoptiumError::NoBuild);
+    }
+    Ok(asset.clone())
+}
+
+/// Fetch the latest Adoptium release for a Java version and architecture, retrying
+/// transient failures. `None` means Adoptium has no such build.
+async fn fetch_adoptium_release_for(request: &JavaRequest, arch: &str) -> Result<Option<serde_json::Value>, String> {
+    let api_url = format!(
+        "https://api.adoptium.net/v3/assets/feature_releases/{}/ga?architecture={}&image_type={}&os={}&vendor={}&heap_size=normal",
+        request.major, arch, request.image_type.as_str(), PLATFORM, request.vendor
+    );
+    let mut attempt = 1;
+    loop {
+        match query_adoptium(&api_url).await {
+            Ok(asset) => return Ok(Some(asset)),
+            Err(AdoptiumError::NoBuild) => return Ok(None),
+            Err(AdoptiumError::Invalid(e)) => return Err(e),
+            Err(AdoptiumError::Transient(e)) if attempt < ADOPTIUM_ATTEMPTS => {
+                println!("Warning: {} (attempt {}), retrying", e, attempt);
+                tokio::time::sleep(std::time::Duration::from_secs(1 << (attempt - 1))).await;
+                attempt += 1;
+            }
+            Err(AdoptiumError::Transient(e)) => {
+                return Err(format!("{} after {} attempts; please try again later", e, attempt));
+            }
+        }
+    }
+}
+
+/// Fetch the latest Adoptium release for a Java version, falling back to an
+/// emulated architecture where this platform has one
+async fn fetch_adoptium_release(request: &JavaRequest) -> Result<serde_json::Value, String> {
+    let java_version = request.major;
+    let archs: Vec<&str> = std::iter::once(arch()).chain(FALLBACK_ARCH).collect();
+    for arch in &archs {
+        match fetch_adoptium_release_for(request, arch).await? {
+            Some(asset) => {
+                if *arch != archs[0] {
+                    println!("No native Java {} build for {}, using the {} build", java_version, archs[0], arch);
+                }
+                return Ok(asset);
+            }
+            None => println!("Adoptium returned no Java {} assets for {} {}", java_version, PLATFORM, arch),
+        }
+    }
+    Err(format!(
+        "Adoptium has no {} Java {} {} build for {} {}. Try installing Java {} from a different vendor and setting its path manually.",
+        request.vendor, java_version, request.image_type.as_str().to_uppercase(), PLATFORM, archs.join(" or "), java_version
+    ))
+}
 
-const JAVA_VENDOR: &str = "eclipse";
-const IMAGE_TYPE: &str = "jre";
+/// Directory holding every Java runtime the launcher downloaded
+pub(crate) fn runtime_root() -> PathBuf {
+    if let Some(root) = crate::settings::storage_root() {
+        return root.join("runtime");
+    }
+    default_runtime_root()
+}
 
-fn java_storage_dir(java_version: u32) -> PathBuf {
+/// Managed runtime directory when no storage root is configured. Doesn't read
+/// settings, so it is safe to use while the default settings are being built.
+fn default_runtime_root() -> PathBuf {
     data_dir()
         .unwrap_or_else(|| PathBuf::from("."))
         .join(".minecraft-wise0wl")
         .join("runtime")
-        .join(format!("jre-{}", java_version))
+}
+
+/// JREs and JDKs (and other vendors' builds) live in separate folders so they can coexist
+fn java_storage_dir(request: &JavaRequest) -> PathBuf {
+    let name = format!("{}-{}", request.image_type.as_str(), request.major);
+    if request.vendor == DEFAULT_JAVA_VENDOR {
+        return runtime_root().join(name);
+    }
+    runtime_root().join(format!("{}-{}", name, request.vendor))
 }
 
 fn java_bin_path(root: &Path) -> PathBuf {
@@ -39,8 +209,8 @@ fn java_bin_path(root: &Path) -> PathBuf {
     }
 }
 
-fn find_existing_java(java_version: u32) -> Option<PathBuf> {
-    let java_dir = java_storage_dir(java_version);
+pub(crate) fn find_existing_java(request: &JavaRequest) -> Option<PathBuf> {
+    let java_dir = java_storage_dir(request);
     if java_dir.exists() {
         let entries = fs::read_dir(&java_dir).ok()?;
         for entry in entries {
@@ -57,55 +227,407 @@ fn find_existing_java(java_version: u32) -> Option<PathBuf> {
     None
 }
 
-pub async fn ensure_java_installed(java_version: u32) -> Result<PathBuf, String> {
+/// Major version from `java -version` output, e.g. 8 for `"1.8.0_392"` and 17 for `"17.0.9"`
+pub(crate) fn parse_java_major(version_output: &str) -> Option<u32> {
+    let start = version_output.find("version \"")? + "version \"".len();
+    let version = version_output[start..].split('"').next()?;
+    let mut parts = version.split(|c: char| !c.is_ascii_digit());
+    match parts.next()?.parse::<u32>().ok()? {
+        1 => parts.next()?.parse().ok(),
+        major => Some(major),
+    }
+}
+
+/// A Java runtime found on this machine
+#[derive(Debug, Serialize, Clone)]
+pub struct JavaInstallation {
+    pub path: PathBuf,
+    pub major_version: u32,
+    /// e.g. "Eclipse Adoptium", when the runtime says
+    pub vendor: Option<String>,
+    /// e.g. "x86_64" or "aarch64", when the runtime says
+    pub architecture: Option<String>,
+}
+
+/// `KEY="value"` pairs from the `release` file at the root of a Java home
+fn read_release_file(java_bin: &Path) -> std::collections::HashMap<String, String> {
+    // Follow links such as /usr/bin/java to the real Java home
+    let java_bin = fs::canonicalize(java_bin).unwrap_or_else(|_| java_bin.to_path_buf());
+    let Some(home) = java_bin.parent().and_then(Path::parent) else {
+        return Default::default();
+    };
+    fs::read_to_string(home.join("release"))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().trim_matches('"').to_string()))
+        .collect()
+}
+
+/// Vendor from the runtime line of `java -version`, e.g. "Temurin" from
+/// `OpenJDK Runtime Environment Temurin-17.0.9+9 (build 17.0.9+9)`
+fn parse_java_vendor(version_output: &str) -> Option<String> {
+    let runtime = version_output.lines().nth(1)?;
+    let build = runtime.split("Runtime Environment").nth(1)?.trim();
+    let vendor = build.split(['-', ' ', '(']).next()?;
+    (!vendor.is_empty()).then(|| vendor.to_string())
+}
+
+/// Run the Java at `path` and describe it, or None if it doesn't run
+pub(crate) fn inspect_java(path: &Path) -> Option<JavaInstallation> {
+    let output = Command::new(path).arg("-version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    // `java -version` prints to stderr
+    let version_output = String::from_utf8_lossy(&output.stderr);
+    let release = read_release_file(path);
+    Some(JavaInstallation {
+        path: path.to_path_buf(),
+        major_version: parse_java_major(&version_output)?,
+        vendor: release.get("IMPLEMENTOR").cloned().or_else(|| parse_java_vendor(&version_output)),
+        architecture: release.get("OS_ARCH").cloned(),
+    })
+}
+
+/// Every working Java found on this machine, newest first
+pub fn enumerate_java_installations() -> Vec<JavaInstallation> {
+    installations_with_runtimes_in(&runtime_root())
+}
+
+/// Every working Java found on this machine, looking for managed runtimes under
+/// `managed_root`, newest first
+fn installations_with_runtimes_in(managed_root: &Path) -> Vec<JavaInstallation> {
+    let mut installations: Vec<JavaInstallation> = java_candidates(managed_root)
+        .iter()
+        .filter_map(|path| inspect_java(path))
+        .collect();
+    installations.sort_by_key(|installation| std::cmp::Reverse(installation.major_version));
+    installations
+}
+
+/// A Java installed outside the launcher's managed runtimes that can run Java `major`
+/// code, preferring that exact major version over newer ones
+pub(crate) fn find_detected_java(major: u32) -> Option<JavaInstallation> {
+    let managed = runtime_root();
+    let installations: Vec<JavaInstallation> = enumerate_java_installations()
+        .into_iter()
+        .filter(|java| !java.path.starts_with(&managed) && java.major_version >= major)
+        .collect();
+    let exact = installations.iter().position(|java| java.major_version == major);
+    installations.into_iter().nth(exact.unwrap_or(0))
+}
+
+/// `java` binaries in every subdirectory of `dir`, looking `depth` levels down
+fn java_bins_under(dir: &Path, depth: u32, bins: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.flatten().map(|entry| entry.path()).filter(|path| path.is_dir()) {
+        let java_bin = java_bin_path(&path);
+        if java_bin.exists() {
+            bins.push(java_bin);
+        } else if depth > 1 {
+            java_bins_under(&path, depth - 1, bins);
+        }
+    }
+}
+
+/// Places a Java may be installed: JAVA_HOME, PATH, the launcher's managed
+/// runtimes under `managed_root` and the OS's usual install directories.
+/// Duplicates are removed.
+fn java_candidates(managed_root: &Path) -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+    if let Some(java_home) = std::env::var_os("JAVA_HOME") {
+        candidates.push(java_bin_path(Path::new(&java_home)));
+    }
+    if let Some(path) = std::env::var_os("PATH") {
+        let exe = if cfg!(target_os = "windows") { "java.exe" } else { "java" };
+        candidates.extend(std::env::split_paths(&path).map(|dir| dir.join(exe)));
+    }
+    // Managed runtimes sit at <runtime>/<image>-<major>/<archive>/<release>/bin
+    java_bins_under(managed_root, 3, &mut candidates);
+
+    #[cfg(target_os = "windows")]
+    for dir in ["C:\\Program Files\\Java", "C:\\Program Files\\Eclipse Adoptium", "C:\\Program Files\\Microsoft"] {
+        java_bins_under(Path::new(dir), 1, &mut candidates);
+    }
+    #[cfg(target_os = "linux")]
+    java_bins_under(Path::new("/usr/lib/jvm"), 1, &mut candidates);
+    #[cfg(target_os = "macos")]
+    {
+        let vms = Path::new("/Library/Java/JavaVirtualMachines");
+        if let Ok(entries) = fs::read_dir(vms) {
+            for entry in entries.flatten() {
+                candidates.push(java_bin_path(&entry.path().join("Contents").join("Home")));
+            }
+        }
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    candidates
+        .into_iter()
+        .filter(|path| path.exists())
+        .filter(|path| seen.insert(fs::canonicalize(path).unwrap_or_else(|_| path.clone())))
+        .collect()
+}
+
+/// The newest Java found on this machine, looked up once per run. This backs
+/// `Settings::default()`, so it must not load settings: doing so with no settings
+/// file would build the defaults again and re-enter this initializer.
+static DETECTED_JAVA: Lazy<Option<PathBuf>> = Lazy::new(|| {
+    let newest = installations_with_runtimes_in(&default_runtime_root()).into_iter().next()?;
+    println!("Detected Java {} at {}", newest.major_version, newest.path.display());
+    Some(newest.path)
+});
+
+/// Path of the newest usable Java on this machine. On Windows the console-less
+/// `javaw.exe` next to it is preferred.
+pub(crate) fn detect_java() -> Option<PathBuf> {
+    let java = DETECTED_JAVA.clone()?;
+    #[cfg(target_os = "windows")]
+    {
+        let javaw = java.with_file_name("javaw.exe");
+        if javaw.exists() {
+            return Some(javaw);
+        }
+    }
+    Some(java)
+}
+
+/// Where the Java chosen for a launch comes from, in order of precedence
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum JavaSource {
+    /// The Java path passed with the launch options
+    Explicit,
+    /// The Java path configured in settings
+    Settings,
+    /// A Java found on this machine outside the launcher's runtimes
+    Detected,
+    /// A runtime the launcher already downloaded
+    Managed,
+    /// No suitable runtime yet; one is downloaded from Adoptium at launch
+    Download,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JavaResolution {
+    /// The binary that will run the game, if already installed
+    pub path: Option<PathBuf>,
+    pub major: u32,
+    pub source: JavaSource,
+}
+
+/// A configured Java that runs and is new enough, or None (with the reason logged)
+fn usable_java(path: &str, required: u32, what: &str) -> Option<JavaInstallation> {
+    if path.trim().is_empty() {
+        return None;
+    }
+    match inspect_java(Path::new(path)) {
+        Some(java) if java.major_version >= required => Some(java),
+        Some(java) => {
+            println!("Skipping {} Java {}: Java {} is older than the required {}", what, path, java.major_version, required);
+            None
+        }
+        None => {
+            println!("Skipping {} Java {}: it doesn't run", what, path);
+            None
+        }
+    }
+}
+
+/// Pick the Java for a launch needing Java `required`: the launch option's Java,
+/// then the one in settings, then a detected install, then a managed runtime.
+/// Java that doesn't run or is too old is skipped. Launches and the launch
+/// preview both go through here so they always agree.
+pub(crate) fn resolve_java(explicit: Option<&str>, configured: &str, required: u32) -> JavaResolution {
+    let found = |java: JavaInstallation, source| JavaResolution {
+        path: Some(java.path),
+        major: java.major_version,
+        source,
+    };
+    if let Some(java) = explicit.and_then(|path| usable_java(path, required, "launch option")) {
+        return found(java, JavaSource::Explicit);
+    }
+    if let Some(java) = usable_java(configured, required, "settings") {
+        return found(java, JavaSource::Settings);
+    }
+    if let Some(java) = find_detected_java(required) {
+        return found(java, JavaSource::Detected);
+    }
+    match find_existing_java(&JavaRequest::from_settings(required)) {
+        Some(path) => JavaResolution { path: Some(path), major: required, source: JavaSource::Managed },
+        None => JavaResolution { path: None, major: required, source: JavaSource::Download },
+    }
+}
+
+/// Install (or reuse) a full JDK of the given major version
+pub async fn ensure_jdk_installed(java_version: u32, app: &AppHandle) -> Result<PathBuf, String> {
+    let request = JavaRequest { image_type: ImageType::Jdk, ..JavaRequest::from_settings(java_version) };
+    ensure_java_image_installed(&request, app).await
+}
+
+/// Size in bytes of the archive `ensure_java_image_installed` would download, or 0
+/// when that Java is already installed
+pub async fn java_download_size(request: &JavaRequest) -> Result<u64, String> {
+    if find_existing_java(request).is_some() {
+        return Ok(0);
+    }
+    let asset = fetch_adoptium_release(request).await?;
+    asset["binaries"][0]["package"]["size"]
+        .as_u64()
+        .ok_or("No size in Adoptium package".to_string())
+}
+
+/// Payload of the `java_extract_progress` event
+#[derive(Debug, Serialize, Clone)]
+pub struct JavaExtractProgress {
+    pub file: String,
+    pub extracted: usize,
+    /// Entry count, known up front for zip archives only
+    pub total: Option<usize>,
+}
+
+/// Payload of the `java_download_progress` event
+#[derive(Debug, Serialize, Clone)]
+pub struct JavaDownloadProgress {
+    pub downloaded: u64,
+    /// The package size reported by Adoptium, or Content-Length when it gives none
+    pub total: Option<u64>,
+}
+
+/// Bytes downloaded between two `java_download_progress` events
+const PROGRESS_STEP_BYTES: u64 = 1024 * 1024;
+
+/// Stream a download to disk chunk by chunk instead of buffering the whole archive,
+/// returning the lowercase hex SHA-256 of what was written. `size` is the expected
+/// archive size, used as the progress total.
+async fn download_to_file(url: &str, path: &Path, size: Option<u64>, app: &AppHandle) -> Result<String, String> {
+    use sha2::{Digest, Sha256};
+    let mut resp = http::client().get(url).send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Failed to download JRE: {}", e))?;
+    let mut file = fs::File::create(path)
+        .map_err(|e| format!("Failed to save JRE archive: {}", e))?;
+    let mut hasher = Sha256::new();
+    let total = size.or(resp.content_length());
+    let mut downloaded = 0;
+    let mut reported = 0;
+    while let Some(chunk) = resp.chunk().await.map_err(|e| format!("Failed to read JRE bytes: {}", e))? {
+        hasher.update(&chunk);
+        file.write_all(&chunk)
+            .map_err(|e| format!("Failed to save JRE archive: {}", e))?;
+        downloaded += chunk.len() as u64;
+        if downloaded - reported >= PROGRESS_STEP_BYTES {
+            reported = downloaded;
+            let _ = app.emit("java_download_progress", JavaDownloadProgress { downloaded, total });
+        }
+    }
+    let _ = app.emit("java_download_progress", JavaDownloadProgress { downloaded, total });
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+#[cfg(target_os = "windows")]
+fn extract_archive(archive_path: &Path, extract_dir: &Path, app: &AppHandle) -> Result<(), String> {
+    let file = fs::File::open(archive_path).map_err(|e| format!("Failed to open zip: {}", e))?;
+    let mut zip = zip::ZipArchive::new(file).map_err(|e| format!("Failed to open zip: {}", e))?;
+    let total = zip.len();
+    for index in 0..total {
+        let mut entry = zip.by_index(index).map_err(|e| format!("Failed to extract zip: {}", e))?;
+        // Skip entries that would escape the extraction directory
+        let relative = match entry.enclosed_name() {
+            Some(path) => path.to_path_buf(),
+            None => continue,
+        };
+        let out_path = extract_dir.join(&relative);
+        if entry.is_dir() {
+            fs::create_dir_all(&out_path).map_err(|e| format!("Failed to extract zip: {}", e))?;
+        } else {
+            if let Some(parent) = out_path.parent() {
+                fs::create_dir_all(parent).map_err(|e| format!("Failed to extract zip: {}", e))?;
+            }
+            let mut out = fs::File::create(&out_path).map_err(|e| format!("Failed to extract zip: {}", e))?;
+            std::io::copy(&mut entry, &mut out).map_err(|e| format!("Failed to extract zip: {}", e))?;
+        }
+        let _ = app.emit("java_extract_progress", JavaExtractProgress {
+            file: relative.to_string_lossy().to_string(),
+            extracted: index + 1,
+            total: Some(total),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "windows"))]
+fn extract_archive(archive_path: &Path, extract_dir: &Path, app: &AppHandle) -> Result<(), String> {
+    use flate2::read::GzDecoder;
+    use tar::Archive;
+    let file = fs::File::open(archive_path).map_err(|e| format!("Failed to open tar.gz: {}", e))?;
+    let mut archive = Archive::new(GzDecoder::new(file));
+    fs::create_dir_all(extract_dir).map_err(|e| format!("Failed to extract tar.gz: {}", e))?;
+    let entries = archive.entries().map_err(|e| format!("Failed to extract tar.gz: {}", e))?;
+    for (index, entry) in entries.enumerate() {
+        let mut entry = entry.map_err(|e| format!("Failed to extract tar.gz: {}", e))?;
+        let name = entry.path()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default();
+        // unpack_in refuses entries that would escape the extraction directory
+        entry.unpack_in(extract_dir).map_err(|e| format!("Failed to extract tar.gz: {}", e))?;
+        let _ = app.emit("java_extract_progress", JavaExtractProgress {
+            file: name,
+            extracted: index + 1,
+            total: None,
+        });
+    }
+    Ok(())
+}
+
+pub async fn ensure_java_image_installed(request: &JavaRequest, app: &AppHandle) -> Result<PathBuf, String> {
+    let java_version = request.major;
     // 1. Check for existing Java in our managed dir
-    if let Some(java_bin) = find_existing_java(java_version) {
+    if let Some(java_bin) = find_existing_java(request) {
         return Ok(java_bin);
     }
 
     // 2. Download Adoptium JRE
-    let api_url = format!(
-        "https://api.adoptium.net/v3/assets/feature_releases/{}/ga?architecture={}&image_type={}&os={}&vendor={}&heap_size=normal",
-        java_version, ARCH, IMAGE_TYPE, PLATFORM, JAVA_VENDOR
-    );
-    let client = Client::new();
-    let resp = client.get(&api_url).send().await.map_err(|e| format!("Failed to query Adoptium API: {}", e))?;
-    let json: serde_json::Value = resp.json().await.map_err(|e| format!("Failed to parse Adoptium API response: {}", e))?;
-    let assets = json.as_array().ok_or("Unexpected Adoptium API response")?;
-    let asset = assets.get(0).ok_or("No Adoptium JRE found for this platform")?;
-    let binaries = asset.get("binaries").and_then(|b| b.as_array()).ok_or("No binaries in Adoptium asset")?;
-    let binary = binaries.get(0).ok_or("No binary found in Adoptium asset")?;
+    let asset = fetch_adoptium_release(request).await?;
+    let binary = &asset["binaries"][0];
     let pkg = binary.get("package").ok_or("No package in Adoptium binary")?;
     let link = pkg.get("link").and_then(|l| l.as_str()).ok_or("No download link in Adoptium package")?;
     let filename = pkg.get("name").and_then(|n| n.as_str()).ok_or("No filename in Adoptium package")?;
+    let size = pkg.get("size").and_then(|s| s.as_u64());
+    if let Some(size) = size {
+        println!("Downloading Java {} ({} bytes)", java_version, size);
+    }
 
     // 3. Download the archive
-    let java_dir = java_storage_dir(java_version);
+    let java_dir = java_storage_dir(request);
     fs::create_dir_all(&java_dir).map_err(|e| format!("Failed to create java dir: {}", e))?;
     let archive_path = java_dir.join(filename);
-    let resp = client.get(link).send().await.map_err(|e| format!("Failed to download JRE: {}", e))?;
-    let bytes = resp.bytes().await.map_err(|e| format!("Failed to read JRE bytes: {}", e))?;
-    fs::write(&archive_path, &bytes).map_err(|e| format!("Failed to save JRE archive: {}", e))?;
+    let sha256 = download_to_file(link, &archive_path, size, app).await?;
+    match pkg.get("checksum").and_then(|c| c.as_str()) {
+        Some(expected) if !expected.eq_ignore_ascii_case(&sha256) => {
+            let _ = fs::remove_file(&archive_path);
+            return Err(format!(
+                "Downloaded Java {} archive is corrupt (SHA-256 {} instead of {}); please try again",
+                java_version, sha256, expected
+            ));
+        }
+        Some(_) => {}
+        None => println!("Warning: Adoptium gave no checksum for {}, skipping verification", filename),
+    }
 
     // 4. Extract the archive
     let extract_dir = java_dir.join(filename.replace(".zip", "").replace(".tar.gz", ""));
     if extract_dir.exists() {
         fs::remove_dir_all(&extract_dir).map_err(|e| format!("Failed to clean old java dir: {}", e))?;
     }
-    #[cfg(target_os = "windows")]
-    {
-        let reader = Cursor::new(&bytes);
-        let mut zip = zip::ZipArchive::new(reader).map_err(|e| format!("Failed to open zip: {}", e))?;
-        zip.extract(&extract_dir).map_err(|e| format!("Failed to extract zip: {}", e))?;
-    }
-    #[cfg(not(target_os = "windows"))]
-    {
-        use flate2::read::GzDecoder;
-        use tar::Archive;
-        let reader = Cursor::new(&bytes);
-        let gz = GzDecoder::new(reader);
-        let mut archive = Archive::new(gz);
-        archive.unpack(&extract_dir).map_err(|e| format!("Failed to extract tar.gz: {}", e))?;
+    let _ = app.emit("java_extracting", filename);
+    extract_archive(&archive_path, &extract_dir, app)?;
+    if let Err(e) = fs::remove_file(&archive_path) {
+        println!("Warning: Failed to remove Java archive {}: {}", archive_path.display(), e);
     }
 
     // 5. Find the java binary in the extracted dir
@@ -124,4 +646,24 @@ pub async fn ensure_java_installed(java_version: u32) -> Result<PathBuf, String>
     }
     let java_bin = java_bin.ok_or("Failed to find java binary after extraction")?;
     Ok(java_bin)
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rust_arches_map_to_adoptium_names() {
+        assert_eq!(adoptium_arch("x86_64"), "x64");
+        assert_eq!(adoptium_arch("x86"), "x32");
+        assert_eq!(adoptium_arch("aarch64"), "aarch64");
+        assert_eq!(adoptium_arch("arm"), "arm");
+        assert_eq!(adoptium_arch("s390x"), "s390x");
+        assert_eq!(adoptium_arch("riscv64"), "riscv64");
+    }
+
+    #[test]
+    fn powerpc64_maps_by_endianness() {
+        let expected = if cfg!(target_endian = "little") { "ppc64le" } else { "ppc64" };
+        assert_eq!(adoptium_arch("powerpc64"), expected);
+    }
+}
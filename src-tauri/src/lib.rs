@@ -1,6 +1,6 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::collections::HashMap;
 use std::sync::Mutex;
 use std::sync::Arc;
@@ -19,8 +19,15 @@ mod modpack;
 mod settings;
 mod java;
 mod downloader;
+mod http;
+mod instance;
+mod version;
+mod servers;
+mod mojang;
+mod paths;
+mod storage;
 
-use minecraft::MinecraftLauncher;
+use minecraft::{LaunchResult, MinecraftLauncher};
 use modpack::Modpack;
 use settings::Settings;
 
@@ -70,12 +77,32 @@ pub struct LaunchOptions {
     access_token: Option<String>,
     uuid: Option<String>,
     username: Option<String>,
+    xuid: Option<String>,
+    /// Testing only: launch the pack's mods against this Minecraft version instead
+    /// of the one the pack declares. Unsupported and expected to break packs.
+    #[serde(rename = "minecraftVersionOverride")]
+    minecraft_version_override: Option<String>,
+    /// Working directory of the game process; defaults to the game directory
+    #[serde(rename = "workingDir")]
+    working_dir: Option<PathBuf>,
+    /// Launch the demo, which needs no purchased account
+    #[serde(default)]
+    demo: bool,
+    /// `-javaagent` jars, optionally with `=options`; falls back to the settings
+    #[serde(rename = "javaAgents")]
+    java_agents: Option<Vec<String>>,
+    /// Jars appended to the resolved classpath; falls back to the settings
+    #[serde(rename = "extraClasspath")]
+    extra_classpath: Option<Vec<PathBuf>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct MinecraftVersionRequest {
     #[serde(rename = "minecraftVersion")]
     minecraft_version: String,
+    /// The image type from settings unless one is asked for
+    #[serde(rename = "imageType", default)]
+    image_type: Option<java::ImageType>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -85,66 +112,201 @@ pub struct AuthToken {
     uuid: String,
     name: String,
     expires_at: u64,
+    #[serde(default)]
+    xuid: Option<String>,
+    /// Microsoft refresh token, used to renew the sign-in without the OAuth flow
+    #[serde(default)]
+    refresh_token: Option<String>,
+}
+
+/// An `AuthToken` as handed to the webview; the refresh token stays in the backend store
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AuthSession {
+    access_token: String,
+    client_token: String,
+    uuid: String,
+    name: String,
+    expires_at: u64,
+    xuid: Option<String>,
+}
+
+impl From<AuthToken> for AuthSession {
+    fn from(token: AuthToken) -> Self {
+        AuthSession {
+            access_token: token.access_token,
+            client_token: token.client_token,
+            uuid: token.uuid,
+            name: token.name,
+            expires_at: token.expires_at,
+            xuid: token.xuid,
+        }
+    }
 }
 
 #[derive(Debug)]
 struct XboxLiveAuthResponse {
     token: String,
     user_hash: String,
+    xuid: Option<String>,
 }
 
 // Global auth token storage
 static AUTH_TOKENS: Lazy<Arc<Mutex<HashMap<String, AuthToken>>>> = 
     Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
 
+// Pending sign-ins older than this are abandoned and pruned on startup
+const PKCE_TTL_SECS: u64 = 15 * 60;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct PkceEntry {
+    verifier: String,
+    created_at: u64,
+}
+
+fn current_timestamp() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+// Serializes every read-modify-write of pkce.json so concurrent sign-ins
+// can't overwrite each other's verifiers
+static PKCE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
 // Persistent PKCE code verifier storage
 fn get_pkce_file_path() -> PathBuf {
-    dirs::config_dir()
+    crate::paths::config_dir()
         .expect("Failed to get config directory")
         .join("wise0wl-launcher")
         .join("pkce.json")
 }
 
-fn load_pkce_map() -> HashMap<String, String> {
-    let pkce_path = get_pkce_file_path();
+fn load_pkce_map() -> HashMap<String, PkceEntry> {
+    load_pkce_map_at(&get_pkce_file_path())
+}
+
+fn load_pkce_map_at(pkce_path: &Path) -> HashMap<String, PkceEntry> {
     if pkce_path.exists() {
-        if let Ok(content) = fs::read_to_string(&pkce_path) {
+        if let Ok(content) = fs::read_to_string(pkce_path) {
             if let Ok(map) = serde_json::from_str(&content) {
                 return map;
             }
+            // Older files stored bare verifiers without a timestamp; treat them as stale
+            if let Ok(legacy) = serde_json::from_str::<HashMap<String, String>>(&content) {
+                return legacy
+                    .into_iter()
+                    .map(|(state, verifier)| (state, PkceEntry { verifier, created_at: 0 }))
+                    .collect();
+            }
         }
     }
     HashMap::new()
 }
 
-fn save_pkce_map(map: &HashMap<String, String>) -> Result<(), String> {
-    let pkce_path = get_pkce_file_path();
+fn save_pkce_map_at(pkce_path: &Path, map: &HashMap<String, PkceEntry>) -> Result<(), String> {
     if let Some(parent) = pkce_path.parent() {
         fs::create_dir_all(parent)
             .map_err(|e| format!("Failed to create PKCE directory: {}", e))?;
     }
     let content = serde_json::to_string_pretty(map)
         .map_err(|e| format!("Failed to serialize PKCE map: {}", e))?;
-    fs::write(&pkce_path, content)
-        .map_err(|e| format!("Failed to write PKCE file: {}", e))
+    // Write a temp file and rename it over the old one so readers never see a partial file
+    let tmp_path = pkce_path.with_extension("json.tmp");
+    fs::write(&tmp_path, content)
+        .map_err(|e| format!("Failed to write PKCE file: {}", e))?;
+    fs::rename(&tmp_path, pkce_path)
+        .map_err(|e| format!("Failed to replace PKCE file: {}", e))
+}
+
+// Load, modify and save the PKCE map while holding the lock
+fn update_pkce_map<R>(f: impl FnOnce(&mut HashMap<String, PkceEntry>) -> R) -> Result<R, String> {
+    update_pkce_map_at(&get_pkce_file_path(), f)
+}
+
+fn update_pkce_map_at<R>(pkce_path: &Path, f: impl FnOnce(&mut HashMap<String, PkceEntry>) -> R) -> Result<R, String> {
+    let _guard = PKCE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut map = load_pkce_map_at(pkce_path);
+    let result = f(&mut map);
+    save_pkce_map_at(pkce_path, &map)?;
+    Ok(result)
 }
 
 fn add_pkce_verifier(state: &str, verifier: &str) -> Result<(), String> {
-    let mut map = load_pkce_map();
-    map.insert(state.to_string(), verifier.to_string());
-    save_pkce_map(&map)
+    update_pkce_map(|map| {
+        map.insert(state.to_string(), PkceEntry {
+            verifier: verifier.to_string(),
+            created_at: current_timestamp(),
+        });
+    })
 }
 
 fn get_and_remove_pkce_verifier(state: &str) -> Option<String> {
-    let mut map = load_pkce_map();
-    let verifier = map.remove(state);
-    let _ = save_pkce_map(&map);
-    verifier
+    update_pkce_map(|map| map.remove(state))
+        .ok()
+        .flatten()
+        .filter(|e| is_fresh(e, current_timestamp()))
+        .map(|e| e.verifier)
+}
+
+// Check the query parameters of an OAuth redirect before handing it to the UI:
+// the provider must not report an error and the state must be one we issued
+fn validate_oauth_callback(params: &HashMap<String, String>) -> Result<(), String> {
+    let pending = {
+        let _guard = PKCE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        load_pkce_map()
+    };
+    check_oauth_callback(params, &pending, current_timestamp())
+}
+
+// A state is only accepted while its sign-in is pending and not yet stale
+fn check_oauth_callback(
+    params: &HashMap<String, String>,
+    pending: &HashMap<String, PkceEntry>,
+    now: u64,
+) -> Result<(), String> {
+    if let Some(error) = params.get("error") {
+        let description = params
+            .get("error_description")
+            .map(String::as_str)
+            .unwrap_or("Unknown error");
+        return Err(format!("Microsoft sign-in failed: {} - {}", error, description));
+    }
+    let state = params.get("state").ok_or("OAuth callback is missing the state parameter")?;
+    if !pending.get(state).is_some_and(|entry| is_fresh(entry, now)) {
+        return Err("OAuth callback state does not match a pending sign-in. Please try logging in again.".to_string());
+    }
+    if !params.contains_key("code") {
+        return Err("OAuth callback is missing the authorization code".to_string());
+    }
+    Ok(())
+}
+
+fn is_fresh(entry: &PkceEntry, now: u64) -> bool {
+    now.saturating_sub(entry.created_at) < PKCE_TTL_SECS
+}
+
+// Remove entries older than the TTL, returning how many were dropped
+fn prune_stale_verifiers(map: &mut HashMap<String, PkceEntry>, now: u64) -> usize {
+    let before = map.len();
+    map.retain(|_, entry| is_fresh(entry, now));
+    before - map.len()
+}
+
+// Drop verifiers from sign-ins that were never completed
+fn prune_pkce_verifiers() {
+    let now = current_timestamp();
+    let pruned = update_pkce_map(|map| prune_stale_verifiers(map, now));
+    match pruned {
+        Ok(0) => {}
+        Ok(count) => println!("Pruned {} stale PKCE verifiers", count),
+        Err(e) => println!("Warning: Failed to save pruned PKCE verifiers: {}", e),
+    }
 }
 
 // Token storage functions
 fn get_tokens_file_path() -> PathBuf {
-    dirs::config_dir()
+    crate::paths::config_dir()
         .expect("Failed to get config directory")
         .join("wise0wl-launcher")
         .join("tokens.json")
@@ -210,6 +372,11 @@ async fn get_modpacks() -> Result<Vec<Modpack>, String> {
     modpack::get_all_modpacks().map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn validate_modpacks() -> Result<modpack::LoadedModpacks, String> {
+    modpack::load_modpacks()
+}
+
 #[tauri::command]
 async fn get_settings() -> Result<Settings, String> {
     settings::load_settings().map_err(|e| e.to_string())
@@ -217,13 +384,166 @@ async fn get_settings() -> Result<Settings, String> {
 
 #[tauri::command]
 async fn save_settings(settings: Settings) -> Result<(), String> {
-    settings::save_settings(&settings).map_err(|e| e.to_string())
+    // Catch read-only or missing drives now rather than halfway through a download
+    let dir_check = downloader::check_game_directory(&settings.game_directory);
+    if let Some(error) = dir_check.error {
+        return Err(error);
+    }
+    settings::save_settings(&settings).map_err(|e| e.to_string())?;
+    // Network options may have changed
+    http::reset_client();
+    Ok(())
+}
+
+#[tauri::command]
+async fn reset_settings() -> Result<Settings, String> {
+    let settings = settings::reset_settings()?;
+    http::reset_client();
+    Ok(settings)
+}
+
+// Fill in the XUID from the stored token if the UI didn't pass it
+fn fill_xuid_from_store(options: &mut LaunchOptions) {
+    if options.xuid.is_none() {
+        if let Some(uuid) = &options.uuid {
+            let tokens = AUTH_TOKENS.lock().unwrap();
+            options.xuid = tokens.get(uuid).and_then(|t| t.xuid.clone());
+        }
+    }
+}
+
+#[tauri::command]
+async fn launch_minecraft(app: tauri::AppHandle, mut options: LaunchOptions) -> Result<LaunchResult, String> {
+    fill_xuid_from_store(&mut options);
+    let launcher = MinecraftLauncher::for_launch(&options);
+    launcher.launch(options, &app).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-async fn launch_minecraft(options: LaunchOptions) -> Result<(), String> {
+async fn launch_ephemeral(app: tauri::AppHandle, modpack: Modpack, mut options: LaunchOptions) -> Result<LaunchResult, String> {
+    fill_xuid_from_store(&mut options);
+    let launcher = MinecraftLauncher::for_launch(&options);
+    launcher.launch_ephemeral(modpack, options, &app).await
+}
+
+#[tauri::command]
+async fn launch_and_wait(app: tauri::AppHandle, mut options: LaunchOptions) -> Result<minecraft::GameRunResult, String> {
+    fill_xuid_from_store(&mut options);
+    let launcher = MinecraftLauncher::for_launch(&options);
+    launcher.launch_and_wait(options, &app).await
+}
+
+/// Outcome of `verify_and_launch`: the checks that ran and, if they all passed, the launch
+#[derive(Debug, Serialize)]
+pub struct VerifyLaunchReport {
+    checks: Vec<minecraft::IntegrityCheck>,
+    launched: bool,
+    result: Option<LaunchResult>,
+}
+
+/// Check the instance, Java and the sign-in, and launch only when everything passes
+#[tauri::command]
+async fn verify_and_launch(app: tauri::AppHandle, mut options: LaunchOptions) -> Result<VerifyLaunchReport, String> {
+    fill_xuid_from_store(&mut options);
+    let launcher = MinecraftLauncher::for_launch(&options);
+    let mut checks = launcher.verify_instance(&options).await?;
+    checks.push(match (&options.access_token, options.demo) {
+        (_, true) => minecraft::IntegrityCheck::new("token", true, "The demo needs no account"),
+        (None, false) => minecraft::IntegrityCheck::new("token", false, "Not signed in"),
+        (Some(token), false) => match validate_minecraft_token(token).await {
+            Ok(()) => minecraft::IntegrityCheck::new("token", true, "Access token is valid"),
+            Err(e) => minecraft::IntegrityCheck::new("token", false, format!("{}; please sign in again", e)),
+        },
+    });
+
+    if checks.iter().any(|check| !check.ok) {
+        return Ok(VerifyLaunchReport { checks, launched: false, result: None });
+    }
+    let result = launcher.launch(options, &app).await?;
+    Ok(VerifyLaunchReport { checks, launched: true, result: Some(result) })
+}
+
+#[tauri::command]
+async fn get_classpath(modpack_id: String) -> Result<Vec<PathBuf>, String> {
     let launcher = MinecraftLauncher::new();
-    launcher.launch(options).await.map_err(|e| e.to_string())
+    launcher.resolve_classpath(&modpack_id).await
+}
+
+#[tauri::command]
+async fn list_installed_versions() -> Result<Vec<minecraft::VersionDiskInfo>, String> {
+    let modpacks = modpack::get_all_modpacks()?;
+    MinecraftLauncher::new().list_installed_versions(&modpacks)
+}
+
+#[tauri::command]
+async fn remove_version(id: String) -> Result<(), String> {
+    let modpacks = modpack::get_all_modpacks()?;
+    MinecraftLauncher::new().remove_version(&id, &modpacks)
+}
+
+#[tauri::command]
+async fn explain_launch_args(mut options: LaunchOptions, modpack: Modpack) -> Result<minecraft::LaunchArgExplanation, String> {
+    fill_xuid_from_store(&mut options);
+    let launcher = MinecraftLauncher::for_launch(&options);
+    launcher.explain_launch_args(options, &modpack).await
+}
+
+#[tauri::command]
+async fn list_running_instances() -> Result<Vec<minecraft::RunningInstance>, String> {
+    Ok(minecraft::running_instances())
+}
+
+/// Same as `kill_game`, for the running instances list
+#[tauri::command]
+async fn stop_instance(modpack_id: String) -> Result<(), String> {
+    minecraft::kill_game(&modpack_id)
+}
+
+/// Output of the modpack's latest launch, for a log window opened after the game started
+#[tauri::command]
+async fn get_game_log(modpack_id: String) -> Result<Vec<minecraft::GameLogLine>, String> {
+    Ok(minecraft::game_log(&modpack_id))
+}
+
+#[tauri::command]
+async fn is_game_running(modpack_id: String) -> Result<bool, String> {
+    Ok(minecraft::is_game_running(&modpack_id))
+}
+
+#[tauri::command]
+async fn kill_game(modpack_id: String) -> Result<(), String> {
+    minecraft::kill_game(&modpack_id)
+}
+
+#[tauri::command]
+async fn cancel_launch(modpack_id: String) -> Result<(), String> {
+    minecraft::cancel_launch(&modpack_id)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    rss_bytes: u64,
+    cpu_percent: f32,
+}
+
+#[tauri::command]
+async fn get_instance_resource_usage(modpack_id: String) -> Result<ResourceUsage, String> {
+    use sysinfo::{Pid, System};
+    let pid = minecraft::running_game_pid(&modpack_id)
+        .ok_or(format!("{} is not running", modpack_id))?;
+    let pid = Pid::from_u32(pid);
+    let mut system = System::new();
+    // CPU usage is measured between two refreshes
+    system.refresh_process(pid);
+    tokio::time::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL).await;
+    if !system.refresh_process(pid) {
+        return Err(format!("{} is not running", modpack_id));
+    }
+    let process = system.process(pid).ok_or(format!("{} is not running", modpack_id))?;
+    Ok(ResourceUsage {
+        rss_bytes: process.memory(),
+        cpu_percent: process.cpu_usage(),
+    })
 }
 
 fn required_java_version(minecraft_version: &str) -> u32 {
@@ -242,9 +562,41 @@ fn required_java_version(minecraft_version: &str) -> u32 {
 }
 
 #[tauri::command]
-async fn ensure_java_installed_for_mc(request: MinecraftVersionRequest) -> Result<String, String> {
+async fn ensure_java_installed_for_mc(app: tauri::AppHandle, request: MinecraftVersionRequest) -> Result<String, String> {
     let java_version = required_java_version(&request.minecraft_version);
-    java::ensure_java_installed(java_version).await
+    let mut java_request = java::JavaRequest::from_settings(java_version);
+    java_request.image_type = request.image_type.unwrap_or(java_request.image_type);
+    java::ensure_java_image_installed(&java_request, &app).await
+        .map(|p| p.to_string_lossy().to_string())
+}
+
+/// Report which Java a launch of the modpack would use, without installing anything
+#[tauri::command]
+async fn resolve_java_for_launch(modpack_id: String) -> Result<java::JavaResolution, String> {
+    let modpack = modpack::get_all_modpacks()?
+        .into_iter()
+        .find(|m| m.id == modpack_id)
+        .ok_or(format!("Modpack {} not found", modpack_id))?;
+    let settings = settings::load_settings()?;
+    Ok(java::resolve_java(None, &settings.java_path, required_java_version(&modpack.minecraft_version)))
+}
+
+/// Every Java found on this machine, newest first, for the settings Java picker
+#[tauri::command]
+async fn enumerate_java_installations() -> Result<Vec<java::JavaInstallation>, String> {
+    Ok(java::enumerate_java_installations())
+}
+
+#[tauri::command]
+async fn java_download_size(major: u32, image_type: Option<java::ImageType>) -> Result<u64, String> {
+    let mut request = java::JavaRequest::from_settings(major);
+    request.image_type = image_type.unwrap_or(request.image_type);
+    java::java_download_size(&request).await
+}
+
+#[tauri::command]
+async fn ensure_jdk_installed(app: tauri::AppHandle, major: u32) -> Result<String, String> {
+    java::ensure_jdk_installed(major, &app).await
         .map(|p| p.to_string_lossy().to_string())
 }
 
@@ -282,13 +634,23 @@ async fn get_microsoft_auth_url() -> Result<MicrosoftAuthUrl, String> {
 }
 
 #[tauri::command]
-async fn handle_microsoft_callback(code: String, state: String) -> Result<AuthToken, String> {
+async fn cancel_auth(state: String) -> Result<(), String> {
+    if update_pkce_map(|map| map.remove(&state))?.is_some() {
+        println!("Cancelled pending sign-in");
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn handle_microsoft_callback(code: String, state: String) -> Result<AuthSession, String> {
     check_oauth_credentials()?;
     println!("Received OAuth code: {}", &code[..std::cmp::min(20, code.len())]);
     // For public clients, we need to use PKCE and no client secret
     let token_url = "https://login.microsoftonline.com/consumers/oauth2/v2.0/token";
-    // Get the code verifier that was stored when generating the auth URL
-    let code_verifier = get_and_remove_pkce_verifier(&state).ok_or("No code verifier found. Please try logging in again.")?;
+    // Get the code verifier that was stored when generating the auth URL; this
+    // also rejects any state we didn't issue
+    let code_verifier = get_and_remove_pkce_verifier(&state)
+        .ok_or("OAuth state does not match a pending sign-in. Please try logging in again.")?;
     let client_id = get_microsoft_client_id();
     let redirect_uri = get_oauth_redirect_uri();
     let grant_type = "authorization_code".to_string();
@@ -301,7 +663,7 @@ async fn handle_microsoft_callback(code: String, state: String) -> Result<AuthTo
         ("code_verifier", &code_verifier),
     ];
     println!("Exchanging code for token...");
-    let client = reqwest::Client::new();
+    let client = http::client();
     let token_resp = client.post(token_url)
         .form(&token_params)
         .send()
@@ -322,58 +684,177 @@ async fn handle_microsoft_callback(code: String, state: String) -> Result<AuthTo
         .as_str()
         .ok_or("No access token in response")?;
     println!("Successfully obtained Microsoft access token");
+    let refresh_token = token_data["refresh_token"].as_str().map(str::to_string);
+    let auth_token = complete_sign_in(access_token, refresh_token).await?;
+    println!("Successfully authenticated user: {}", auth_token.name);
+    Ok(auth_token.into())
+}
+
+/// Run the Xbox Live/XSTS/Minecraft chain for a Microsoft access token and store the result
+async fn complete_sign_in(ms_access_token: &str, refresh_token: Option<String>) -> Result<AuthToken, String> {
     // Get Xbox Live token
-    let xbox_auth_response = get_xbox_live_token(access_token).await?;
+    let xbox_auth_response = get_xbox_live_token(ms_access_token).await?;
     // Get Minecraft token
     let minecraft_token = get_minecraft_token(&xbox_auth_response).await?;
     // Get user profile
-    let profile = get_minecraft_profile(&minecraft_token).await?;
+    let profile = get_minecraft_profile(&minecraft_token.access_token).await?;
     let auth_token = AuthToken {
-        access_token: minecraft_token,
+        access_token: minecraft_token.access_token,
         client_token: "wise0wl-launcher".to_string(),
         uuid: profile.id.clone(),
         name: profile.name.clone(),
-        expires_at: std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs() + 3600, // 1 hour
+        expires_at: current_timestamp() + minecraft_token.expires_in,
+        xuid: xbox_auth_response.xuid.clone(),
+        refresh_token,
     };
     // Store the token
-    {
-        let mut tokens = AUTH_TOKENS.lock().unwrap();
-        tokens.insert(profile.id.clone(), auth_token.clone());
-        // Save to persistent storage
-        if let Err(e) = save_tokens_to_file(&tokens) {
-            println!("Warning: Failed to save token to persistent storage: {}", e);
-        }
+    let mut tokens = AUTH_TOKENS.lock().unwrap();
+    tokens.insert(profile.id.clone(), auth_token.clone());
+    // Save to persistent storage
+    if let Err(e) = save_tokens_to_file(&tokens) {
+        println!("Warning: Failed to save token to persistent storage: {}", e);
     }
-    println!("Successfully authenticated user: {}", profile.name);
     Ok(auth_token)
 }
 
-async fn get_xbox_live_token(access_token: &str) -> Result<XboxLiveAuthResponse, String> {
-    println!("Getting Xbox Live token with access token: {}", &access_token[..std::cmp::min(20, access_token.len())]);
-    
-    let client = reqwest::Client::new();
-    let xbox_resp = client.post("https://user.auth.xboxlive.com/user/authenticate")
-        .json(&serde_json::json!({
-            "Properties": {
-                "AuthMethod": "RPS",
-                "SiteName": "user.auth.xboxlive.com",
-                "RpsTicket": format!("d={}", access_token)
-            },
-            "RelyingParty": "http://auth.xboxlive.com",
-            "TokenType": "JWT"
-        }))
+/// Renew a sign-in with its stored refresh token. Returns `None` when Microsoft
+/// rejects the refresh token (`invalid_grant`), meaning the player must sign in again.
+async fn refresh_minecraft_token(token: &AuthToken) -> Result<Option<AuthToken>, String> {
+    let refresh_token = match &token.refresh_token {
+        Some(refresh_token) => refresh_token,
+        None => return Ok(None),
+    };
+    check_oauth_credentials()?;
+    println!("Refreshing sign-in for {}", token.name);
+    let token_url = "https://login.microsoftonline.com/consumers/oauth2/v2.0/token";
+    let client_id = get_microsoft_client_id();
+    let scopes = get_oauth_scopes();
+    let token_params = [
+        ("client_id", client_id.as_str()),
+        ("grant_type", "refresh_token"),
+        ("refresh_token", refresh_token.as_str()),
+        ("scope", scopes.as_str()),
+    ];
+    let token_resp = http::client().post(token_url)
+        .form(&token_params)
         .send()
         .await
-        .map_err(|e| format!("Failed to get Xbox Live token: {}", e))?;
-    
-    println!("Xbox Live response status: {}", xbox_resp.status());
-    
-    let xbox_data: serde_json::Value = xbox_resp.json()
+        .map_err(|e| format!("Failed to refresh token: {}", e))?;
+    let token_data: serde_json::Value = token_resp.json()
         .await
-        .map_err(|e| format!("Failed to parse Xbox Live response: {}", e))?;
+        .map_err(|e| format!("Failed to parse token refresh response: {}", e))?;
+    if let Some(error) = token_data["error"].as_str() {
+        if error == "invalid_grant" {
+            println!("Refresh token for {} was rejected; a new sign-in is needed", token.name);
+            return Ok(None);
+        }
+        let error_description = token_data["error_description"]
+            .as_str()
+            .unwrap_or("Unknown error");
+        return Err(format!("OAuth error: {} - {}", error, error_description));
+    }
+    let access_token = token_data["access_token"]
+        .as_str()
+        .ok_or("No access token in refresh response")?;
+    // Microsoft may rotate the refresh token
+    let refresh_token = token_data["refresh_token"]
+        .as_str()
+        .map(str::to_string)
+        .or_else(|| token.refresh_token.clone());
+    let refreshed = complete_sign_in(access_token, refresh_token).await?;
+    println!("Refreshed sign-in for {}", refreshed.name);
+    Ok(Some(refreshed))
+}
+
+/// Forget a stored token and persist the change
+fn remove_stored_token(uuid: &str) {
+    let mut tokens = AUTH_TOKENS.lock().unwrap();
+    tokens.remove(uuid);
+    if let Err(e) = save_tokens_to_file(&tokens) {
+        println!("Warning: Failed to save token removal to persistent storage: {}", e);
+    }
+}
+
+/// Try to renew an expired or rejected token, removing it when it can't be renewed
+async fn refresh_or_remove(uuid: &str, token: &AuthToken) -> Result<Option<AuthToken>, String> {
+    match refresh_minecraft_token(token).await? {
+        Some(refreshed) => Ok(Some(refreshed)),
+        None => {
+            remove_stored_token(uuid);
+            Ok(None)
+        }
+    }
+}
+
+/// Attempts per step of the sign-in chain before giving up on transient failures
+const AUTH_ATTEMPTS: u32 = 3;
+
+/// Why a sign-in step failed
+enum AuthStepError {
+    /// Network trouble, a 5xx or rate limiting that may go away on retry
+    Transient(String),
+    /// The service rejected the request; retrying won't help
+    Fatal(String),
+}
+
+/// Send one sign-in request and parse its JSON body
+async fn try_auth_request(step: &str, request: reqwest::RequestBuilder) -> Result<serde_json::Value, AuthStepError> {
+    let resp = request.send()
+        .await
+        .map_err(|e| AuthStepError::Transient(format!("Failed to get {}: {}", step, e)))?;
+    let status = resp.status();
+    println!("{} response status: {}", step, status);
+    if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return Err(AuthStepError::Transient(format!("{} request returned HTTP {}", step, status)));
+    }
+    if !status.is_success() {
+        // XSTS explains rejections (no Xbox account, child account) in the body
+        let body = resp.text().await.unwrap_or_default();
+        return Err(AuthStepError::Fatal(format!("{} request was rejected (HTTP {}): {}", step, status, body)));
+    }
+    resp.json()
+        .await
+        .map_err(|e| AuthStepError::Transient(format!("Failed to parse {} response: {}", step, e)))
+}
+
+/// Run a sign-in step, retrying transient failures with backoff so a blip
+/// doesn't send the player back through OAuth
+async fn auth_request<F>(step: &str, build: F) -> Result<serde_json::Value, String>
+where
+    F: Fn(&reqwest::Client) -> reqwest::RequestBuilder,
+{
+    let mut attempt = 1;
+    loop {
+        match try_auth_request(step, build(&http::client())).await {
+            Ok(data) => return Ok(data),
+            Err(AuthStepError::Fatal(e)) => return Err(e),
+            Err(AuthStepError::Transient(e)) if attempt < AUTH_ATTEMPTS => {
+                println!("Warning: {} (attempt {}), retrying", e, attempt);
+                tokio::time::sleep(std::time::Duration::from_secs(1 << (attempt - 1))).await;
+                attempt += 1;
+            }
+            Err(AuthStepError::Transient(e)) => {
+                return Err(format!("{} after {} attempts; please try again later", e, attempt));
+            }
+        }
+    }
+}
+
+async fn get_xbox_live_token(access_token: &str) -> Result<XboxLiveAuthResponse, String> {
+    println!("Getting Xbox Live token with access token: {}", &access_token[..std::cmp::min(20, access_token.len())]);
+    
+    let xbox_data = auth_request("Xbox Live token", |client| {
+        client.post("https://user.auth.xboxlive.com/user/authenticate")
+            .json(&serde_json::json!({
+                "Properties": {
+                    "AuthMethod": "RPS",
+                    "SiteName": "user.auth.xboxlive.com",
+                    "RpsTicket": format!("d={}", access_token)
+                },
+                "RelyingParty": "http://auth.xboxlive.com",
+                "TokenType": "JWT"
+            }))
+    }).await?;
     
     println!("Xbox Live response data: {:?}", xbox_data);
     
@@ -382,24 +863,17 @@ async fn get_xbox_live_token(access_token: &str) -> Result<XboxLiveAuthResponse,
         .ok_or("No Xbox Live token in response")?;
     
     // Get XSTS token
-    let xsts_resp = client.post("https://xsts.auth.xboxlive.com/xsts/authorize")
-        .json(&serde_json::json!({
-            "Properties": {
-                "SandboxId": "RETAIL",
-                "UserTokens": [xbox_token]
-            },
-            "RelyingParty": "rp://api.minecraftservices.com/",
-            "TokenType": "JWT"
-        }))
-        .send()
-        .await
-        .map_err(|e| format!("Failed to get XSTS token: {}", e))?;
-    
-    println!("XSTS response status: {}", xsts_resp.status());
-    
-    let xsts_data: serde_json::Value = xsts_resp.json()
-        .await
-        .map_err(|e| format!("Failed to parse XSTS response: {}", e))?;
+    let xsts_data = auth_request("XSTS token", |client| {
+        client.post("https://xsts.auth.xboxlive.com/xsts/authorize")
+            .json(&serde_json::json!({
+                "Properties": {
+                    "SandboxId": "RETAIL",
+                    "UserTokens": [xbox_token]
+                },
+                "RelyingParty": "rp://api.minecraftservices.com/",
+                "TokenType": "JWT"
+            }))
+    }).await?;
     
     println!("XSTS response data: {:?}", xsts_data);
     
@@ -411,31 +885,65 @@ async fn get_xbox_live_token(access_token: &str) -> Result<XboxLiveAuthResponse,
         .as_str()
         .ok_or("No user hash in XSTS response")?;
     
+    // The Minecraft relying party doesn't return the XUID, so ask for an
+    // Xbox Live XSTS token as well. Failure here isn't fatal for login.
+    let xuid = get_xbox_xuid(&http::client(), xbox_token).await;
+    if xuid.is_none() {
+        println!("Warning: Could not determine XUID for this account");
+    }
+
     Ok(XboxLiveAuthResponse {
         token: xsts_token.to_string(),
         user_hash: user_hash.to_string(),
+        xuid,
     })
 }
 
-async fn get_minecraft_token(xbox_auth: &XboxLiveAuthResponse) -> Result<String, String> {
-    let client = reqwest::Client::new();
-    let minecraft_resp = client.post("https://api.minecraftservices.com/authentication/login_with_xbox")
+async fn get_xbox_xuid(client: &reqwest::Client, xbox_token: &str) -> Option<String> {
+    let resp = client.post("https://xsts.auth.xboxlive.com/xsts/authorize")
         .json(&serde_json::json!({
-            "identityToken": format!("XBL3.0 x={};{}", xbox_auth.user_hash, xbox_auth.token)
+            "Properties": {
+                "SandboxId": "RETAIL",
+                "UserTokens": [xbox_token]
+            },
+            "RelyingParty": "http://xboxlive.com",
+            "TokenType": "JWT"
         }))
         .send()
         .await
-        .map_err(|e| format!("Failed to get Minecraft token: {}", e))?;
-    
-    let minecraft_data: serde_json::Value = minecraft_resp.json()
-        .await
-        .map_err(|e| format!("Failed to parse Minecraft response: {}", e))?;
+        .ok()?;
+    let data: serde_json::Value = resp.json().await.ok()?;
+    data["DisplayClaims"]["xui"][0]["xid"]
+        .as_str()
+        .map(|s| s.to_string())
+}
+
+/// Lifetime assumed for a Minecraft token whose response has no `expires_in`
+const DEFAULT_TOKEN_LIFETIME_SECS: u64 = 3600;
+
+/// A Minecraft access token and how many seconds it stays valid
+struct MinecraftToken {
+    access_token: String,
+    expires_in: u64,
+}
+
+async fn get_minecraft_token(xbox_auth: &XboxLiveAuthResponse) -> Result<MinecraftToken, String> {
+    let minecraft_data = auth_request("Minecraft token", |client| {
+        client.post("https://api.minecraftservices.com/authentication/login_with_xbox")
+            .json(&serde_json::json!({
+                "identityToken": format!("XBL3.0 x={};{}", xbox_auth.user_hash, xbox_auth.token)
+            }))
+    }).await?;
     
     let access_token = minecraft_data["access_token"]
         .as_str()
         .ok_or("No Minecraft access token in response")?;
     
-    Ok(access_token.to_string())
+    let expires_in = minecraft_data["expires_in"]
+        .as_u64()
+        .unwrap_or(DEFAULT_TOKEN_LIFETIME_SECS);
+
+    Ok(MinecraftToken { access_token: access_token.to_string(), expires_in })
 }
 
 #[derive(Deserialize)]
@@ -445,71 +953,86 @@ struct MinecraftProfile {
 }
 
 async fn get_minecraft_profile(access_token: &str) -> Result<MinecraftProfile, String> {
-    let client = reqwest::Client::new();
-    let profile_resp = client.get("https://api.minecraftservices.com/minecraft/profile")
-        .header("Authorization", format!("Bearer {}", access_token))
-        .send()
-        .await
-        .map_err(|e| format!("Failed to get Minecraft profile: {}", e))?;
+    let profile_data = auth_request("Minecraft profile", |client| {
+        client.get("https://api.minecraftservices.com/minecraft/profile")
+            .header("Authorization", format!("Bearer {}", access_token))
+    }).await?;
     
-    let profile: MinecraftProfile = profile_resp.json()
-        .await
-        .map_err(|e| format!("Failed to parse profile response: {}", e))?;
-    
-    Ok(profile)
-}
-
-#[tauri::command]
-async fn get_auth_token(uuid: String) -> Result<Option<AuthToken>, String> {
-    // First, get the token and check expiration
-    let token = {
-        let tokens = AUTH_TOKENS.lock().unwrap();
-        if let Some(token) = tokens.get(&uuid) {
-            // Check if token is expired
-            let current_time = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs();
-            
-            if current_time >= token.expires_at {
-                // Token is expired, remove it
-                drop(tokens); // Release the lock
-                let mut tokens = AUTH_TOKENS.lock().unwrap();
-                tokens.remove(&uuid);
-                // Save changes to persistent storage
-                if let Err(e) = save_tokens_to_file(&tokens) {
-                    println!("Warning: Failed to save token removal to persistent storage: {}", e);
-                }
-                return Ok(None);
-            }
-            
-            Some(token.clone())
-        } else {
-            None
-        }
+    serde_json::from_value(profile_data)
+        .map_err(|e| format!("Failed to parse profile response: {}", e))
+}
+
+#[tauri::command]
+async fn get_auth_token(uuid: String) -> Result<Option<AuthSession>, String> {
+    let token = match AUTH_TOKENS.lock().unwrap().get(&uuid).cloned() {
+        Some(token) => token,
+        None => return Ok(None),
     };
-    
-    // If we have a token, validate it with Minecraft services
-    if let Some(token) = token {
-        if let Err(_) = validate_minecraft_token(&token.access_token).await {
-            // Token is invalid, remove it
-            let mut tokens = AUTH_TOKENS.lock().unwrap();
-            tokens.remove(&uuid);
-            // Save changes to persistent storage
-            if let Err(e) = save_tokens_to_file(&tokens) {
-                println!("Warning: Failed to save invalid token removal to persistent storage: {}", e);
-            }
-            return Ok(None);
+
+    // An expired token is renewed with the refresh token rather than dropped
+    if current_timestamp() >= token.expires_at {
+        return Ok(refresh_or_remove(&uuid, &token).await?.map(AuthSession::from));
+    }
+
+    // Validate it with Minecraft services; a revoked token gets the same treatment
+    if validate_minecraft_token(&token.access_token).await.is_err() {
+        return Ok(refresh_or_remove(&uuid, &token).await?.map(AuthSession::from));
+    }
+
+    Ok(Some(token.into()))
+}
+
+/// Health of a stored sign-in, for support to read when a player keeps getting logged out
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuthDiagnosis {
+    has_token: bool,
+    name: Option<String>,
+    expires_at: Option<u64>,
+    expired: bool,
+    /// Whether Minecraft services accepted the access token; `None` when it wasn't checked
+    token_valid: Option<bool>,
+    validation_error: Option<String>,
+    has_refresh_token: bool,
+}
+
+// Unlike get_auth_token this never removes the stored token, so the report
+// describes the state the player is actually in
+#[tauri::command]
+async fn diagnose_auth(uuid: String) -> Result<AuthDiagnosis, String> {
+    let token = AUTH_TOKENS.lock().unwrap().get(&uuid).cloned();
+    let token = match token {
+        Some(token) => token,
+        None => {
+            return Ok(AuthDiagnosis {
+                has_token: false,
+                name: None,
+                expires_at: None,
+                expired: false,
+                token_valid: None,
+                validation_error: None,
+                has_refresh_token: false,
+            })
         }
-        
-        Ok(Some(token))
+    };
+    let expired = current_timestamp() >= token.expires_at;
+    let validation = if expired {
+        None
     } else {
-        Ok(None)
-    }
+        Some(validate_minecraft_token(&token.access_token).await)
+    };
+    Ok(AuthDiagnosis {
+        has_token: true,
+        name: Some(token.name),
+        expires_at: Some(token.expires_at),
+        expired,
+        token_valid: validation.as_ref().map(|v| v.is_ok()),
+        validation_error: validation.and_then(|v| v.err()),
+        has_refresh_token: token.refresh_token.is_some(),
+    })
 }
 
 async fn validate_minecraft_token(access_token: &str) -> Result<(), String> {
-    let client = reqwest::Client::new();
+    let client = http::client();
     let profile_resp = client.get("https://api.minecraftservices.com/minecraft/profile")
         .header("Authorization", format!("Bearer {}", access_token))
         .send()
@@ -523,6 +1046,137 @@ async fn validate_minecraft_token(access_token: &str) -> Result<(), String> {
     Ok(())
 }
 
+// The official launcher's own .minecraft, independent of portable mode
+fn get_official_launcher_dir() -> Option<PathBuf> {
+    if cfg!(target_os = "windows") {
+        dirs::data_dir().map(|d| d.join(".minecraft"))
+    } else if cfg!(target_os = "macos") {
+        dirs::data_dir().map(|d| d.join("minecraft"))
+    } else {
+        dirs::home_dir().map(|d| d.join(".minecraft"))
+    }
+}
+
+// Convert an ISO-8601 UTC timestamp ("2024-05-01T12:30:00.000Z") to Unix seconds
+fn parse_iso8601_utc(value: &str) -> Option<u64> {
+    let (date, time) = value.split_once('T')?;
+    let mut date_parts = date.split('-').map(|p| p.parse::<i64>());
+    let (year, month, day) = (date_parts.next()?.ok()?, date_parts.next()?.ok()?, date_parts.next()?.ok()?);
+    let time = time.trim_end_matches('Z');
+    let time = time.split('.').next()?;
+    let mut time_parts = time.split(':').map(|p| p.parse::<i64>());
+    let (hour, minute, second) = (time_parts.next()?.ok()?, time_parts.next()?.ok()?, time_parts.next()?.ok()?);
+    // Days since the epoch for a proleptic Gregorian date (Howard Hinnant's algorithm)
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+    u64::try_from(days * 86400 + hour * 3600 + minute * 60 + second).ok()
+}
+
+// Read the signed-in accounts from the official launcher's account files
+fn read_official_accounts() -> Vec<AuthToken> {
+    let launcher_dir = match get_official_launcher_dir() {
+        Some(dir) => dir,
+        None => return Vec::new(),
+    };
+    let now = current_timestamp();
+    let mut accounts = Vec::new();
+    for file_name in ["launcher_accounts.json", "launcher_accounts_microsoft_store.json"] {
+        let content = match fs::read_to_string(launcher_dir.join(file_name)) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+        let json: serde_json::Value = match serde_json::from_str(&content) {
+            Ok(json) => json,
+            Err(e) => {
+                println!("Warning: Failed to parse {}: {}", file_name, e);
+                continue;
+            }
+        };
+        let entries = match json["accounts"].as_object() {
+            Some(entries) => entries,
+            None => continue,
+        };
+        for account in entries.values() {
+            let access_token = account["accessToken"].as_str().unwrap_or("");
+            let uuid = account["minecraftProfile"]["id"].as_str().unwrap_or("");
+            let name = account["minecraftProfile"]["name"].as_str().unwrap_or("");
+            if access_token.is_empty() || uuid.is_empty() {
+                continue;
+            }
+            let expires_at = account["accessTokenExpiresAt"]
+                .as_str()
+                .and_then(parse_iso8601_utc)
+                .unwrap_or(now + 3600);
+            if expires_at <= now {
+                println!("Skipping expired official launcher account {}", name);
+                continue;
+            }
+            accounts.push(AuthToken {
+                access_token: access_token.to_string(),
+                client_token: json["mojangClientToken"].as_str().unwrap_or("wise0wl-launcher").to_string(),
+                uuid: uuid.to_string(),
+                name: name.to_string(),
+                expires_at,
+                xuid: None,
+                // The official launcher doesn't expose its Microsoft refresh token
+                refresh_token: None,
+            });
+        }
+    }
+    accounts
+}
+
+// Import accounts already signed in to the official launcher, keeping only
+// tokens Minecraft services still accept
+#[tauri::command]
+async fn import_official_accounts() -> Result<Vec<AuthSession>, String> {
+    let mut imported = Vec::new();
+    for token in read_official_accounts() {
+        if let Err(e) = validate_minecraft_token(&token.access_token).await {
+            println!("Skipping official launcher account {}: {}", token.name, e);
+            continue;
+        }
+        imported.push(token);
+    }
+    if !imported.is_empty() {
+        let mut tokens = AUTH_TOKENS.lock().unwrap();
+        for token in &imported {
+            tokens.insert(token.uuid.clone(), token.clone());
+        }
+        save_tokens_to_file(&tokens)?;
+    }
+    println!("Imported {} accounts from the official launcher", imported.len());
+    Ok(imported.into_iter().map(AuthSession::from).collect())
+}
+
+/// A stored account as shown in the account switcher, without its tokens
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccountInfo {
+    uuid: String,
+    name: String,
+    expires_at: u64,
+}
+
+#[tauri::command]
+async fn list_accounts() -> Result<Vec<AccountInfo>, String> {
+    let tokens = AUTH_TOKENS.lock().unwrap();
+    let mut accounts: Vec<AccountInfo> = tokens
+        .values()
+        .map(|token| AccountInfo {
+            uuid: token.uuid.clone(),
+            name: token.name.clone(),
+            expires_at: token.expires_at,
+        })
+        .collect();
+    accounts.sort_by_key(|account| account.name.to_lowercase());
+    Ok(accounts)
+}
+
 #[tauri::command]
 async fn logout_user(uuid: String) -> Result<(), String> {
     let mut tokens = AUTH_TOKENS.lock().unwrap();
@@ -540,6 +1194,15 @@ pub fn run() {
     
     // Initialize tokens from persistent storage
     initialize_tokens();
+
+    // Clean up sign-ins that were started but never finished
+    prune_pkce_verifiers();
+
+    // Remove throwaway instances whose game outlived the last launcher session
+    instance::cleanup_ephemeral_instances();
+
+    // Games may have outlived the last launcher session
+    minecraft::reconcile_running_instances();
     
     let mut builder = tauri::Builder::default();
 
@@ -565,7 +1228,17 @@ pub fn run() {
                 let urls = event.urls();
                 println!("Deep link opened: {:?}", urls);
                 if let Some(url) = urls.first() {
-                     app_handle.emit("oauth_callback", vec![url.to_string()]).unwrap();
+                    let params: HashMap<String, String> = url
+                        .query_pairs()
+                        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+                        .collect();
+                    match validate_oauth_callback(&params) {
+                        Ok(()) => app_handle.emit("oauth_callback", vec![url.to_string()]).unwrap(),
+                        Err(e) => {
+                            println!("Rejected OAuth callback: {}", e);
+                            app_handle.emit("oauth_error", e).unwrap();
+                        }
+                    }
                 }
             });
 
@@ -579,17 +1252,169 @@ pub fn run() {
         })
         .invoke_handler(tauri::generate_handler![
             get_modpacks,
+            validate_modpacks,
             get_settings,
             save_settings,
+            reset_settings,
             launch_minecraft,
+            launch_and_wait,
+            verify_and_launch,
+            launch_ephemeral,
+            get_instance_resource_usage,
+            list_running_instances,
+            stop_instance,
+            is_game_running,
+            kill_game,
+            get_game_log,
+            cancel_launch,
+            explain_launch_args,
+            get_classpath,
+            list_installed_versions,
+            remove_version,
             ensure_java_installed_for_mc,
+            ensure_jdk_installed,
+            java_download_size,
+            enumerate_java_installations,
+            resolve_java_for_launch,
             downloader::download_modpack_with_groups,
+            downloader::get_install_progress,
             downloader::fetch_modpack_list,
+            downloader::refresh_modpack,
+            downloader::reset_pack_configs,
+            downloader::diff_modpack_versions,
+            downloader::lint_modpack_list,
+            downloader::cache_modpack_icon,
+            downloader::check_disk_space,
+            downloader::repair_instance_paths,
+            downloader::effective_mod_list,
+            downloader::list_modloader_versions,
+            downloader::clean_installer_artifacts,
+            downloader::find_partial_downloads,
+            downloader::clean_partial_downloads,
+            downloader::classify_update,
+            downloader::get_optional_mods,
+            downloader::validate_game_directory,
+            storage::relocate_data,
+            instance::list_resource_packs,
+            instance::add_resource_pack,
+            instance::migrate_to_instances,
+            instance::backup_world,
+            instance::restore_world,
+            instance::list_screenshots,
+            instance::delete_screenshot,
+            servers::list_servers,
+            servers::check_server_reachable,
             get_microsoft_auth_url,
             handle_microsoft_callback,
+            cancel_auth,
             get_auth_token,
+            diagnose_auth,
+            list_accounts,
             logout_user,
+            import_official_accounts,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_pkce_path() -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("wise0wl-pkce-test-{}", uuid::Uuid::new_v4()))
+            .join("pkce.json")
+    }
+
+    fn entry(verifier: &str, created_at: u64) -> PkceEntry {
+        PkceEntry { verifier: verifier.to_string(), created_at }
+    }
+
+    fn callback(state: &str) -> HashMap<String, String> {
+        HashMap::from([
+            ("state".to_string(), state.to_string()),
+            ("code".to_string(), "auth-code".to_string()),
+        ])
+    }
+
+    #[test]
+    fn pkce_map_round_trips_through_the_temp_file() {
+        let path = temp_pkce_path();
+        let map = HashMap::from([("state-a".to_string(), entry("verifier-a", 42))]);
+        save_pkce_map_at(&path, &map).unwrap();
+        assert!(!path.with_extension("json.tmp").exists());
+        let loaded = load_pkce_map_at(&path);
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded["state-a"].verifier, "verifier-a");
+        assert_eq!(loaded["state-a"].created_at, 42);
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn legacy_pkce_file_loads_as_stale() {
+        let path = temp_pkce_path();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, r#"{"state-a": "verifier-a"}"#).unwrap();
+        let loaded = load_pkce_map_at(&path);
+        assert_eq!(loaded["state-a"].created_at, 0);
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn interleaved_auth_starts_keep_both_verifiers() {
+        let path = temp_pkce_path();
+        let handles: Vec<_> = (0..2)
+            .map(|i| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    for round in 0..20 {
+                        update_pkce_map_at(&path, |map| {
+                            map.insert(format!("state-{}-{}", i, round), entry(&format!("verifier-{}", i), 1));
+                        })
+                        .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        let loaded = load_pkce_map_at(&path);
+        assert_eq!(loaded.len(), 40);
+        assert_eq!(loaded["state-0-19"].verifier, "verifier-0");
+        assert_eq!(loaded["state-1-19"].verifier, "verifier-1");
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn callback_with_pending_state_is_accepted() {
+        let pending = HashMap::from([("state-a".to_string(), entry("verifier-a", 1000))]);
+        assert!(check_oauth_callback(&callback("state-a"), &pending, 1010).is_ok());
+    }
+
+    #[test]
+    fn callback_with_mismatched_state_is_rejected() {
+        let pending = HashMap::from([("state-a".to_string(), entry("verifier-a", 1000))]);
+        assert!(check_oauth_callback(&callback("state-b"), &pending, 1010).is_err());
+    }
+
+    #[test]
+    fn callback_with_stale_state_is_rejected() {
+        let pending = HashMap::from([("state-a".to_string(), entry("verifier-a", 1000))]);
+        let now = 1000 + PKCE_TTL_SECS;
+        assert!(check_oauth_callback(&callback("state-a"), &pending, now).is_err());
+    }
+
+    #[test]
+    fn stale_verifiers_are_pruned() {
+        let now = 10 * PKCE_TTL_SECS;
+        let mut map = HashMap::from([
+            ("fresh".to_string(), entry("v1", now - 60)),
+            ("stale".to_string(), entry("v2", now - PKCE_TTL_SECS)),
+            ("legacy".to_string(), entry("v3", 0)),
+        ]);
+        assert_eq!(prune_stale_verifiers(&mut map, now), 2);
+        assert!(map.contains_key("fresh"));
+    }
+}
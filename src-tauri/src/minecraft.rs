@@ -1,53 +1,97 @@
+use crate::http;
 use crate::modpack::{Mod, Modpack};
+use crate::version::strip_mc_prefix;
 use crate::LaunchOptions;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
 use std::fs;
-use std::io::Write;
-use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
 use futures::stream::{FuturesUnordered, StreamExt};
+use once_cell::sync::Lazy;
 
-#[derive(Debug, Serialize, Deserialize)]
-struct MinecraftVersion {
-    id: String,
-    #[serde(rename = "type")]
-    version_type: String,
-    url: String,
-    time: String,
-    #[serde(rename = "releaseTime")]
-    release_time: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct VersionManifest {
-    latest: HashMap<String, String>,
-    versions: Vec<MinecraftVersion>,
-}
-
+/// A vanilla version JSON. Only the fields needed to launch are required, so a
+/// field Mojang renames or drops in a new version doesn't break parsing.
 #[derive(Debug, Serialize, Deserialize)]
 struct VersionDetails {
     id: String,
-    #[serde(rename = "type")]
+    #[serde(rename = "type", default)]
     version_type: String,
     #[serde(rename = "mainClass")]
     main_class: String,
     #[serde(rename = "minecraftArguments")]
     minecraft_arguments: Option<String>,
     arguments: Option<Arguments>,
+    #[serde(default)]
     libraries: Vec<Library>,
     downloads: Downloads,
     #[serde(rename = "assetIndex")]
     asset_index: AssetIndex,
+    #[serde(default)]
     assets: String,
-    #[serde(rename = "releaseTime")]
+    #[serde(rename = "releaseTime", default)]
     release_time: String,
+    #[serde(default)]
     time: String,
+    logging: Option<Logging>,
+    /// Fields we don't model (`complianceLevel`, `javaVersion`, ...), kept so they survive a re-save
+    #[serde(flatten)]
+    extra: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Logging {
+    client: Option<LoggingConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LoggingConfig {
+    argument: String,
+    file: LoggingFile,
+    #[serde(rename = "type")]
+    config_type: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LoggingFile {
+    id: String,
+    sha1: String,
+    size: u64,
+    url: String,
+}
+
+/// A modloader's version profile, layered on top of the vanilla version JSON
+#[derive(Debug, Deserialize)]
+struct ModloaderProfile {
+    id: String,
+    /// The vanilla version whose jar the modloader runs
+    #[serde(rename = "inheritsFrom")]
+    inherits_from: Option<String>,
+    #[serde(rename = "mainClass")]
+    main_class: Option<String>,
+    arguments: Option<ProfileArguments>,
+    #[serde(default)]
+    libraries: Vec<Library>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct ProfileArguments {
+    #[serde(default)]
+    game: Vec<Argument>,
+    #[serde(default)]
+    jvm: Vec<Argument>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct Arguments {
+    #[serde(default)]
     game: Vec<Argument>,
+    #[serde(default)]
     jvm: Vec<Argument>,
 }
 
@@ -80,6 +124,8 @@ struct OsRule {
 struct Library {
     name: String,
     downloads: Option<LibraryDownloads>,
+    /// Maven repository base for entries that only give a coordinate
+    url: Option<String>,
     rules: Option<Vec<Rule>>,
     natives: Option<HashMap<String, String>>,
     extract: Option<Extract>,
@@ -118,23 +164,551 @@ struct Downloads {
 #[derive(Debug, Serialize, Deserialize)]
 struct AssetIndex {
     id: String,
-    #[serde(rename = "totalSize")]
+    #[serde(rename = "totalSize", default)]
     total_size: u64,
     url: String,
 }
 
+/// Payload of the `asset_progress` event
+#[derive(Debug, Serialize, Clone)]
+pub struct AssetProgress {
+    pub downloaded: usize,
+    pub total: usize,
+}
+
+/// Largest fraction of the asset index that may fail to download before
+/// the launch is aborted even when asset failures are tolerated
+const MAX_ASSET_FAILURE_RATIO: f64 = 0.05;
+/// Passes over the missing assets; later passes only retry the ones that failed
+const ASSET_PASSES: u32 = 2;
+
+/// Number of output lines kept when reporting a failed blocking launch
+const OUTPUT_TAIL_LINES: usize = 50;
+
+/// Log lines printed once the game window is up
+const READY_MARKERS: &[&str] = &["Backend library:", "LWJGL Version", "Sound engine started"];
+
+/// How long the game must have been silent before a slow start counts as stalled
+const STALL_IDLE_SECS: u64 = 10;
+
+/// Payload of the `launch_stalled` event
+#[derive(Debug, Serialize, Clone)]
+pub struct LaunchStalled {
+    pub elapsed_secs: u64,
+    pub log_tail: Vec<String>,
+}
+
+/// Game output lines kept per modpack so a log window opened late can backfill
+const GAME_LOG_LINES: usize = 2000;
+
+/// Payload of the `game_log` event, one per line of game output
+#[derive(Debug, Serialize, Clone)]
+pub struct GameLogLine {
+    pub modpack_id: String,
+    /// `stdout` or `stderr`
+    pub stream: &'static str,
+    pub line: String,
+}
+
+/// Recent output of the latest launch of each modpack
+static GAME_LOGS: Lazy<Mutex<HashMap<String, VecDeque<GameLogLine>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// The buffered output of a modpack's latest launch, oldest line first
+pub fn game_log(modpack_id: &str) -> Vec<GameLogLine> {
+    GAME_LOGS.lock().unwrap()
+        .get(modpack_id)
+        .map(|lines| lines.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Payload of the `game_exited` event
+#[derive(Debug, Serialize, Clone)]
+pub struct GameExited {
+    pub modpack_id: String,
+    /// None when the game was killed by a signal
+    pub exit_code: Option<i32>,
+    pub crashed: bool,
+    /// Newest file in `<game_dir>/crash-reports` after a crash, if any
+    pub crash_report: Option<PathBuf>,
+}
+
+/// Most recently modified file in the game directory's `crash-reports`
+fn latest_crash_report(game_dir: &Path) -> Option<PathBuf> {
+    fs::read_dir(game_dir.join("crash-reports"))
+        .ok()?
+        .flatten()
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            metadata.is_file().then(|| (metadata.modified().ok(), entry.path()))
+        })
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, path)| path)
+}
+
+/// A game started by the launcher that is still running
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RunningInstance {
+    pub modpack_id: String,
+    pub pid: u32,
+    /// Unix seconds
+    pub started_at: u64,
+}
+
+/// Games started by the launcher that are still running, by modpack id. Mirrored
+/// to disk so a restarted launcher still knows about games it left running.
+static RUNNING_GAMES: Lazy<Mutex<HashMap<String, RunningInstance>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Process id of the running game for a modpack, if the launcher started one
+pub fn running_game_pid(modpack_id: &str) -> Option<u32> {
+    RUNNING_GAMES.lock().unwrap().get(modpack_id).map(|game| game.pid)
+}
+
+/// Every game the launcher started that is still running, oldest first
+pub fn running_instances() -> Vec<RunningInstance> {
+    let mut games: Vec<RunningInstance> = RUNNING_GAMES.lock().unwrap().values().cloned().collect();
+    games.sort_by_key(|game| game.started_at);
+    games
+}
+
+fn get_running_games_path() -> PathBuf {
+    crate::paths::config_dir()
+        .expect("Failed to get config directory")
+        .join("wise0wl-launcher")
+        .join("running-instances.json")
+}
+
+fn save_running_games(games: &HashMap<String, RunningInstance>) -> Result<(), String> {
+    let path = get_running_games_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+    let content = serde_json::to_string_pretty(&games.values().collect::<Vec<_>>())
+        .map_err(|e| format!("Failed to serialize running instances: {}", e))?;
+    fs::write(&path, content)
+        .map_err(|e| format!("Failed to write running instances: {}", e))
+}
+
+fn track_running_game(modpack_id: &str, pid: u32) {
+    let mut games = RUNNING_GAMES.lock().unwrap();
+    let started_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    games.insert(modpack_id.to_string(), RunningInstance { modpack_id: modpack_id.to_string(), pid, started_at });
+    if let Err(e) = save_running_games(&games) {
+        println!("Warning: {}", e);
+    }
+}
+
+fn untrack_running_game(modpack_id: &str) {
+    let mut games = RUNNING_GAMES.lock().unwrap();
+    games.remove(modpack_id);
+    if let Err(e) = save_running_games(&games) {
+        println!("Warning: {}", e);
+    }
+}
+
+/// A game started this session
+struct GameProcess {
+    child: Child,
+    /// Set by `kill_game`, so the exit isn't reported as a crash
+    stop_requested: bool,
+}
+
+/// Processes of the games started this session, by modpack id. Keeping the `Child`
+/// lets exits be seen with `try_wait` and the game be killed without trusting a
+/// pid the OS may already have handed to another process.
+static GAME_PROCESSES: Lazy<Mutex<HashMap<String, Arc<Mutex<GameProcess>>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn game_process(modpack_id: &str) -> Option<Arc<Mutex<GameProcess>>> {
+    GAME_PROCESSES.lock().unwrap().get(modpack_id).cloned()
+}
+
+/// Whether the game this session started for a modpack is still running, or None
+/// when this session has no process for it
+fn game_process_running(modpack_id: &str) -> Option<bool> {
+    let process = game_process(modpack_id)?;
+    let running = matches!(process.lock().unwrap().child.try_wait(), Ok(None));
+    Some(running)
+}
+
+/// Poll a game process until it exits
+fn wait_for_game_process(process: &Mutex<GameProcess>) -> std::io::Result<ExitStatus> {
+    loop {
+        if let Some(status) = process.lock().unwrap().child.try_wait().transpose() {
+            return status;
+        }
+        std::thread::sleep(Duration::from_millis(500));
+    }
+}
+
+/// Stop tracking an exited game, unless a newer launch of the modpack replaced it
+fn forget_game_process(modpack_id: &str, process: &Arc<Mutex<GameProcess>>) {
+    let mut processes = GAME_PROCESSES.lock().unwrap();
+    if processes.get(modpack_id).is_some_and(|tracked| Arc::ptr_eq(tracked, process)) {
+        processes.remove(modpack_id);
+        untrack_running_game(modpack_id);
+    }
+}
+
+/// Whether the tracked game is still alive. The start time is compared too, since
+/// the OS may have reused the pid for an unrelated process.
+fn is_game_alive(system: &sysinfo::System, game: &RunningInstance) -> bool {
+    system
+        .process(sysinfo::Pid::from_u32(game.pid))
+        .is_some_and(|process| process.start_time().abs_diff(game.started_at) <= 5)
+}
+
+/// Whether the launcher has a game of this modpack running. Games started this
+/// session are checked through their process handle; the pid check covers ones
+/// inherited from a previous session.
+pub fn is_game_running(modpack_id: &str) -> bool {
+    if let Some(running) = game_process_running(modpack_id) {
+        return running;
+    }
+    let Some(game) = RUNNING_GAMES.lock().unwrap().get(modpack_id).cloned() else {
+        return false;
+    };
+    let mut system = sysinfo::System::new();
+    system.refresh_process(sysinfo::Pid::from_u32(game.pid));
+    if is_game_alive(&system, &game) {
+        return true;
+    }
+    untrack_running_game(modpack_id);
+    false
+}
+
+/// Pick up games left running by a previous launcher session, dropping any that have exited
+pub fn reconcile_running_instances() {
+    let tracked: Vec<RunningInstance> = fs::read_to_string(get_running_games_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    let mut system = sysinfo::System::new();
+    system.refresh_processes();
+    let mut games = RUNNING_GAMES.lock().unwrap();
+    for game in tracked {
+        if is_game_alive(&system, &game) {
+            println!("{} is still running (pid {})", game.modpack_id, game.pid);
+            games.insert(game.modpack_id.clone(), game);
+        }
+    }
+    if let Err(e) = save_running_games(&games) {
+        println!("Warning: {}", e);
+    }
+}
+
+/// Cancellation flags of launches still downloading or starting, by modpack id
+static LAUNCHES_IN_PROGRESS: Lazy<Mutex<HashMap<String, Arc<AtomicBool>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Keeps a launch cancellable through `cancel_launch` until it is dropped
+struct LaunchRegistration {
+    modpack_id: String,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Drop for LaunchRegistration {
+    fn drop(&mut self) {
+        let mut launches = LAUNCHES_IN_PROGRESS.lock().unwrap();
+        // A newer launch of the same modpack may have replaced this one
+        if launches.get(&self.modpack_id).is_some_and(|cancel| Arc::ptr_eq(cancel, &self.cancel)) {
+            launches.remove(&self.modpack_id);
+        }
+    }
+}
+
+/// Abort the in-progress launch of a modpack. Downloads stop after the current
+/// batch and the game is not started.
+pub fn cancel_launch(modpack_id: &str) -> Result<(), String> {
+    let launches = LAUNCHES_IN_PROGRESS.lock().unwrap();
+    let cancel = launches.get(modpack_id)
+        .ok_or(format!("{} is not being launched", modpack_id))?;
+    println!("Cancelling launch of {}", modpack_id);
+    cancel.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Kill the running game of a modpack. Games started this session are killed
+/// through their process handle and untracked by their watcher thread once
+/// they exit.
+pub fn kill_game(modpack_id: &str) -> Result<(), String> {
+    if let Some(process) = game_process(modpack_id) {
+        let mut process = process.lock().unwrap();
+        if !matches!(process.child.try_wait(), Ok(None)) {
+            return Err(format!("{} is not running", modpack_id));
+        }
+        process.child.kill().map_err(|e| format!("Failed to stop {}: {}", modpack_id, e))?;
+        process.stop_requested = true;
+        println!("Stopped {} (pid {})", modpack_id, process.child.id());
+        return Ok(());
+    }
+
+    // Inherited from a previous session: only the pid and start time are known
+    let game = RUNNING_GAMES.lock().unwrap().get(modpack_id).cloned()
+        .ok_or(format!("{} is not running", modpack_id))?;
+    let mut system = sysinfo::System::new();
+    system.refresh_process(sysinfo::Pid::from_u32(game.pid));
+    if !is_game_alive(&system, &game) {
+        untrack_running_game(modpack_id);
+        return Err(format!("{} is not running", modpack_id));
+    }
+    let process = system.process(sysinfo::Pid::from_u32(game.pid))
+        .ok_or(format!("{} is not running", modpack_id))?;
+    if !process.kill() {
+        return Err(format!("Failed to stop {}", modpack_id));
+    }
+    // There is no watcher thread to untrack it
+    untrack_running_game(modpack_id);
+    println!("Stopped {} (pid {})", modpack_id, game.pid);
+    Ok(())
+}
+
+/// Game output shared between the reader threads and the launch watchdog
+struct OutputWatch {
+    tail: VecDeque<String>,
+    last_output: Instant,
+    ready: bool,
+}
+
+/// Outcome of a blocking launch once the game has exited
+#[derive(Debug, Serialize)]
+pub struct GameRunResult {
+    /// -1 when the game was killed by a signal
+    pub exit_code: i32,
+    /// Last lines of the game's output when it exited with a non-zero code
+    pub output_tail: Option<String>,
+}
+
+/// Outcome of a successful launch, with any non-fatal problems for the UI
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct LaunchResult {
+    pub warnings: Vec<String>,
+}
+
+/// Placeholder substituted for secrets in explained launch arguments
+const REDACTED: &str = "<redacted>";
+
+/// Where a launch argument comes from
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ArgSource {
+    Vanilla,
+    Modloader,
+    /// Added by the launcher itself, e.g. memory limits or the classpath
+    Launcher,
+}
+
+/// One launch argument as written in the version JSON and as it would be passed
+#[derive(Debug, Serialize)]
+pub struct ExplainedArg {
+    pub source: ArgSource,
+    pub raw: String,
+    /// Whether the argument's rules matched this machine
+    pub included: bool,
+    pub rules: Option<serde_json::Value>,
+    /// Value after placeholder substitution, absent when the argument is excluded
+    pub value: Option<String>,
+}
+
+impl ExplainedArg {
+    fn launcher(value: String) -> Self {
+        ExplainedArg {
+            source: ArgSource::Launcher,
+            raw: value.clone(),
+            included: true,
+            rules: None,
+            value: Some(value),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct LaunchArgExplanation {
+    pub jvm: Vec<ExplainedArg>,
+    pub main_class: String,
+    pub game: Vec<ExplainedArg>,
+}
+
+/// Outcome of one pre-launch integrity check
+#[derive(Debug, Serialize)]
+pub struct IntegrityCheck {
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+}
+
+impl IntegrityCheck {
+    pub fn new(name: &str, ok: bool, detail: impl Into<String>) -> Self {
+        IntegrityCheck { name: name.to_string(), ok, detail: detail.into() }
+    }
+}
+
+/// A downloaded entry in `versions/` and the modpacks that still need it
+#[derive(Debug, Serialize)]
+pub struct VersionDiskInfo {
+    pub id: String,
+    pub jar_size: u64,
+    pub has_json: bool,
+    pub referenced_by: Vec<String>,
+}
+
+/// Java agents and extra classpath jars for a launch: the launch options' own,
+/// or the ones saved in settings
+fn dev_extras(options: &LaunchOptions) -> (Vec<String>, Vec<PathBuf>) {
+    let settings = crate::settings::load_settings().unwrap_or_default();
+    let agents = options.java_agents.clone().or(settings.java_agents).unwrap_or_default();
+    let classpath = options.extra_classpath.clone().or(settings.extra_classpath).unwrap_or_default();
+    (agents, classpath)
+}
+
+/// Fail the launch early if a Java agent or extra classpath jar is missing
+fn validate_dev_extras(agents: &[String], classpath: &[PathBuf]) -> Result<(), String> {
+    for agent in agents {
+        // Anything after '=' is passed to the agent
+        let jar = Path::new(agent.split_once('=').map_or(agent.as_str(), |(jar, _)| jar));
+        if !jar.is_file() {
+            return Err(format!("Java agent not found: {}", jar.display()));
+        }
+    }
+    for entry in classpath {
+        if !entry.exists() {
+            return Err(format!("Extra classpath entry not found: {}", entry.display()));
+        }
+    }
+    Ok(())
+}
+
 pub struct MinecraftLauncher {
     minecraft_dir: PathBuf,
+    assets_dir: PathBuf,
+    /// Launch the demo, enabling the `is_demo_user` argument rule
+    demo: bool,
+    /// Set by `cancel_launch`
+    cancel: Arc<AtomicBool>,
 }
 
 impl MinecraftLauncher {
     pub fn new() -> Self {
+        let minecraft_dir = Self::get_minecraft_dir();
         MinecraftLauncher {
-            minecraft_dir: Self::get_minecraft_dir(),
+            assets_dir: crate::settings::assets_directory(&minecraft_dir),
+            minecraft_dir,
+            demo: false,
+            cancel: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Make this launcher's launch of `modpack_id` cancellable
+    fn register_launch(&self, modpack_id: &str) -> LaunchRegistration {
+        LAUNCHES_IN_PROGRESS.lock().unwrap().insert(modpack_id.to_string(), self.cancel.clone());
+        LaunchRegistration { modpack_id: modpack_id.to_string(), cancel: self.cancel.clone() }
+    }
+
+    fn check_cancelled(&self) -> Result<(), String> {
+        if self.cancel.load(Ordering::SeqCst) {
+            return Err("Launch cancelled".to_string());
+        }
+        Ok(())
+    }
+
+    /// A launcher configured for the given launch options
+    pub fn for_launch(options: &LaunchOptions) -> Self {
+        MinecraftLauncher { demo: options.demo, ..Self::new() }
+    }
+
+    pub async fn launch(&self, mut options: LaunchOptions, app: &AppHandle) -> Result<LaunchResult, String> {
+        let _registration = self.register_launch(&options.modpack_id);
+        let modpack = self.get_modpack(&options.modpack_id)?;
+        let (command, result) = self.prepare_launch(&mut options, &modpack, app).await?;
+        let launch_timeout = crate::settings::load_settings().unwrap_or_default().launch_timeout_secs;
+
+        // 8. Launch the game
+        self.check_cancelled()?;
+        self.execute_command(command, &options.modpack_id, app, launch_timeout, &options.game_dir, None)?;
+        Ok(result)
+    }
+
+    /// Launch `modpack` in a throwaway game directory that is deleted when the
+    /// game exits. Versions, libraries and assets still come from the shared cache.
+    pub async fn launch_ephemeral(&self, modpack: Modpack, mut options: LaunchOptions, app: &AppHandle) -> Result<LaunchResult, String> {
+        let _registration = self.register_launch(&modpack.id);
+        let game_dir = crate::instance::create_ephemeral_instance()?;
+        options.game_dir = game_dir.clone();
+        options.modpack_id = modpack.id.clone();
+
+        let prepared = self.prepare_launch(&mut options, &modpack, app).await;
+        let (command, result) = match prepared {
+            Ok(prepared) => prepared,
+            Err(e) => {
+                crate::instance::remove_ephemeral_instance(&game_dir);
+                return Err(e);
+            }
+        };
+        let launch_timeout = crate::settings::load_settings().unwrap_or_default().launch_timeout_secs;
+        let launched = self.check_cancelled()
+            .and_then(|()| self.execute_command(command, &options.modpack_id, app, launch_timeout, &game_dir, Some(game_dir.clone())));
+        if let Err(e) = launched {
+            crate::instance::remove_ephemeral_instance(&game_dir);
+            return Err(e);
         }
+        Ok(result)
     }
 
-    pub async fn launch(&self, options: LaunchOptions) -> Result<(), String> {
+    /// Launch the game and block until it exits, returning its exit code and, for a
+    /// non-zero exit, the tail of its output. Errors mean the game couldn't be
+    /// started or waited for.
+    pub async fn launch_and_wait(&self, mut options: LaunchOptions, app: &AppHandle) -> Result<GameRunResult, String> {
+        let registration = self.register_launch(&options.modpack_id);
+        let modpack = self.get_modpack(&options.modpack_id)?;
+        let (mut command, _) = self.prepare_launch(&mut options, &modpack, app).await?;
+        self.check_cancelled()?;
+        // Once the game is running there is nothing left to cancel
+        drop(registration);
+
+        println!("Executing command and waiting for exit: {:?}", command);
+        let mut child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to launch Minecraft: {}", e))?;
+        // Tracked like any other launch, so a second launch or a data move sees it
+        // running and kill_game can stop it
+        track_running_game(&options.modpack_id, child.id());
+        let stdout = child.stdout.take().map(collect_output);
+        let stderr = child.stderr.take().map(collect_output);
+        let process = Arc::new(Mutex::new(GameProcess { child, stop_requested: false }));
+        GAME_PROCESSES.lock().unwrap().insert(options.modpack_id.clone(), process.clone());
+
+        let waited = process.clone();
+        let status = tauri::async_runtime::spawn_blocking(move || wait_for_game_process(&waited)).await;
+        forget_game_process(&options.modpack_id, &process);
+        if crate::instance::is_ephemeral_instance(&options.game_dir) {
+            crate::instance::remove_ephemeral_instance(&options.game_dir);
+        }
+        let status = status
+            .map_err(|e| format!("Failed to wait for Minecraft: {}", e))?
+            .map_err(|e| format!("Failed to wait for Minecraft: {}", e))?;
+
+        let exit_code = status.code().unwrap_or(-1);
+        println!("Minecraft exited with code {}", exit_code);
+        if status.success() {
+            return Ok(GameRunResult { exit_code, output_tail: None });
+        }
+
+        let mut combined = String::new();
+        for output in [stdout, stderr].into_iter().flatten() {
+            combined.push_str(&String::from_utf8_lossy(&output.join().unwrap_or_default()));
+        }
+        let lines: Vec<&str> = combined.lines().collect();
+        let tail = lines[lines.len().saturating_sub(OUTPUT_TAIL_LINES)..].join("\n");
+        Ok(GameRunResult { exit_code, output_tail: Some(tail) })
+    }
+
+    /// Steps shared by every launch mode: pick and verify Java, install everything
+    /// the modpack needs and build the command line
+    async fn prepare_launch(&self, options: &mut LaunchOptions, modpack: &Modpack, app: &AppHandle) -> Result<(Command, LaunchResult), String> {
         // Debug authentication data
         println!("Launch options - Username: {:?}, UUID: {:?}, Token: {:?}", 
             options.username, 
@@ -144,38 +718,144 @@ impl MinecraftLauncher {
         println!("Launching Minecraft with game directory: {}", options.game_dir.display());
         println!("Minecraft directory: {}", self.minecraft_dir.display());
         
-        // 1. Verify Java installation
-        self.verify_java(&options.java_path)?;
+        if is_game_running(&options.modpack_id) {
+            return Err(format!("{} is already running", modpack.name));
+        }
 
-        // 2. Get modpack info
-        let modpack = self.get_modpack(&options.modpack_id)?;
+        // Staff can lock a pack during maintenance
+        if let Some(message) = crate::downloader::modpack_lock_message(&options.modpack_id) {
+            return Err(format!("{} is locked: {}", modpack.name, message));
+        }
 
-        // 3. Ensure Minecraft version is downloaded
-        self.ensure_minecraft_version(&modpack.minecraft_version).await?;
+        // Settings only validate the directory, so it may not exist yet
+        fs::create_dir_all(&options.game_dir)
+            .map_err(|e| format!("Failed to create game directory {}: {}", options.game_dir.display(), e))?;
+
+        let settings = crate::settings::load_settings().unwrap_or_default();
+        let mut result = LaunchResult::default();
+
+        // 0. Apply the testing-only Minecraft version override
+        let overridden;
+        let modpack = match options.minecraft_version_override.as_deref().filter(|v| !v.is_empty()) {
+            Some(version) if version != modpack.minecraft_version => {
+                let warning = format!(
+                    "UNSUPPORTED: launching {} against Minecraft {} instead of {} (version override for testing only)",
+                    modpack.name, version, modpack.minecraft_version
+                );
+                println!("==================== WARNING ====================");
+                println!("Warning: {}", warning);
+                println!("=================================================");
+                result.warnings.push(warning);
+                overridden = Modpack { minecraft_version: version.to_string(), ..modpack.clone() };
+                &overridden
+            }
+            _ => modpack,
+        };
 
-        // 3.5. Ensure assets are downloaded
-        self.ensure_assets(&modpack.minecraft_version).await?;
+        // 1. Pick Java (downloading the managed runtime when nothing usable is
+        // installed) and verify it, and any development agents/jars
+        let major = crate::required_java_version(&modpack.minecraft_version);
+        let resolution = crate::java::resolve_java(options.java_path.as_deref(), &settings.java_path, major);
+        let java = match resolution.path {
+            Some(path) => path,
+            None => {
+                println!("No usable Java {} installed, downloading the managed runtime", major);
+                crate::java::ensure_java_image_installed(&crate::java::JavaRequest::from_settings(major), app).await?
+            }
+        };
+        println!("Using {:?} Java {} at {}", resolution.source, resolution.major, java.display());
+        options.java_path = Some(java.to_string_lossy().to_string());
+        self.verify_java(&options.java_path, &modpack.minecraft_version)?;
+        let (agents, extra_classpath) = dev_extras(options);
+        validate_dev_extras(&agents, &extra_classpath)?;
+
+        // 2. Ensure Minecraft version is downloaded
+        self.ensure_minecraft_version(&modpack.minecraft_version, settings.verify_jar_on_launch).await?;
+
+        // 3. Ensure assets are downloaded
+        result.warnings.extend(self.ensure_assets(&modpack.minecraft_version, settings.tolerate_asset_failures, app).await?);
 
         // 4. Handle modloader installation if needed
-        self.handle_modloader(&modpack).await?;
+        self.handle_modloader(modpack, settings.verify_jar_on_launch).await?;
 
         // 5. Download/Update mods if needed
         if !modpack.mods.is_empty() {
-            self.update_mods(&modpack, &options.game_dir).await?;
+            self.update_mods(modpack, &options.game_dir).await?;
         }
 
         // 6. Ensure native libraries are extracted
         self.ensure_native_libraries(&modpack.minecraft_version, &options.game_dir).await?;
 
+        // 6.5. Preset the game language unless the player already picked one in-game
+        if let Some(language) = settings.game_language.as_deref().filter(|l| !l.is_empty()) {
+            crate::instance::preset_option(&options.game_dir, "lang", language)?;
+        }
+
         // 7. Get version details and build launch command
         let version_details = self.get_version_details(&modpack.minecraft_version).await?;
-        let command = self.build_launch_command(&options, &modpack, &version_details)?;
+        self.ensure_logging_config(&version_details).await?;
+        let profile = self.load_modloader_profile(modpack)?;
+        let command = self.build_launch_command(options, modpack, &version_details, profile.as_ref())?;
 
-        // 8. Launch the game
-        self.execute_command(command)
+        Ok((command, result))
     }
 
-    fn verify_java(&self, java_path: &Option<String>) -> Result<(), String> {
+    /// Lightweight checks of everything a launch needs, repairing a corrupt client
+    /// jar or a missing modloader install on the way
+    pub async fn verify_instance(&self, options: &LaunchOptions) -> Result<Vec<IntegrityCheck>, String> {
+        let modpack = self.get_modpack(&options.modpack_id)?;
+        let version = &modpack.minecraft_version;
+        let mut checks = Vec::new();
+
+        checks.push(match self.version_jar_matches(version) {
+            Some(true) => IntegrityCheck::new("client_jar", true, "Client jar matches its SHA-1"),
+            None => IntegrityCheck::new("client_jar", true, "Client jar will be downloaded at launch"),
+            Some(false) => match self.verify_version_jar(version).await {
+                Ok(()) => IntegrityCheck::new("client_jar", true, "Client jar was corrupt and has been re-downloaded"),
+                Err(e) => IntegrityCheck::new("client_jar", false, format!("Client jar is corrupt and could not be repaired: {}", e)),
+            },
+        });
+
+        checks.push(self.check_modloader_profile(&modpack).await);
+
+        checks.push(match self.verify_java(&options.java_path, &modpack.minecraft_version) {
+            Ok(()) => IntegrityCheck::new("java", true, format!("Java runs: {}", options.java_path.as_deref().unwrap_or("java"))),
+            Err(e) => IntegrityCheck::new("java", false, e),
+        });
+
+        Ok(checks)
+    }
+
+    async fn check_modloader_profile(&self, modpack: &Modpack) -> IntegrityCheck {
+        if let Err(e) = modpack.validate_modloader() {
+            return IntegrityCheck::new("modloader_profile", false, e);
+        }
+        let id = match modloader_version_id(modpack) {
+            Some(id) => id,
+            None => return IntegrityCheck::new("modloader_profile", true, "No modloader needed"),
+        };
+        let version = &modpack.minecraft_version;
+        let profile_path = self.minecraft_dir.join("versions").join(&id).join(format!("{}.json", id));
+        let vanilla_jar = self.minecraft_dir.join("versions").join(version).join(format!("{}.jar", version));
+        if profile_path.exists() {
+            return IntegrityCheck::new("modloader_profile", true, format!("Modloader profile {} is installed", id));
+        }
+        // On a fresh instance the launch installs Minecraft first, then the modloader
+        if !vanilla_jar.exists() {
+            return IntegrityCheck::new("modloader_profile", true, format!("Modloader {} will be installed at launch", id));
+        }
+        if let Err(e) = self.handle_modloader(modpack, true).await {
+            return IntegrityCheck::new("modloader_profile", false, format!("Modloader profile {} is missing and could not be installed: {}", id, e));
+        }
+        if profile_path.exists() {
+            IntegrityCheck::new("modloader_profile", true, format!("Modloader profile {} was missing and has been reinstalled", id))
+        } else {
+            IntegrityCheck::new("modloader_profile", false, format!("Modloader profile {} is missing after reinstalling", id))
+        }
+    }
+
+    /// Check that Java runs and is new enough for `minecraft_version`
+    fn verify_java(&self, java_path: &Option<String>, minecraft_version: &str) -> Result<(), String> {
         let java = java_path.as_ref().map(String::as_str).unwrap_or("java");
 
         let output = Command::new(java)
@@ -187,7 +867,20 @@ impl MinecraftLauncher {
             return Err("Java is not installed or not accessible".to_string());
         }
 
-        Ok(())
+        // `java -version` prints to stderr
+        let required = crate::required_java_version(minecraft_version);
+        match crate::java::parse_java_major(&String::from_utf8_lossy(&output.stderr)) {
+            Some(major) if major < required => Err(format!(
+                "{} is Java {}, but Minecraft {} needs Java {} or newer. \
+                 Install it with ensure_java_installed_for_mc or pick a newer Java in settings.",
+                java, major, minecraft_version, required
+            )),
+            Some(_) => Ok(()),
+            None => {
+                println!("Warning: Could not read the Java version of {}", java);
+                Ok(())
+            }
+        }
     }
 
     fn get_modpack(&self, modpack_id: &str) -> Result<Modpack, String> {
@@ -198,10 +891,16 @@ impl MinecraftLauncher {
             .ok_or_else(|| format!("Modpack '{}' not found", modpack_id))
     }
 
-    async fn ensure_minecraft_version(&self, version: &str) -> Result<(), String> {
+    /// Make sure the version jar and libraries are present. With `verify_jar`
+    /// the client jar is also checked against its SHA-1 and re-downloaded on mismatch.
+    async fn ensure_minecraft_version(&self, version: &str, verify_jar: bool) -> Result<(), String> {
         let version_dir = self.minecraft_dir.join("versions").join(version);
         let jar_path = version_dir.join(format!("{}.jar", version));
 
+        if verify_jar {
+            self.verify_version_jar(version).await?;
+        }
+
         if !jar_path.exists() {
             self.download_minecraft_version(version).await?;
         }
@@ -213,29 +912,9 @@ impl MinecraftLauncher {
     }
 
     async fn download_minecraft_version(&self, version: &str) -> Result<(), String> {
-        // Get version manifest
-        let manifest_url = "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json";
-        let manifest_resp = reqwest::get(manifest_url)
-            .await
-            .map_err(|e| format!("Failed to fetch version manifest: {}", e))?;
-        
-        let manifest: VersionManifest = manifest_resp.json()
-            .await
-            .map_err(|e| format!("Failed to parse version manifest: {}", e))?;
-
-        // Find the version
-        let version_info = manifest.versions
-            .into_iter()
-            .find(|v| v.id == version)
-            .ok_or_else(|| format!("Version {} not found", version))?;
-
-        // Get version details
-        let version_resp = reqwest::get(&version_info.url)
-            .await
-            .map_err(|e| format!("Failed to fetch version details: {}", e))?;
-        
-        let version_details: VersionDetails = version_resp.json()
-            .await
+        // Get version details via the manifest, with mirror fallback and SHA-1 check
+        let version_bytes = crate::mojang::fetch_version_json_bytes(version).await?;
+        let version_details: VersionDetails = serde_json::from_slice(&version_bytes)
             .map_err(|e| format!("Failed to parse version details: {}", e))?;
 
         // Create version directory
@@ -246,7 +925,7 @@ impl MinecraftLauncher {
         // Download client jar
         let jar_path = version_dir.join(format!("{}.jar", version));
         if !jar_path.exists() {
-            let jar_resp = reqwest::get(&version_details.downloads.client.url)
+            let jar_resp = http::client().get(&version_details.downloads.client.url).send()
                 .await
                 .map_err(|e| format!("Failed to download client jar: {}", e))?;
             
@@ -260,52 +939,114 @@ impl MinecraftLauncher {
                 .map_err(|e| format!("Failed to write jar file: {}", e))?;
         }
 
-        // Save version details
+        // Save the version JSON exactly as verified, so it keeps matching the manifest
         let version_json_path = version_dir.join(format!("{}.json", version));
-        let version_json = serde_json::to_string_pretty(&version_details)
-            .map_err(|e| format!("Failed to serialize version details: {}", e))?;
-        
-        fs::write(&version_json_path, version_json)
+        fs::write(&version_json_path, &version_bytes)
             .map_err(|e| format!("Failed to write version JSON: {}", e))?;
 
         Ok(())
     }
 
-    async fn handle_modloader(&self, modpack: &Modpack) -> Result<(), String> {
+    /// Re-download a version jar that doesn't match its SHA-1, then hash the new
+    /// download too so a bad download can't be launched
+    async fn verify_version_jar(&self, version: &str) -> Result<(), String> {
+        if self.version_jar_matches(version) != Some(false) {
+            return Ok(());
+        }
+        println!("Client jar for {} is corrupt, re-downloading", version);
+        let jar_path = self.minecraft_dir.join("versions").join(version).join(format!("{}.jar", version));
+        fs::remove_file(&jar_path)
+            .map_err(|e| format!("Failed to remove corrupt jar: {}", e))?;
+        self.download_minecraft_version(version).await?;
+        if self.version_jar_matches(version) == Some(false) {
+            return Err(format!("Client jar for {} still doesn't match its SHA-1 after re-downloading", version));
+        }
+        Ok(())
+    }
+
+    /// Check a version jar against the SHA-1 declared in its version JSON.
+    /// Returns `None` when the JSON doesn't declare one (as with most modloader versions).
+    fn version_jar_matches(&self, version: &str) -> Option<bool> {
+        let version_dir = self.minecraft_dir.join("versions").join(version);
+        let version_json = fs::read_to_string(version_dir.join(format!("{}.json", version))).ok()?;
+        let json: serde_json::Value = serde_json::from_str(&version_json).ok()?;
+        let expected = json["downloads"]["client"]["sha1"].as_str()?;
+        let bytes = fs::read(version_dir.join(format!("{}.jar", version))).ok()?;
+        Some(sha1_hex(&bytes).eq_ignore_ascii_case(expected))
+    }
+
+    /// Path of the profile JSON a modloader installer writes for `version_id`
+    fn modloader_profile_path(&self, version_id: &str) -> PathBuf {
+        self.minecraft_dir
+            .join("versions")
+            .join(version_id)
+            .join(format!("{}.json", version_id))
+    }
+
+    /// Load the installed modloader profile for the modpack, if it has a modloader.
+    /// A modpack whose modloader isn't installed is an error: launching the vanilla
+    /// profile instead would start the game without any of its mods.
+    fn load_modloader_profile(&self, modpack: &Modpack) -> Result<Option<ModloaderProfile>, String> {
+        let version_id = match modloader_version_id(modpack) {
+            Some(id) => id,
+            None => return Ok(None),
+        };
+        let profile_path = self.modloader_profile_path(&version_id);
+        if !profile_path.exists() {
+            return Err(format!(
+                "Modloader profile {} is not installed ({} not found)",
+                version_id, profile_path.display()
+            ));
+        }
+        let content = fs::read_to_string(&profile_path)
+            .map_err(|e| format!("Failed to read modloader profile: {}", e))?;
+        serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|e| format!("Failed to parse modloader profile: {}", e))
+    }
+
+    async fn handle_modloader(&self, modpack: &Modpack, verify_jar: bool) -> Result<(), String> {
+        modpack.validate_modloader()?;
+
         // Check if modloader is already installed
-        let modloader_version = if let Some(forge_version) = &modpack.forge_version {
-            format!("{}-forge-{}", modpack.minecraft_version, forge_version)
-        } else if let Some(fabric_version) = &modpack.fabric_version {
-            format!("{}-fabric-{}", modpack.minecraft_version, fabric_version)
-        } else if let Some(neoforge_version) = &modpack.neoforge_version {
-            format!("{}-neoforge-{}", modpack.minecraft_version, neoforge_version)
-        } else {
-            return Ok(()); // No modloader needed
+        let modloader_version = match modloader_version_id(modpack) {
+            Some(id) => id,
+            None => return Ok(()), // No modloader needed
         };
 
-        let modloader_dir = self.minecraft_dir.join("versions").join(&modloader_version);
-        let modloader_jar = modloader_dir.join(format!("{}.jar", modloader_version));
+        let profile_path = self.modloader_profile_path(&modloader_version);
 
-        if !modloader_jar.exists() {
+        // Not every installer writes a jar next to the profile, so the profile marks the install
+        if !profile_path.exists() {
             // Install modloader
-            if modpack.forge_version.is_some() {
-                self.install_forge(&modpack.minecraft_version, modpack.forge_version.as_ref().unwrap()).await?;
-            } else if modpack.fabric_version.is_some() {
-                self.install_fabric(&modpack.minecraft_version, modpack.fabric_version.as_ref().unwrap()).await?;
-            } else if modpack.neoforge_version.is_some() {
-                self.install_neoforge(&modpack.minecraft_version, modpack.neoforge_version.as_ref().unwrap()).await?;
+            let installer_version = modpack.installer_version.as_deref();
+            if let Some(forge_version) = &modpack.forge_version {
+                self.install_forge(&modpack.minecraft_version, forge_version, installer_version).await?;
+            } else if let Some(fabric_version) = &modpack.fabric_version {
+                self.install_fabric(&modpack.minecraft_version, fabric_version, installer_version).await?;
+            } else if let Some(neoforge_version) = &modpack.neoforge_version {
+                self.install_neoforge(&modpack.minecraft_version, neoforge_version, installer_version).await?;
+            }
+        }
+
+        if let Some(profile) = self.load_modloader_profile(modpack)? {
+            // Loader libraries often give only a maven coordinate and repository url
+            self.download_libraries(&profile.libraries).await?;
+            // Modloader profiles declare no jar hash of their own; the jar they run
+            // is the one they inherit from, which is checked here
+            if let Some(inherited) = profile.inherits_from.as_deref().filter(|_| verify_jar) {
+                self.verify_version_jar(inherited).await?;
             }
         }
 
         Ok(())
     }
 
-    async fn install_forge(&self, mc_version: &str, forge_version: &str) -> Result<(), String> {
-        let installer_url = format!(
-            "https://maven.minecraftforge.net/net/minecraftforge/forge/{mc_version}-{forge_version}/forge-{mc_version}-{forge_version}-installer.jar"
-        );
+    async fn install_forge(&self, mc_version: &str, forge_version: &str, installer_version: Option<&str>) -> Result<(), String> {
+        let forge_version = strip_mc_prefix(mc_version, forge_version);
+        let installer_url = crate::version::forge_installer_url(mc_version, forge_version, installer_version);
         
-        let installer_resp = reqwest::get(&installer_url)
+        let installer_resp = http::client().get(&installer_url).send()
             .await
             .map_err(|e| format!("Failed to download Forge installer: {}", e))?;
         
@@ -324,25 +1065,24 @@ impl MinecraftLauncher {
             .map_err(|e| format!("Failed to write installer: {}", e))?;
 
         // Run installer
-        let status = Command::new("java")
-            .arg("-jar")
-            .arg(&installer_path)
+        let mut command = installer_command(&installer_path);
+        command
             .arg("--installClient")
-            .arg(&self.minecraft_dir)
-            .status()
-            .map_err(|e| format!("Failed to run Forge installer: {}", e))?;
+            .arg(&self.minecraft_dir);
+        run_installer(command, "Forge")?;
 
-        if !status.success() {
-            return Err("Forge installer failed".to_string());
-        }
+        // The installer is only needed once
+        let _ = fs::remove_file(&installer_path);
 
         Ok(())
     }
 
-    async fn install_fabric(&self, mc_version: &str, fabric_version: &str) -> Result<(), String> {
-        let installer_url = "https://maven.fabricmc.net/net/fabricmc/fabric-installer/0.11.2/fabric-installer-0.11.2.jar";
+    async fn install_fabric(&self, mc_version: &str, fabric_version: &str, installer_version: Option<&str>) -> Result<(), String> {
+        let fabric_installer_version = crate::downloader::fabric_installer_version(installer_version).await?;
+        println!("Using Fabric installer {}", fabric_installer_version);
+        let installer_url = crate::version::fabric_installer_url(&fabric_installer_version);
         
-        let installer_resp = reqwest::get(installer_url)
+        let installer_resp = http::client().get(&installer_url).send()
             .await
             .map_err(|e| format!("Failed to download Fabric installer: {}", e))?;
         
@@ -354,39 +1094,35 @@ impl MinecraftLauncher {
         fs::create_dir_all(&temp_dir)
             .map_err(|e| format!("Failed to create temp directory: {}", e))?;
 
-        let installer_path = temp_dir.join("fabric-installer-0.11.2.jar");
+        let installer_path = temp_dir.join(format!("fabric-installer-{}.jar", fabric_installer_version));
         let mut file = fs::File::create(&installer_path)
             .map_err(|e| format!("Failed to create installer file: {}", e))?;
         file.write_all(&installer_bytes)
             .map_err(|e| format!("Failed to write installer: {}", e))?;
 
         // Run installer
-        let status = Command::new("java")
-            .arg("-jar")
-            .arg(&installer_path)
+        let mut command = installer_command(&installer_path);
+        command
             .arg("client")
             .arg("-dir")
             .arg(&self.minecraft_dir)
             .arg("-mcversion")
             .arg(mc_version)
             .arg("-loader")
-            .arg(fabric_version)
-            .status()
-            .map_err(|e| format!("Failed to run Fabric installer: {}", e))?;
+            .arg(fabric_version);
+        run_installer(command, "Fabric")?;
 
-        if !status.success() {
-            return Err("Fabric installer failed".to_string());
-        }
+        // The installer is only needed once
+        let _ = fs::remove_file(&installer_path);
 
         Ok(())
     }
 
-    async fn install_neoforge(&self, mc_version: &str, neoforge_version: &str) -> Result<(), String> {
-        let installer_url = format!(
-            "https://maven.neoforged.net/releases/net/neoforged/neoforge/{mc_version}-{neoforge_version}/neoforge-{mc_version}-{neoforge_version}-installer.jar"
-        );
+    async fn install_neoforge(&self, mc_version: &str, neoforge_version: &str, installer_version: Option<&str>) -> Result<(), String> {
+        let neoforge_version = strip_mc_prefix(mc_version, neoforge_version);
+        let installer_url = crate::version::neoforge_installer_url(mc_version, neoforge_version, installer_version);
         
-        let installer_resp = reqwest::get(&installer_url)
+        let installer_resp = http::client().get(&installer_url).send()
             .await
             .map_err(|e| format!("Failed to download NeoForge installer: {}", e))?;
         
@@ -405,17 +1141,14 @@ impl MinecraftLauncher {
             .map_err(|e| format!("Failed to write installer: {}", e))?;
 
         // Run installer
-        let status = Command::new("java")
-            .arg("-jar")
-            .arg(&installer_path)
+        let mut command = installer_command(&installer_path);
+        command
             .arg("--installClient")
-            .arg(&self.minecraft_dir)
-            .status()
-            .map_err(|e| format!("Failed to run NeoForge installer: {}", e))?;
+            .arg(&self.minecraft_dir);
+        run_installer(command, "NeoForge")?;
 
-        if !status.success() {
-            return Err("NeoForge installer failed".to_string());
-        }
+        // The installer is only needed once
+        let _ = fs::remove_file(&installer_path);
 
         Ok(())
     }
@@ -430,9 +1163,19 @@ impl MinecraftLauncher {
 
         let version_json = fs::read_to_string(&version_json_path)
             .map_err(|e| format!("Failed to read version JSON: {}", e))?;
-        
-        serde_json::from_str(&version_json)
-            .map_err(|e| format!("Failed to parse version JSON: {}", e))
+
+        match serde_json::from_str(&version_json) {
+            Ok(details) => Ok(details),
+            Err(e) => {
+                // A corrupt local copy would break every later step, so fetch it again
+                println!("Warning: Version JSON for {} is unreadable ({}), re-downloading", version, e);
+                self.download_minecraft_version(version).await?;
+                let version_json = fs::read_to_string(&version_json_path)
+                    .map_err(|e| format!("Failed to read version JSON: {}", e))?;
+                serde_json::from_str(&version_json)
+                    .map_err(|e| format!("Failed to parse version JSON: {}", e))
+            }
+        }
     }
 
     async fn update_mods(&self, modpack: &Modpack, game_dir: &PathBuf) -> Result<(), String> {
@@ -441,6 +1184,10 @@ impl MinecraftLauncher {
             .map_err(|e| format!("Failed to create mods directory: {}", e))?;
 
         for mod_info in &modpack.mods {
+            // Required mods always install; optional ones only when on by default
+            if mod_info.optional && !mod_info.required && !mod_info.default_enabled {
+                continue;
+            }
             self.download_mod(mod_info, &mods_dir).await?;
         }
 
@@ -449,22 +1196,31 @@ impl MinecraftLauncher {
 
     async fn download_mod(&self, mod_info: &Mod, mods_dir: &PathBuf) -> Result<(), String> {
         let mod_path = mods_dir.join(&mod_info.name);
-        
-        if !mod_path.exists() {
-            let resp = reqwest::get(&mod_info.download_url)
-                .await
-                .map_err(|e| format!("Failed to download mod {}: {}", mod_info.name, e))?;
-            
-            let bytes = resp.bytes()
-                .await
-                .map_err(|e| format!("Failed to read mod bytes: {}", e))?;
 
-            let mut file = fs::File::create(&mod_path)
-                .map_err(|e| format!("Failed to create mod file: {}", e))?;
-            file.write_all(&bytes)
-                .map_err(|e| format!("Failed to write mod file: {}", e))?;
+        // A file that doesn't match the expected hash (e.g. a partial download) is fetched again
+        if mod_path.exists() {
+            let existing = fs::read(&mod_path)
+                .map_err(|e| format!("Failed to read mod {}: {}", mod_info.name, e))?;
+            if verify_mod_hash(mod_info, &existing).is_ok() {
+                return Ok(());
+            }
+            println!("Mod {} does not match its hash, re-downloading", mod_info.name);
         }
 
+        let resp = http::client().get(&mod_info.download_url).send()
+            .await
+            .map_err(|e| format!("Failed to download mod {}: {}", mod_info.name, e))?;
+
+        let bytes = resp.bytes()
+            .await
+            .map_err(|e| format!("Failed to read mod bytes: {}", e))?;
+        verify_mod_hash(mod_info, &bytes)?;
+
+        let mut file = fs::File::create(&mod_path)
+            .map_err(|e| format!("Failed to create mod file: {}", e))?;
+        file.write_all(&bytes)
+            .map_err(|e| format!("Failed to write mod file: {}", e))?;
+
         Ok(())
     }
 
@@ -473,6 +1229,7 @@ impl MinecraftLauncher {
         options: &LaunchOptions,
         modpack: &Modpack,
         version_details: &VersionDetails,
+        profile: Option<&ModloaderProfile>,
     ) -> Result<Command, String> {
         let java_path = options
             .java_path
@@ -482,22 +1239,265 @@ impl MinecraftLauncher {
 
         let mut command = Command::new(java_path);
 
+        // Mods resolve relative paths against the working directory, so never inherit the launcher's
+        let working_dir = options.working_dir.as_ref().unwrap_or(&options.game_dir);
+        fs::create_dir_all(working_dir)
+            .map_err(|e| format!("Failed to create working directory {}: {}", working_dir.display(), e))?;
+        command.current_dir(working_dir);
+
         // JVM arguments
-        self.add_jvm_arguments(&mut command, version_details, options)?;
+        self.add_jvm_arguments(&mut command, version_details, profile, options)?;
 
-        // Main class
-        command.arg(&version_details.main_class);
+        // Main class, which the modloader profile replaces
+        let main_class = profile
+            .and_then(|p| p.main_class.as_ref())
+            .unwrap_or(&version_details.main_class);
+        command.arg(main_class);
 
         // Game arguments
-        self.add_game_arguments(&mut command, version_details, options, modpack)?;
+        self.add_game_arguments(&mut command, version_details, profile, options, modpack)?;
 
         Ok(command)
     }
 
+    /// Describe the JVM and game arguments a launch would use, without installing
+    /// anything. Secrets such as the access token are redacted.
+    pub async fn explain_launch_args(&self, mut options: LaunchOptions, modpack: &Modpack) -> Result<LaunchArgExplanation, String> {
+        options.access_token = options.access_token.map(|_| REDACTED.to_string());
+        options.xuid = options.xuid.map(|_| REDACTED.to_string());
+
+        let version_details = self.get_version_details(&modpack.minecraft_version).await?;
+        let profile = self.load_modloader_profile(modpack)?;
+        let profile_args = profile.as_ref().and_then(|p| p.arguments.as_ref());
+        let version_name = profile.as_ref().map(|p| p.id.as_str()).unwrap_or(&version_details.id);
+        let natives_dir = self.natives_dir(&options.game_dir, &version_details.id);
+
+        // JVM arguments, in the order add_jvm_arguments passes them
+        let mut jvm = Vec::new();
+        if let Some(max_mem) = options.max_memory {
+            jvm.push(ExplainedArg::launcher(format!("-Xmx{}M", max_mem)));
+        }
+        if let Some(min_mem) = options.min_memory {
+            jvm.push(ExplainedArg::launcher(format!("-Xms{}M", min_mem)));
+        }
+        let (agents, extra_classpath) = dev_extras(&options);
+        jvm.extend(agents.iter().map(|agent| ExplainedArg::launcher(format!("-javaagent:{}", agent))));
+        let mut from_json = version_details.arguments
+            .as_ref()
+            .map(|a| self.explain_arguments(&a.jvm, ArgSource::Vanilla))
+            .unwrap_or_default();
+        if let Some(args) = profile_args {
+            from_json.extend(self.explain_arguments(&args.jvm, ArgSource::Modloader));
+        }
+        for arg in &mut from_json {
+            if self.should_skip_jvm_argument(&arg.raw) {
+                arg.included = false;
+                arg.value = None;
+            } else if let Some(value) = &arg.value {
+                arg.value = Some(self.process_jvm_argument(value, &natives_dir, version_name));
+            }
+        }
+
+        let values: Vec<String> = from_json.iter().filter_map(|a| a.value.clone()).collect();
+        let module_path = module_path_of(&values);
+        let has_classpath_arg = values.iter().any(|arg| arg == "-cp" || arg == "-classpath");
+        let has_library_path = values.iter().any(|arg| arg.starts_with("-Djava.library.path="));
+        let classpath = self.build_classpath(&version_details, profile.as_ref(), &module_path, &extra_classpath)?;
+        for arg in &mut from_json {
+            if let Some(value) = &mut arg.value {
+                *value = value.replace("${classpath}", &classpath);
+            }
+        }
+        jvm.extend(from_json);
+
+        if is_log4j_vulnerable(&version_details.id) {
+            jvm.push(ExplainedArg::launcher("-Dlog4j2.formatMsgNoLookups=true".to_string()));
+        }
+        if let Some((logging, path)) = self.logging_config(&version_details) {
+            if path.exists() {
+                jvm.push(ExplainedArg::launcher(logging.argument.replace("${path}", &path.to_string_lossy())));
+            }
+        }
+        if !has_library_path {
+            jvm.push(ExplainedArg::launcher(format!("-Djava.library.path={}", natives_dir.display())));
+        }
+        if !has_classpath_arg {
+            jvm.push(ExplainedArg::launcher("-cp".to_string()));
+            jvm.push(ExplainedArg::launcher(classpath));
+        }
+
+        // Game arguments: vanilla (or the legacy string) followed by the modloader's
+        let mut game = match (&version_details.arguments, &version_details.minecraft_arguments) {
+            (Some(arguments), _) => self.explain_arguments(&arguments.game, ArgSource::Vanilla),
+            (None, Some(legacy)) => legacy
+                .split_whitespace()
+                .map(|arg| ExplainedArg {
+                    source: ArgSource::Vanilla,
+                    raw: arg.to_string(),
+                    included: true,
+                    rules: None,
+                    value: Some(arg.to_string()),
+                })
+                .collect(),
+            (None, None) => Vec::new(),
+        };
+        if let Some(args) = profile_args {
+            game.extend(self.explain_arguments(&args.game, ArgSource::Modloader));
+        }
+        for arg in &mut game {
+            if let Some(value) = &arg.value {
+                arg.value = Some(self.process_game_argument(value, &options, modpack));
+            }
+        }
+
+        let main_class = profile
+            .as_ref()
+            .and_then(|p| p.main_class.clone())
+            .unwrap_or_else(|| version_details.main_class.clone());
+
+        Ok(LaunchArgExplanation { jvm, main_class, game })
+    }
+
+    /// The ordered classpath jars a launch of the modpack would use, for external tooling
+    pub async fn resolve_classpath(&self, modpack_id: &str) -> Result<Vec<PathBuf>, String> {
+        let modpack = self.get_modpack(modpack_id)?;
+        let version_details = self.get_version_details(&modpack.minecraft_version).await?;
+        let profile = self.load_modloader_profile(&modpack)?;
+        let version_name = profile.as_ref().map(|p| p.id.as_str()).unwrap_or(&version_details.id);
+        let natives_dir = self.natives_dir(&crate::downloader::get_instance_dir(modpack_id), &version_details.id);
+
+        let mut jvm_args = version_details.arguments
+            .as_ref()
+            .map(|a| self.flatten_arguments(&a.jvm))
+            .unwrap_or_default();
+        if let Some(profile_args) = profile.as_ref().and_then(|p| p.arguments.as_ref()) {
+            jvm_args.extend(self.flatten_arguments(&profile_args.jvm));
+        }
+        let jvm_args: Vec<String> = jvm_args
+            .iter()
+            .map(|arg| self.process_jvm_argument(arg, &natives_dir, version_name))
+            .collect();
+        Ok(self.classpath_entries(&version_details, profile.as_ref(), &module_path_of(&jvm_args)))
+    }
+
+    /// Every downloaded version with its jar size and the modpacks that use it
+    pub fn list_installed_versions(&self, modpacks: &[Modpack]) -> Result<Vec<VersionDiskInfo>, String> {
+        let versions_dir = self.minecraft_dir.join("versions");
+        if !versions_dir.exists() {
+            return Ok(Vec::new());
+        }
+        let entries = fs::read_dir(&versions_dir)
+            .map_err(|e| format!("Failed to read versions directory: {}", e))?;
+        let mut versions: Vec<VersionDiskInfo> = entries
+            .flatten()
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| {
+                let id = entry.file_name().to_string_lossy().to_string();
+                let dir = entry.path();
+                VersionDiskInfo {
+                    jar_size: fs::metadata(dir.join(format!("{}.jar", id))).map(|m| m.len()).unwrap_or(0),
+                    has_json: dir.join(format!("{}.json", id)).exists(),
+                    referenced_by: self.modpacks_using_version(&id, modpacks),
+                    id,
+                }
+            })
+            .collect();
+        versions.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(versions)
+    }
+
+    /// Delete a downloaded version, unless a modpack still uses it
+    pub fn remove_version(&self, id: &str, modpacks: &[Modpack]) -> Result<(), String> {
+        // Only a direct child of versions/ may be removed
+        if id.is_empty() || id == "." || id == ".." || id.contains(['/', '\\']) {
+            return Err(format!("Invalid version id: {}", id));
+        }
+        let version_dir = self.minecraft_dir.join("versions").join(id);
+        if !version_dir.is_dir() {
+            return Err(format!("Version {} is not installed", id));
+        }
+        let referenced_by = self.modpacks_using_version(id, modpacks);
+        if !referenced_by.is_empty() {
+            return Err(format!("Version {} is still used by: {}", id, referenced_by.join(", ")));
+        }
+        fs::remove_dir_all(&version_dir)
+            .map_err(|e| format!("Failed to remove version {}: {}", id, e))?;
+        println!("Removed version {}", id);
+        Ok(())
+    }
+
+    /// Ids of the modpacks whose vanilla or modloader version is `version_id`
+    fn modpacks_using_version(&self, version_id: &str, modpacks: &[Modpack]) -> Vec<String> {
+        modpacks
+            .iter()
+            .filter(|m| {
+                m.minecraft_version == version_id
+                    || modloader_version_id(m).as_deref() == Some(version_id)
+            })
+            .map(|m| m.id.clone())
+            .collect()
+    }
+
+    /// Expand version JSON arguments one entry per string, recording whether their rules matched
+    fn explain_arguments(&self, arguments: &[Argument], source: ArgSource) -> Vec<ExplainedArg> {
+        let mut explained = Vec::new();
+        for argument in arguments {
+            match argument {
+                Argument::String(s) => explained.push(ExplainedArg {
+                    source,
+                    raw: s.clone(),
+                    included: true,
+                    rules: None,
+                    value: Some(s.clone()),
+                }),
+                Argument::Object { rules, value } => {
+                    let included = self.should_apply_rule(rules);
+                    let raw_values: Vec<String> = match value {
+                        serde_json::Value::String(s) => vec![s.clone()],
+                        serde_json::Value::Array(arr) => arr.iter().filter_map(|item| item.as_str()).map(str::to_string).collect(),
+                        _ => Vec::new(),
+                    };
+                    for raw in raw_values {
+                        explained.push(ExplainedArg {
+                            source,
+                            value: included.then(|| raw.clone()),
+                            raw,
+                            included,
+                            rules: serde_json::to_value(rules).ok(),
+                        });
+                    }
+                }
+            }
+        }
+        explained
+    }
+
+    /// Flatten version JSON arguments into strings, dropping entries whose rules don't match
+    fn flatten_arguments(&self, arguments: &[Argument]) -> Vec<String> {
+        let mut flattened = Vec::new();
+        for argument in arguments {
+            match argument {
+                Argument::String(s) => flattened.push(s.clone()),
+                Argument::Object { rules, value } => {
+                    if !self.should_apply_rule(rules) {
+                        continue;
+                    }
+                    if let Some(s) = value.as_str() {
+                        flattened.push(s.to_string());
+                    } else if let Some(arr) = value.as_array() {
+                        flattened.extend(arr.iter().filter_map(|item| item.as_str()).map(str::to_string));
+                    }
+                }
+            }
+        }
+        flattened
+    }
+
     fn add_jvm_arguments(
         &self,
         command: &mut Command,
         version_details: &VersionDetails,
+        profile: Option<&ModloaderProfile>,
         options: &LaunchOptions,
     ) -> Result<(), String> {
         // Memory settings
@@ -508,58 +1508,115 @@ impl MinecraftLauncher {
             command.arg(format!("-Xms{}M", min_mem));
         }
 
-        // Add JVM arguments from version details
-        if let Some(arguments) = &version_details.arguments {
-            for arg in &arguments.jvm {
-                match arg {
-                    Argument::String(s) => {
-                        // Filter out macOS-specific options on other platforms
-                        if !self.should_skip_jvm_argument(s) {
-                            let processed = self.process_jvm_argument(s, options);
-                            command.arg(processed);
-                        }
-                    }
-                    Argument::Object { rules, value } => {
-                        if self.should_apply_rule(rules) {
-                            if let Some(s) = value.as_str() {
-                                if !self.should_skip_jvm_argument(s) {
-                                    let processed = self.process_jvm_argument(s, options);
-                                    command.arg(processed);
-                                }
-                            } else if let Some(arr) = value.as_array() {
-                                for item in arr {
-                                    if let Some(s) = item.as_str() {
-                                        if !self.should_skip_jvm_argument(s) {
-                                            let processed = self.process_jvm_argument(s, options);
-                                            command.arg(processed);
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+        // Development agents (mixin debugging, profilers)
+        let (agents, extra_classpath) = dev_extras(options);
+        for agent in &agents {
+            command.arg(format!("-javaagent:{}", agent));
+        }
+
+        // JVM arguments from the version, followed by the modloader profile's
+        let mut jvm_args = version_details.arguments
+            .as_ref()
+            .map(|a| self.flatten_arguments(&a.jvm))
+            .unwrap_or_default();
+        if let Some(profile_args) = profile.and_then(|p| p.arguments.as_ref()) {
+            jvm_args.extend(self.flatten_arguments(&profile_args.jvm));
+        }
+        let version_name = profile.map(|p| p.id.as_str()).unwrap_or(&version_details.id);
+        let natives_dir = self.natives_dir(&options.game_dir, &version_details.id);
+        let jvm_args: Vec<String> = jvm_args
+            .iter()
+            .filter(|arg| !self.should_skip_jvm_argument(arg))
+            .map(|arg| self.process_jvm_argument(arg, &natives_dir, version_name))
+            .collect();
+
+        let classpath = self.build_classpath(version_details, profile, &module_path_of(&jvm_args), &extra_classpath)?;
+
+        let mut has_classpath_arg = false;
+        for arg in &jvm_args {
+            has_classpath_arg |= arg == "-cp" || arg == "-classpath";
+            command.arg(arg.replace("${classpath}", &classpath));
+        }
+
+        // Log4Shell (CVE-2021-44228) mitigation for versions before 1.18.1
+        if is_log4j_vulnerable(&version_details.id) {
+            command.arg("-Dlog4j2.formatMsgNoLookups=true");
+        }
+        if let Some((logging, path)) = self.logging_config(version_details) {
+            if path.exists() {
+                command.arg(logging.argument.replace("${path}", &path.to_string_lossy()));
+            }
+        }
+
+        // Point the native library path at this version's natives unless the version JSON already did
+        if !jvm_args.iter().any(|arg| arg.starts_with("-Djava.library.path=")) {
+            command.arg(format!("-Djava.library.path={}", natives_dir.display()));
+        }
+
+        // Legacy versions have no JVM arguments, so pass the classpath ourselves
+        if !has_classpath_arg {
+            command.arg("-cp");
+            command.arg(classpath);
         }
 
-        // Add library paths
-        let libraries_path = self.minecraft_dir.join("libraries");
-        command.arg(format!("-Djava.library.path={}", libraries_path.display()));
+        Ok(())
+    }
+
+    fn logging_config<'a>(&self, version_details: &'a VersionDetails) -> Option<(&'a LoggingConfig, PathBuf)> {
+        let logging = version_details.logging.as_ref()?.client.as_ref()?;
+        let path = self.assets_dir
+            .join("log_configs")
+            .join(&logging.file.id);
+        Some((logging, path))
+    }
 
-        // Add natives directory
-        let natives_dir = options.game_dir.join("natives");
-        command.arg(format!("-Djava.library.path={};{}", libraries_path.display(), natives_dir.display()));
+    /// Download the patched log4j configuration referenced by the version JSON,
+    /// as the official launcher does
+    async fn ensure_logging_config(&self, version_details: &VersionDetails) -> Result<(), String> {
+        let (logging, config_path) = match self.logging_config(version_details) {
+            Some(config) => config,
+            None => return Ok(()),
+        };
 
-        // Add classpath
-        let classpath = self.build_classpath(version_details)?;
-        command.arg(format!("-cp"));
-        command.arg(classpath);
+        if config_path.exists() {
+            let existing = fs::read(&config_path)
+                .map_err(|e| format!("Failed to read logging config: {}", e))?;
+            if sha1_hex(&existing) == logging.file.sha1 {
+                return Ok(());
+            }
+        }
 
+        println!("Downloading logging config {}", logging.file.id);
+        let resp = http::client().get(&logging.file.url).send()
+            .await
+            .map_err(|e| format!("Failed to download logging config: {}", e))?;
+        let bytes = resp.bytes()
+            .await
+            .map_err(|e| format!("Failed to read logging config bytes: {}", e))?;
+        if sha1_hex(&bytes) != logging.file.sha1 {
+            return Err(format!("Logging config {} failed SHA-1 verification", logging.file.id));
+        }
+        fs::create_dir_all(config_path.parent().unwrap())
+            .map_err(|e| format!("Failed to create log config dir: {}", e))?;
+        fs::write(&config_path, &bytes)
+            .map_err(|e| format!("Failed to write logging config: {}", e))?;
         Ok(())
     }
 
-    fn process_jvm_argument(&self, arg: &str, options: &LaunchOptions) -> String {
-        arg.replace("${natives_directory}", &options.game_dir.join("natives").to_string_lossy())
+    /// Natives are extracted per Minecraft version so packs on different versions
+    /// sharing a game directory never load each other's native libraries
+    fn natives_dir(&self, game_dir: &Path, version: &str) -> PathBuf {
+        game_dir.join("natives").join(version)
+    }
+
+    fn process_jvm_argument(&self, arg: &str, natives_dir: &Path, version_name: &str) -> String {
+        let separator = if cfg!(target_os = "windows") { ";" } else { ":" };
+        arg.replace("${natives_directory}", &natives_dir.to_string_lossy())
+            .replace("${library_directory}", &self.minecraft_dir.join("libraries").to_string_lossy())
+            .replace("${classpath_separator}", separator)
+            .replace("${version_name}", version_name)
+            .replace("${launcher_name}", "wise0wl-launcher")
+            .replace("${launcher_version}", env!("CARGO_PKG_VERSION"))
     }
 
     fn should_skip_jvm_argument(&self, arg: &str) -> bool {
@@ -578,9 +1635,34 @@ impl MinecraftLauncher {
         &self,
         command: &mut Command,
         version_details: &VersionDetails,
+        profile: Option<&ModloaderProfile>,
         options: &LaunchOptions,
         modpack: &Modpack,
     ) -> Result<(), String> {
+        self.add_vanilla_game_arguments(command, version_details, options, modpack);
+
+        // Modloader arguments such as Forge's --launchTarget come after the vanilla ones
+        if let Some(profile_args) = profile.and_then(|p| p.arguments.as_ref()) {
+            for arg in self.flatten_arguments(&profile_args.game) {
+                command.arg(self.process_game_argument(&arg, options, modpack));
+            }
+        }
+
+        // Legacy versions have no demo rule, so ask for the demo ourselves
+        if self.demo && !command.get_args().any(|arg| arg == "--demo") {
+            command.arg("--demo");
+        }
+
+        Ok(())
+    }
+
+    fn add_vanilla_game_arguments(
+        &self,
+        command: &mut Command,
+        version_details: &VersionDetails,
+        options: &LaunchOptions,
+        modpack: &Modpack,
+    ) {
         if let Some(arguments) = &version_details.arguments {
             // New argument format
             for argument in &arguments.game {
@@ -624,8 +1706,6 @@ impl MinecraftLauncher {
                 command.arg(processed);
             }
         }
-
-        Ok(())
     }
 
     fn process_game_argument(&self, arg: &str, options: &LaunchOptions, modpack: &Modpack) -> String {
@@ -647,20 +1727,23 @@ impl MinecraftLauncher {
             }
         };
 
+        // The demo runs without a Microsoft account, so don't claim one
+        let user_type = if self.demo && options.access_token.is_none() { "legacy" } else { "msa" };
         let processed = arg.replace("${auth_player_name}", options.username.as_deref().unwrap_or("Player"))
             .replace("${version_name}", &modpack.minecraft_version)
             .replace("${game_directory}", &options.game_dir.to_string_lossy())
-            .replace("${assets_root}", &self.minecraft_dir.join("assets").to_string_lossy())
+            .replace("${assets_root}", &self.assets_dir.to_string_lossy())
+            .replace("${game_assets}", &self.assets_dir.to_string_lossy())
             .replace("${assets_index_name}", &asset_index_name)
             .replace("${auth_uuid}", options.uuid.as_deref().unwrap_or("00000000-0000-0000-0000-000000000000"))
             .replace("${auth_access_token}", options.access_token.as_deref().unwrap_or("token"))
-            .replace("${clientid}", "clientid")
-            .replace("${auth_xuid}", "xuid")
-            .replace("${user_type}", "msa")
+            .replace("${clientid}", &crate::get_microsoft_client_id())
+            .replace("${auth_xuid}", options.xuid.as_deref().unwrap_or("0"))
+            .replace("${user_type}", user_type)
             .replace("${version_type}", "release")
             .replace("${resolution_width}", &options.width.unwrap_or(1280).to_string())
             .replace("${resolution_height}", &options.height.unwrap_or(720).to_string())
-            .replace("${natives_directory}", &options.game_dir.join("natives").to_string_lossy());
+            .replace("${natives_directory}", &self.natives_dir(&options.game_dir, &modpack.minecraft_version).to_string_lossy());
         
         // Debug asset paths
         if arg.contains("${assets_root}") || arg.contains("${assets_index_name}") {
@@ -699,8 +1782,7 @@ impl MinecraftLauncher {
                 for (feature, required) in features {
                     match feature.as_str() {
                         "is_demo_user" => {
-                            println!("is_demo_user rule: required={}, we have valid auth, so should_apply = should_apply && {}", required, *required == false);
-                            should_apply = should_apply && *required == false;
+                            should_apply = should_apply && *required == self.demo;
                         }
                         "has_custom_resolution" => {
                             should_apply = should_apply && !required;
@@ -750,49 +1832,96 @@ impl MinecraftLauncher {
         }
     }
 
-    fn build_classpath(&self, version_details: &VersionDetails) -> Result<String, String> {
-        let mut classpath_parts = Vec::new();
+    fn build_classpath(
+        &self,
+        version_details: &VersionDetails,
+        profile: Option<&ModloaderProfile>,
+        module_path: &[PathBuf],
+        extra: &[PathBuf],
+    ) -> Result<String, String> {
+        let mut entries = self.classpath_entries(version_details, profile, module_path);
+        entries.extend(extra.iter().cloned());
+        let separator = if cfg!(target_os = "windows") { ";" } else { ":" };
+        let classpath = entries
+            .iter()
+            .map(|entry| entry.to_string_lossy().to_string())
+            .collect::<Vec<_>>()
+            .join(separator);
+        println!("Final classpath length: {} characters", classpath.len());
+
+        Ok(classpath)
+    }
+
+    /// Ordered classpath jars: the version jar, then modloader and vanilla libraries
+    fn classpath_entries(
+        &self,
+        version_details: &VersionDetails,
+        profile: Option<&ModloaderProfile>,
+        module_path: &[PathBuf],
+    ) -> Vec<PathBuf> {
+        let mut classpath_parts: Vec<PathBuf> = Vec::new();
 
         // Add version jar
         let version_jar = self.minecraft_dir
             .join("versions")
             .join(&version_details.id)
             .join(format!("{}.jar", version_details.id));
-        classpath_parts.push(version_jar.to_string_lossy().to_string());
         println!("Added version jar to classpath: {}", version_jar.display());
+        classpath_parts.push(version_jar);
 
-        // Add libraries
+        // Add libraries, modloader ones first so they win over vanilla duplicates
+        let profile_libraries = profile.map(|p| p.libraries.as_slice()).unwrap_or(&[]);
         let mut library_count = 0;
-        for library in &version_details.libraries {
+        for library in profile_libraries.iter().chain(&version_details.libraries) {
             if self.should_include_library(library) {
-                if let Some(downloads) = &library.downloads {
-                    if let Some(artifact) = &downloads.artifact {
-                        if let Some(path) = &artifact.path {
-                            let library_path = self.minecraft_dir
-                                .join("libraries")
-                                .join(path);
-                            
-                            if library_path.exists() {
-                                classpath_parts.push(library_path.to_string_lossy().to_string());
-                                library_count += 1;
-                                if library_count <= 5 { // Only print first 5 for debugging
-                                    println!("Added library to classpath: {}", path);
-                                }
-                            } else {
-                                println!("Warning: Library not found: {}", library_path.display());
-                            }
+                if let Some((path, _)) = self.library_artifact(library) {
+                    let library_path = self.minecraft_dir
+                        .join("libraries")
+                        .join(&path);
+
+                    if module_path.contains(&library_path) {
+                        continue;
+                    }
+                    if classpath_parts.contains(&library_path) {
+                        continue;
+                    }
+                    if library_path.exists() {
+                        classpath_parts.push(library_path);
+                        library_count += 1;
+                        if library_count <= 5 { // Only print first 5 for debugging
+                            println!("Added library to classpath: {}", path);
                         }
+                    } else {
+                        println!("Warning: Library not found: {}", library_path.display());
                     }
                 }
             }
         }
         println!("Total libraries in classpath: {}", library_count);
+        if !module_path.is_empty() {
+            println!("Kept {} module path entries off the classpath", module_path.len());
+        }
 
-        let separator = if cfg!(target_os = "windows") { ";" } else { ":" };
-        let classpath = classpath_parts.join(separator);
-        println!("Final classpath length: {} characters", classpath.len());
-        
-        Ok(classpath)
+        classpath_parts
+    }
+
+    /// Library path under `libraries/` and its download URL, if one is known.
+    /// Entries without `downloads.artifact` fall back to their maven coordinate,
+    /// downloaded from the library's `url` repository when it has one.
+    fn library_artifact(&self, library: &Library) -> Option<(String, Option<String>)> {
+        let artifact = library.downloads.as_ref().and_then(|d| d.artifact.as_ref());
+        if let Some(artifact) = artifact {
+            // Forge leaves the url empty for jars its installer generates locally
+            let url = Some(artifact.url.clone()).filter(|url| !url.is_empty());
+            return artifact.path.clone().map(|path| (path, url));
+        }
+        // Natives-only entries are handled by ensure_native_libraries
+        if library.natives.is_some() {
+            return None;
+        }
+        let path = maven_path(&library.name)?;
+        let url = library.url.as_ref().map(|base| format!("{}/{}", base.trim_end_matches('/'), path));
+        Some((path, url))
     }
 
     fn should_include_library(&self, library: &Library) -> bool {
@@ -803,23 +1932,72 @@ impl MinecraftLauncher {
         }
     }
 
-    fn execute_command(&self, mut command: Command) -> Result<(), String> {
+    /// Spawn the game and watch its output. A `cleanup_dir` is removed once the game exits.
+    fn execute_command(
+        &self,
+        mut command: Command,
+        modpack_id: &str,
+        app: &AppHandle,
+        launch_timeout: u64,
+        game_dir: &Path,
+        cleanup_dir: Option<PathBuf>,
+    ) -> Result<(), String> {
         // Debug: Print the command being executed
         println!("Executing command: {:?}", command);
-        
-        command
+
+        let mut child = command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
             .spawn()
             .map_err(|e| format!("Failed to launch Minecraft: {}", e))?;
+        track_running_game(modpack_id, child.id());
+        GAME_LOGS.lock().unwrap().insert(modpack_id.to_string(), VecDeque::new());
+
+        let watch = Arc::new(Mutex::new(OutputWatch {
+            tail: VecDeque::new(),
+            last_output: Instant::now(),
+            ready: false,
+        }));
+        if let Some(stdout) = child.stdout.take() {
+            spawn_output_reader(stdout, "stdout", modpack_id, watch.clone(), app.clone());
+        }
+        if let Some(stderr) = child.stderr.take() {
+            spawn_output_reader(stderr, "stderr", modpack_id, watch.clone(), app.clone());
+        }
+        let process = Arc::new(Mutex::new(GameProcess { child, stop_requested: false }));
+        GAME_PROCESSES.lock().unwrap().insert(modpack_id.to_string(), process.clone());
+        let app = app.clone();
+        let modpack_id = modpack_id.to_string();
+        let game_dir = game_dir.to_path_buf();
+        std::thread::spawn(move || {
+            watch_launch(&process, watch, app.clone(), launch_timeout);
+            let status = wait_for_game_process(&process);
+            forget_game_process(&modpack_id, &process);
+
+            let exit_code = status.as_ref().ok().and_then(|status| status.code());
+            // A game stopped from the launcher exits non-zero but didn't crash
+            let stop_requested = process.lock().unwrap().stop_requested;
+            let crashed = !stop_requested && !status.is_ok_and(|status| status.success());
+            let crash_report = if crashed { latest_crash_report(&game_dir) } else { None };
+            println!("Minecraft exited with code {:?}", exit_code);
+            let _ = app.emit("game_exited", GameExited { modpack_id: modpack_id.clone(), exit_code, crashed, crash_report });
+            if let Some(dir) = cleanup_dir {
+                crate::instance::remove_ephemeral_instance(&dir);
+            }
+        });
         Ok(())
     }
 
     fn get_minecraft_dir() -> PathBuf {
+        if let Some(root) = crate::settings::storage_root() {
+            return root;
+        }
         if cfg!(target_os = "windows") {
-            dirs::data_dir()
+            crate::paths::data_dir()
                 .expect("Failed to get data dir")
                 .join(".minecraft")
         } else {
-            dirs::home_dir()
+            crate::paths::home_dir()
                 .expect("Failed to get home dir")
                 .join(".minecraft")
         }
@@ -838,40 +2016,55 @@ impl MinecraftLauncher {
         let version_details: VersionDetails = serde_json::from_str(&version_json)
             .map_err(|e| format!("Failed to parse version JSON: {}", e))?;
 
-        // Download libraries
-        for library in &version_details.libraries {
+        self.download_libraries(&version_details.libraries).await
+    }
+
+    /// Download the libraries this platform needs from a version JSON or
+    /// modloader profile, skipping those already present and intact
+    async fn download_libraries(&self, libraries: &[Library]) -> Result<(), String> {
+        // Collect the libraries this platform needs
+        let mut pending = Vec::new();
+        for library in libraries {
             if self.should_include_library(library) {
-                if let Some(downloads) = &library.downloads {
-                    if let Some(artifact) = &downloads.artifact {
-                        if let Some(path) = &artifact.path {
-                            let library_path = self.minecraft_dir.join("libraries").join(path);
-                            
-                            // Create parent directory if it doesn't exist
-                            if let Some(parent) = library_path.parent() {
-                                fs::create_dir_all(parent)
-                                    .map_err(|e| format!("Failed to create library directory: {}", e))?;
-                            }
+                if let Some((path, url)) = self.library_artifact(library) {
+                    let library_path = self.minecraft_dir.join("libraries").join(&path);
 
-                            // Download if not exists
-                            if !library_path.exists() {
-                                println!("Downloading library: {}", path);
-                                let library_resp = reqwest::get(&artifact.url)
-                                    .await
-                                    .map_err(|e| format!("Failed to download library {}: {}", path, e))?;
-                                
-                                let library_bytes = library_resp.bytes()
-                                    .await
-                                    .map_err(|e| format!("Failed to read library bytes for {}: {}", path, e))?;
-
-                                let mut file = fs::File::create(&library_path)
-                                    .map_err(|e| format!("Failed to create library file {}: {}", path, e))?;
-                                file.write_all(&library_bytes)
-                                    .map_err(|e| format!("Failed to write library file {}: {}", path, e))?;
-                            }
-                        }
+                    // Create parent directory if it doesn't exist
+                    if let Some(parent) = library_path.parent() {
+                        fs::create_dir_all(parent)
+                            .map_err(|e| format!("Failed to create library directory: {}", e))?;
                     }
+
+                    let expected_sha1 = library.downloads.as_ref()
+                        .and_then(|d| d.artifact.as_ref())
+                        .map(|a| a.sha1.clone());
+                    pending.push((path, url, expected_sha1, library_path));
+                }
+            }
+        }
+
+        // Download libraries in batches, the same way assets are
+        let batch_size = crate::settings::load_settings()
+            .unwrap_or_default()
+            .library_download_batch_size
+            .max(1);
+        for chunk in pending.chunks(batch_size) {
+            self.check_cancelled()?;
+            let mut futures = FuturesUnordered::new();
+            for (path, url, expected_sha1, library_path) in chunk {
+                futures.push(fetch_library(path.clone(), url.clone(), expected_sha1.clone(), library_path.clone()));
+            }
+
+            // Let the rest of the batch finish before reporting a failure
+            let mut first_error = None;
+            while let Some(result) = futures.next().await {
+                if let Err(e) = result {
+                    first_error.get_or_insert(e);
                 }
             }
+            if let Some(e) = first_error {
+                return Err(e);
+            }
         }
 
         Ok(())
@@ -893,7 +2086,7 @@ impl MinecraftLauncher {
             .map_err(|e| format!("Failed to parse version JSON: {}", e))?;
 
         // Create natives directory
-        let natives_dir = game_dir.join("natives");
+        let natives_dir = self.natives_dir(game_dir, version);
         println!("Creating natives directory: {}", natives_dir.display());
         fs::create_dir_all(&natives_dir)
             .map_err(|e| format!("Failed to create natives directory: {}", e))?;
@@ -971,8 +2164,12 @@ impl MinecraftLauncher {
         Ok(())
     }
 
-    /// Download all assets referenced in the asset index for the given version.
-    pub async fn ensure_assets(&self, version: &str) -> Result<(), String> {
+    /// Download the assets of the given version's asset index that are missing,
+    /// emitting `asset_progress` after each batch and once more at the end so the
+    /// UI reaches 100% even when everything was cached. When `tolerate_failures` is
+    /// set, a small number of failed assets is returned as warnings instead of
+    /// aborting the launch.
+    pub async fn ensure_assets(&self, version: &str, tolerate_failures: bool, app: &AppHandle) -> Result<Vec<String>, String> {
         // 1. Load version details to get asset index
         let version_json_path = self.minecraft_dir
             .join("versions")
@@ -987,8 +2184,7 @@ impl MinecraftLauncher {
         println!("Asset index URL: {}", version_details.asset_index.url);
 
         // 2. Download asset index file
-        let asset_index_path = self.minecraft_dir
-            .join("assets")
+        let asset_index_path = self.assets_dir
             .join("indexes")
             .join(format!("{}.json", version_details.asset_index.id));
         
@@ -996,7 +2192,7 @@ impl MinecraftLauncher {
         
         if !asset_index_path.exists() {
             println!("Downloading asset index for version {}", version);
-            let resp = reqwest::get(&version_details.asset_index.url)
+            let resp = http::client().get(&version_details.asset_index.url).send()
                 .await
                 .map_err(|e| format!("Failed to download asset index: {}", e))?;
             let bytes = resp.bytes()
@@ -1017,110 +2213,418 @@ impl MinecraftLauncher {
         let objects = asset_index["objects"].as_object().ok_or("Invalid asset index format")?;
         println!("Found {} assets in index", objects.len());
 
-        // 4. Find missing assets
+        // 4. Find missing assets. Several names can share one object, so fetch each hash once.
         let mut missing_assets = Vec::new();
+        let mut seen_hashes = std::collections::HashSet::new();
         for (name, obj) in objects {
             let hash = obj["hash"].as_str().ok_or("Missing hash in asset object")?;
             let subdir = &hash[0..2];
-            let asset_path = self.minecraft_dir
-                .join("assets")
+            let asset_path = self.assets_dir
                 .join("objects")
                 .join(subdir)
                 .join(hash);
 
-            if !asset_path.exists() {
+            if !asset_path.exists() && seen_hashes.insert(hash.to_string()) {
                 missing_assets.push((name.clone(), hash.to_string(), asset_path));
             }
         }
 
+        // Progress counts unique objects, with cached ones already done
+        let total = objects.values()
+            .filter_map(|obj| obj["hash"].as_str())
+            .collect::<std::collections::HashSet<_>>()
+            .len();
+        let already_present = total - missing_assets.len();
+
         if missing_assets.is_empty() {
             println!("All assets are already downloaded for version {}", version);
-            return Ok(());
+            let _ = app.emit("asset_progress", AssetProgress { downloaded: total, total });
+            return Ok(Vec::new());
         }
 
         println!("Downloading {} missing assets for version {}", missing_assets.len(), version);
-        println!("Assets directory: {}", self.minecraft_dir.join("assets").display());
+        println!("Assets directory: {}", self.assets_dir.display());
 
-        // 5. Download missing assets with retries and rate limiting
+        // 5. Download missing assets with retries and rate limiting. Completion is
+        // tracked per asset, so a retry pass only re-attempts the ones that failed.
         let mut downloaded = 0;
-        let mut failed = Vec::new();
-        
+        let mut failed: Vec<(MissingAsset, String)> = Vec::new();
+        let attempted = missing_assets.len();
+        let mut pending = missing_assets;
+
         // Process assets in smaller batches to avoid overwhelming the server
         let batch_size = 10;
-        for chunk in missing_assets.chunks(batch_size) {
-            let mut futures = FuturesUnordered::new();
-            
-            for (name, hash, asset_path) in chunk {
-                let url = format!("https://resources.download.minecraft.net/{}/{}", &hash[0..2], hash);
-                let asset_path = asset_path.clone();
-                let name = name.clone();
-                let _hash = hash.clone();
-                
-                futures.push(async move {
-                    // Retry up to 3 times with exponential backoff
-                    for attempt in 1..=3 {
-                        match download_asset_with_retry(&url, &asset_path, &name, attempt).await {
-                            Ok(()) => return Ok(()),
-                            Err(e) => {
-                                if attempt == 3 {
-                                    return Err(e);
-                                }
-                                // Wait before retry (exponential backoff)
-                                let delay = std::time::Duration::from_millis(100 * attempt as u64);
-                                tokio::time::sleep(delay).await;
-                            }
-                        }
-                    }
-                    unreachable!()
-                });
+        for pass in 1..=ASSET_PASSES {
+            failed.clear();
+            for chunk in pending.chunks(batch_size) {
+                self.check_cancelled()?;
+                let (batch_downloaded, batch_failed) = fetch_asset_batch(chunk, |(name, hash, asset_path)| async move {
+                    fetch_asset(&name, &hash, &asset_path).await
+                }).await;
+                if (downloaded + batch_downloaded) / 50 > downloaded / 50 {
+                    println!("Downloaded {} assets...", downloaded + batch_downloaded);
+                }
+                downloaded += batch_downloaded;
+                failed.extend(batch_failed);
+
+                let _ = app.emit("asset_progress", AssetProgress { downloaded: already_present + downloaded, total });
+
+                // Rate limiting between batches
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
             }
 
-            // Await batch completion
-            while let Some(result) = futures.next().await {
-                match result {
-                    Ok(()) => {
-                        downloaded += 1;
-                        if downloaded % 50 == 0 {
-                            println!("Downloaded {} assets...", downloaded);
-                        }
-                    }
-                    Err(e) => failed.push(e),
+            if failed.is_empty() || pass == ASSET_PASSES {
+                break;
+            }
+            println!("Retrying {} failed assets", failed.len());
+            pending = failed.iter().map(|(asset, _)| asset.clone()).collect();
+        }
+        let failed: Vec<String> = failed.into_iter().map(|(_, e)| e).collect();
+
+        println!("Asset download complete: {} downloaded, {} failed", downloaded, failed.len());
+        // Failed assets are given up on, so the bar still finishes
+        let _ = app.emit("asset_progress", AssetProgress { downloaded: total, total });
+
+        if failed.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Judged against the assets actually fetched, so a mostly cached install
+        // doesn't hide a high failure rate
+        let error_count = failed.len();
+        let failure_ratio = error_count as f64 / attempted as f64;
+        if tolerate_failures && failure_ratio <= MAX_ASSET_FAILURE_RATIO {
+            for error in failed.iter().take(10) {
+                println!("Warning: {}", error);
+            }
+            return Ok(vec![format!(
+                "{} of {} assets could not be downloaded; some sounds or textures may be missing",
+                error_count,
+                attempted
+            )]);
+        }
+
+        // Only report first 10 errors to avoid overwhelming output
+        let reported_errors = failed.into_iter().take(10).collect::<Vec<_>>();
+        let mut error_msg = format!("{} assets failed to download. First {} errors:", error_count, reported_errors.len());
+        for error in reported_errors {
+            error_msg.push_str(&format!("\n  {}", error));
+        }
+        if error_count > 10 {
+            error_msg.push_str(&format!("\n  ... and {} more errors", error_count - 10));
+        }
+        Err(error_msg)
+    }
+}
+
+/// Read a pipe to the end on its own thread, so stdout and stderr can't block each other
+fn collect_output<R: std::io::Read + Send + 'static>(mut reader: R) -> std::thread::JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut output = Vec::new();
+        let _ = reader.read_to_end(&mut output);
+        output
+    })
+}
+
+/// Echo the game output, stream it as `game_log` events, keep its tail and emit
+/// `minecraft_ready` once the window is up
+fn spawn_output_reader<R: std::io::Read + Send + 'static>(
+    reader: R,
+    stream: &'static str,
+    modpack_id: &str,
+    watch: Arc<Mutex<OutputWatch>>,
+    app: AppHandle,
+) {
+    let modpack_id = modpack_id.to_string();
+    std::thread::spawn(move || {
+        for line in BufReader::new(reader).lines().map_while(Result::ok) {
+            println!("{}", line);
+            let log_line = GameLogLine { modpack_id: modpack_id.clone(), stream, line: line.clone() };
+            if let Some(lines) = GAME_LOGS.lock().unwrap().get_mut(&modpack_id) {
+                lines.push_back(log_line.clone());
+                if lines.len() > GAME_LOG_LINES {
+                    lines.pop_front();
+                }
+            }
+            let _ = app.emit("game_log", log_line);
+            let became_ready = {
+                let mut watch = watch.lock().unwrap();
+                watch.last_output = Instant::now();
+                let became_ready = !watch.ready && READY_MARKERS.iter().any(|m| line.contains(m));
+                watch.ready |= became_ready;
+                watch.tail.push_back(line);
+                if watch.tail.len() > OUTPUT_TAIL_LINES {
+                    watch.tail.pop_front();
                 }
+                became_ready
+            };
+            if became_ready {
+                let _ = app.emit("minecraft_ready", ());
             }
+        }
+    });
+}
 
-            // Rate limiting between batches
-            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+/// Warn with `launch_stalled` when the game is still running but hasn't become
+/// ready within `launch_timeout` seconds and has gone quiet. The process is left
+/// alone so the player can decide whether to kill it. A timeout of 0 disables this.
+fn watch_launch(process: &Mutex<GameProcess>, watch: Arc<Mutex<OutputWatch>>, app: AppHandle, launch_timeout: u64) {
+    if launch_timeout == 0 {
+        return;
+    }
+    let started = Instant::now();
+    loop {
+        std::thread::sleep(Duration::from_secs(1));
+        if !matches!(process.lock().unwrap().child.try_wait(), Ok(None)) {
+            return;
+        }
+        let watch = watch.lock().unwrap();
+        if watch.ready {
+            return;
         }
+        let elapsed = started.elapsed();
+        if elapsed.as_secs() >= launch_timeout && watch.last_output.elapsed().as_secs() >= STALL_IDLE_SECS {
+            println!("Warning: Minecraft has not opened a window after {}s", elapsed.as_secs());
+            let stalled = LaunchStalled {
+                elapsed_secs: elapsed.as_secs(),
+                log_tail: watch.tail.iter().cloned().collect(),
+            };
+            let _ = app.emit("launch_stalled", stalled);
+            return;
+        }
+    }
+}
 
-        println!("Asset download complete: {} downloaded, {} failed", downloaded, failed.len());
+/// `java -jar <installer>` with AWT forced headless, so installers never try to
+/// open a window (which fails outright on Linux machines without a display)
+pub(crate) fn installer_command(installer_path: &Path) -> Command {
+    let mut command = Command::new("java");
+    command.arg("-Djava.awt.headless=true").arg("-jar").arg(installer_path);
+    command
+}
+
+/// Run a modloader installer, logging its output. On failure the error carries
+/// the last lines the installer printed.
+pub(crate) fn run_installer(mut command: Command, name: &str) -> Result<(), String> {
+    let output = command
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("Failed to run {} installer: {}", name, e))?;
+    let mut combined = String::from_utf8_lossy(&output.stdout).to_string();
+    combined.push_str(&String::from_utf8_lossy(&output.stderr));
+    for line in combined.lines() {
+        println!("[{} installer] {}", name, line);
+    }
+    if output.status.success() {
+        return Ok(());
+    }
+    let lines: Vec<&str> = combined.lines().collect();
+    let tail = lines[lines.len().saturating_sub(OUTPUT_TAIL_LINES)..].join("\n");
+    Err(format!("{} installer failed ({}):\n{}", name, output.status, tail))
+}
+
+/// Version id the modloader installer creates under `versions/`: Forge writes
+/// `<mc>-forge-<forge>`, Fabric `fabric-loader-<loader>-<mc>` and NeoForge
+/// `neoforge-<neoforge>`
+fn modloader_version_id(modpack: &Modpack) -> Option<String> {
+    let mc_version = &modpack.minecraft_version;
+    if let Some(forge_version) = &modpack.forge_version {
+        Some(format!("{}-forge-{}", mc_version, strip_mc_prefix(mc_version, forge_version)))
+    } else if let Some(fabric_version) = &modpack.fabric_version {
+        Some(format!("fabric-loader-{}-{}", fabric_version, mc_version))
+    } else {
+        modpack.neoforge_version.as_ref()
+            .map(|v| format!("neoforge-{}", strip_mc_prefix(mc_version, v)))
+    }
+}
+
+/// Jars named by a `-p`/`--module-path` JVM argument. Modern Forge/NeoForge put their
+/// bootstrap jars there; those must not also appear on the classpath or the module
+/// layer fails to boot.
+fn module_path_of(jvm_args: &[String]) -> Vec<PathBuf> {
+    jvm_args
+        .iter()
+        .position(|arg| arg == "-p" || arg == "--module-path")
+        .and_then(|i| jvm_args.get(i + 1))
+        .map(|paths| std::env::split_paths(paths).collect())
+        .unwrap_or_default()
+}
+
+/// Check mod bytes against the mod's hash, if it has one
+fn verify_mod_hash(mod_info: &Mod, bytes: &[u8]) -> Result<(), String> {
+    let Some(expected) = &mod_info.hash else {
+        return Ok(());
+    };
+    let format = mod_info.hashformat.to_lowercase();
+    let actual = crate::downloader::hex_digest(&format, bytes)
+        .ok_or(format!("Unsupported hash format '{}' for mod {}", format, mod_info.name))?;
+    if !actual.eq_ignore_ascii_case(expected) {
+        return Err(format!(
+            "{} hash mismatch for mod {}: expected {}, got {}",
+            format, mod_info.name, expected, actual
+        ));
+    }
+    Ok(())
+}
+
+/// Whether a file on disk has the expected SHA-1; unreadable files count as a mismatch
+pub(crate) fn file_sha1_matches(path: &Path, expected: &str) -> bool {
+    fs::read(path).is_ok_and(|bytes| sha1_hex(&bytes).eq_ignore_ascii_case(expected))
+}
 
-        if !failed.is_empty() {
-            // Only report first 10 errors to avoid overwhelming output
-            let error_count = failed.len();
-            let reported_errors = failed.into_iter().take(10).collect::<Vec<_>>();
-            let mut error_msg = format!("{} assets failed to download. First {} errors:", error_count, reported_errors.len());
-            for error in reported_errors {
-                error_msg.push_str(&format!("\n  {}", error));
+/// Download one library to `library_path`, trying the pack200 + xz variant when
+/// the plain jar isn't published
+async fn download_library(path: &str, url: &str, library_path: &Path) -> Result<(), String> {
+    println!("Downloading library: {}", path);
+    let mut url = url.to_string();
+    let mut library_resp = http::client().get(&url).send()
+        .await
+        .map_err(|e| format!("Failed to download library {}: {}", path, e))?;
+    if !library_resp.status().is_success() && !url.ends_with(".pack.xz") {
+        // Older Forge mavens only publish some libraries as pack200 + xz
+        let packed_url = format!("{}.pack.xz", url);
+        if let Ok(resp) = http::client().get(&packed_url).send().await {
+            if resp.status().is_success() {
+                url = packed_url;
+                library_resp = resp;
+            }
+        }
+    }
+    if !library_resp.status().is_success() {
+        return Err(format!("HTTP {} while downloading library {}", library_resp.status(), path));
+    }
+
+    let library_bytes = library_resp.bytes()
+        .await
+        .map_err(|e| format!("Failed to read library bytes for {}: {}", path, e))?;
+
+    if url.ends_with(".pack.xz") {
+        return unpack_pack_xz(&library_bytes, library_path);
+    }
+
+    let mut file = fs::File::create(library_path)
+        .map_err(|e| format!("Failed to create library file {}: {}", path, e))?;
+    file.write_all(&library_bytes)
+        .map_err(|e| format!("Failed to write library file {}: {}", path, e))
+}
+
+/// Make sure a library is present and, when its SHA-1 is known, intact. A corrupt
+/// or mismatching jar is downloaded again once before giving up.
+async fn fetch_library(path: String, url: Option<String>, expected_sha1: Option<String>, library_path: PathBuf) -> Result<(), String> {
+    if library_path.exists() {
+        match &expected_sha1 {
+            Some(sha1) if !file_sha1_matches(&library_path, sha1) => {
+                println!("Library {} failed SHA-1 verification, re-downloading", path);
             }
-            if error_count > 10 {
-                error_msg.push_str(&format!("\n  ... and {} more errors", error_count - 10));
+            _ => return Ok(()),
+        }
+    }
+    let url = match url {
+        Some(url) => url,
+        None => {
+            // Installed locally by the modloader installer
+            println!("Warning: Library {} is missing and has no download URL", path);
+            return Ok(());
+        }
+    };
+
+    for attempt in 1..=2 {
+        download_library(&path, &url, &library_path).await?;
+        match &expected_sha1 {
+            Some(sha1) if !file_sha1_matches(&library_path, sha1) => {
+                if attempt == 1 {
+                    println!("Downloaded library {} failed SHA-1 verification, retrying", path);
+                }
             }
-            return Err(error_msg);
+            _ => return Ok(()),
         }
+    }
+    Err(format!("Library {} failed SHA-1 verification after re-downloading", library_path.display()))
+}
 
-        Ok(())
+/// Lowercase hex SHA-1 digest of `bytes`
+fn sha1_hex(bytes: &[u8]) -> String {
+    use sha1::{Digest, Sha1};
+    let mut hasher = Sha1::new();
+    hasher.update(bytes);
+    hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Repository path of a maven coordinate `group:artifact:version[:classifier][@ext]`,
+/// e.g. `net.minecraftforge:forge:1.12.2-14.23.5.2860` becomes
+/// `net/minecraftforge/forge/1.12.2-14.23.5.2860/forge-1.12.2-14.23.5.2860.jar`
+fn maven_path(coordinate: &str) -> Option<String> {
+    let (coordinate, extension) = coordinate.split_once('@').unwrap_or((coordinate, "jar"));
+    let mut parts = coordinate.split(':');
+    let group = parts.next()?;
+    let artifact = parts.next()?;
+    let version = parts.next()?;
+    let file_name = match parts.next() {
+        Some(classifier) => format!("{}-{}-{}.{}", artifact, version, classifier, extension),
+        None => format!("{}-{}.{}", artifact, version, extension),
+    };
+    Some(format!("{}/{}/{}/{}", group.replace('.', "/"), artifact, version, file_name))
+}
+
+/// `unpack200` next to the configured Java, falling back to the one on PATH
+fn unpack200_path() -> PathBuf {
+    let exe = if cfg!(target_os = "windows") { "unpack200.exe" } else { "unpack200" };
+    crate::settings::load_settings()
+        .ok()
+        .and_then(|s| PathBuf::from(s.java_path).parent().map(|dir| dir.join(exe)))
+        .filter(|p| p.exists())
+        .unwrap_or_else(|| PathBuf::from(exe))
+}
+
+/// Turn a Forge `.jar.pack.xz` download into the jar: xz-decompress it, strip the
+/// signature trailer and run `unpack200` (shipped with Java 8)
+fn unpack_pack_xz(bytes: &[u8], jar_path: &Path) -> Result<(), String> {
+    let mut packed = Vec::new();
+    lzma_rs::xz_decompress(&mut std::io::Cursor::new(bytes), &mut packed)
+        .map_err(|e| format!("Failed to decompress {}: {}", jar_path.display(), e))?;
+
+    // Forge appends the checksums, their length and a "SIGN" marker after the pack
+    if packed.len() >= 8 && packed.ends_with(b"SIGN") {
+        let length_start = packed.len() - 8;
+        let mut length_bytes = [0u8; 4];
+        length_bytes.copy_from_slice(&packed[length_start..length_start + 4]);
+        let checksums_len = u32::from_le_bytes(length_bytes) as usize;
+        let pack_end = length_start.checked_sub(checksums_len)
+            .ok_or_else(|| format!("Invalid signature trailer in {}", jar_path.display()))?;
+        packed.truncate(pack_end);
+    }
+
+    let pack_path = jar_path.with_extension("jar.pack");
+    fs::write(&pack_path, &packed)
+        .map_err(|e| format!("Failed to write {}: {}", pack_path.display(), e))?;
+    let output = Command::new(unpack200_path())
+        .arg(&pack_path)
+        .arg(jar_path)
+        .output();
+    let _ = fs::remove_file(&pack_path);
+    let output = output.map_err(|e| format!("Failed to run unpack200 (requires Java 8): {}", e))?;
+    if !output.status.success() {
+        let _ = fs::remove_file(jar_path);
+        return Err(format!(
+            "unpack200 failed for {}: {}",
+            jar_path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
     }
+    Ok(())
+}
+
+/// Releases before 1.18.1 ship a log4j affected by CVE-2021-44228
+fn is_log4j_vulnerable(version: &str) -> bool {
+    let is_release = version.starts_with("1.")
+        && version.split('.').all(|p| p.parse::<u32>().is_ok());
+    is_release && crate::version::compare_versions(version, "1.18.1") == std::cmp::Ordering::Less
 }
 
 /// Helper function to download a single asset with retry logic
-async fn download_asset_with_retry(url: &str, asset_path: &PathBuf, name: &str, attempt: u32) -> Result<(), String> {
-    let client = reqwest::Client::builder()
+async fn download_asset_with_retry(url: &str, asset_path: &PathBuf, name: &str, expected_hash: &str, attempt: u32) -> Result<(), String> {
+    let resp = http::client().get(url)
         .timeout(std::time::Duration::from_secs(30))
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
-
-    let resp = client.get(url)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
         .send()
         .await
         .map_err(|e| format!("Failed to download asset {} (attempt {}): {}", name, attempt, e))?;
@@ -1133,11 +2637,300 @@ async fn download_asset_with_retry(url: &str, asset_path: &PathBuf, name: &str,
         .await
         .map_err(|e| format!("Failed to read asset bytes for {} (attempt {}): {}", name, attempt, e))?;
 
+    // Objects are named after their SHA-1, so never store content that doesn't match it
+    let actual_hash = sha1_hex(&bytes);
+    if !actual_hash.eq_ignore_ascii_case(expected_hash) {
+        return Err(format!(
+            "Hash mismatch for asset {} (attempt {}): expected {}, got {}",
+            name, attempt, expected_hash, actual_hash
+        ));
+    }
+
     fs::create_dir_all(asset_path.parent().unwrap())
         .map_err(|e| format!("Failed to create asset dir: {}", e))?;
-    
-    fs::write(asset_path, &bytes)
+
+    // Write beside the object and rename, so an interrupted write never leaves a
+    // truncated file that later runs would take for a finished download
+    let tmp_path = asset_path.with_extension("part");
+    fs::write(&tmp_path, &bytes)
+        .map_err(|e| format!("Failed to write asset file: {}", e))?;
+    fs::rename(&tmp_path, asset_path)
         .map_err(|e| format!("Failed to write asset file: {}", e))?;
 
     Ok(())
 }
+
+/// An asset object still to fetch: its name, hash and destination path
+type MissingAsset = (String, String, PathBuf);
+
+/// Fetch one batch of assets concurrently. Each asset's outcome is tracked on its
+/// own, so the caller retries exactly the ones that failed rather than the batch.
+async fn fetch_asset_batch<F, Fut>(chunk: &[MissingAsset], fetch: F) -> (usize, Vec<(MissingAsset, String)>)
+where
+    F: Fn(MissingAsset) -> Fut,
+    Fut: std::future::Future<Output = Result<(), String>>,
+{
+    let mut futures = FuturesUnordered::new();
+    for asset in chunk {
+        let fetching = fetch(asset.clone());
+        futures.push(async move { (asset, fetching.await) });
+    }
+    let mut downloaded = 0;
+    let mut failed = Vec::new();
+    while let Some((asset, result)) = futures.next().await {
+        match result {
+            Ok(()) => downloaded += 1,
+            Err(e) => failed.push((asset.clone(), e)),
+        }
+    }
+    (downloaded, failed)
+}
+
+/// Whether an asset object is already on disk with the expected content
+fn asset_matches(asset_path: &Path, expected_hash: &str) -> bool {
+    fs::read(asset_path)
+        .map(|bytes| sha1_hex(&bytes).eq_ignore_ascii_case(expected_hash))
+        .unwrap_or(false)
+}
+
+/// Download one asset object, retrying up to 3 times with exponential backoff. An
+/// object already present with the right hash counts as done, so re-running a
+/// batch never fetches it again.
+async fn fetch_asset(name: &str, hash: &str, asset_path: &PathBuf) -> Result<(), String> {
+    let url = format!("https://resources.download.minecraft.net/{}/{}", &hash[0..2], hash);
+    for attempt in 1..=3 {
+        if asset_matches(asset_path, hash) {
+            return Ok(());
+        }
+        match download_asset_with_retry(&url, asset_path, name, hash, attempt).await {
+            Ok(()) => return Ok(()),
+            Err(e) => {
+                if attempt == 3 {
+                    return Err(e);
+                }
+                // Wait before retry (exponential backoff)
+                let delay = std::time::Duration::from_millis(100 * attempt as u64);
+                tokio::time::sleep(delay).await;
+            }
+        }
+    }
+    unreachable!()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+
+    #[test]
+    fn installer_command_runs_the_jar_headless() {
+        let command = installer_command(Path::new("/tmp/forge-installer.jar"));
+        assert_eq!(command.get_program(), OsStr::new("java"));
+        let args: Vec<&OsStr> = command.get_args().collect();
+        assert_eq!(args, ["-Djava.awt.headless=true", "-jar", "/tmp/forge-installer.jar"]);
+    }
+
+    #[test]
+    fn installer_arguments_follow_the_jar() {
+        let mut command = installer_command(Path::new("installer.jar"));
+        command.arg("--installClient").arg("/games/minecraft");
+        let args: Vec<&OsStr> = command.get_args().collect();
+        assert_eq!(args[0], "-Djava.awt.headless=true");
+        assert_eq!(&args[1..], ["-jar", "installer.jar", "--installClient", "/games/minecraft"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_installer_succeeds_on_zero_exit() {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg("echo installed");
+        assert!(run_installer(command, "Test").is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_installer_failure_carries_the_output_tail() {
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg("i=1; while [ $i -le 60 ]; do echo line$i; i=$((i+1)); done; echo boom >&2; exit 3");
+        let error = run_installer(command, "Test").unwrap_err();
+        assert!(error.starts_with("Test installer failed"));
+        let tail: Vec<&str> = error.lines().skip(1).collect();
+        assert_eq!(tail.len(), OUTPUT_TAIL_LINES);
+        assert_eq!(tail.last(), Some(&"boom"));
+        assert!(!tail.contains(&"line10"));
+        assert!(tail.contains(&"line60"));
+    }
+
+    fn missing_asset(name: &str) -> MissingAsset {
+        (name.to_string(), format!("{}hash", name), PathBuf::from(name))
+    }
+
+    #[test]
+    fn asset_retry_only_refetches_the_failed_assets() {
+        let batch: Vec<MissingAsset> = ["a", "b", "c", "d"].into_iter().map(missing_asset).collect();
+        let calls: Mutex<HashMap<String, u32>> = Mutex::new(HashMap::new());
+        let fetch = |(name, _, _): MissingAsset| {
+            let calls = &calls;
+            async move {
+                let mut calls = calls.lock().unwrap();
+                let count = calls.entry(name.clone()).or_insert(0);
+                *count += 1;
+                // "b" fails mid-batch the first time and succeeds on retry
+                if name == "b" && *count == 1 {
+                    Err("connection reset".to_string())
+                } else {
+                    Ok(())
+                }
+            }
+        };
+
+        let (downloaded, failed) = futures::executor::block_on(fetch_asset_batch(&batch, fetch));
+        assert_eq!(downloaded, 3);
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0 .0, "b");
+
+        let retry: Vec<MissingAsset> = failed.into_iter().map(|(asset, _)| asset).collect();
+        let (downloaded, failed) = futures::executor::block_on(fetch_asset_batch(&retry, fetch));
+        assert_eq!(downloaded, 1);
+        assert!(failed.is_empty());
+
+        let calls = calls.lock().unwrap();
+        assert_eq!(calls["a"], 1);
+        assert_eq!(calls["b"], 2);
+        assert_eq!(calls["c"], 1);
+        assert_eq!(calls["d"], 1);
+    }
+
+    #[test]
+    fn asset_already_on_disk_is_not_fetched_again() {
+        let dir = std::env::temp_dir().join(format!("wise0wl-assets-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let content = b"asset bytes";
+        let hash = sha1_hex(content);
+        let asset_path = dir.join(&hash);
+        fs::write(&asset_path, content).unwrap();
+
+        assert!(asset_matches(&asset_path, &hash));
+        // Matches before any request is made, so this needs no network
+        assert!(futures::executor::block_on(fetch_asset("icon.png", &hash, &asset_path)).is_ok());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn truncated_asset_does_not_count_as_done() {
+        let dir = std::env::temp_dir().join(format!("wise0wl-assets-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let hash = sha1_hex(b"asset bytes");
+        let asset_path = dir.join(&hash);
+        fs::write(&asset_path, b"asset").unwrap();
+
+        assert!(!asset_matches(&asset_path, &hash));
+        assert!(!asset_matches(&dir.join("missing"), &hash));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    const VERSION_JSON_WITH_EXTRAS: &str = r#"{
+        "id": "1.99",
+        "mainClass": "net.minecraft.client.main.Main",
+        "complianceLevel": 1,
+        "javaVersion": { "component": "java-runtime-delta", "majorVersion": 21 },
+        "someFutureField": ["a", "b"],
+        "arguments": { "game": ["--username", "${auth_player_name}"] },
+        "downloads": {
+            "client": { "url": "https://example.com/client.jar", "size": 1, "sha1": "abc" }
+        },
+        "assetIndex": { "id": "17", "url": "https://example.com/17.json" }
+    }"#;
+
+    #[test]
+    fn version_json_with_unknown_fields_parses() {
+        let details: VersionDetails = serde_json::from_str(VERSION_JSON_WITH_EXTRAS).unwrap();
+        assert_eq!(details.id, "1.99");
+        assert_eq!(details.extra["complianceLevel"], 1);
+        assert_eq!(details.extra["javaVersion"]["majorVersion"], 21);
+        assert!(details.extra.contains_key("someFutureField"));
+    }
+
+    #[test]
+    fn version_json_missing_optional_fields_uses_defaults() {
+        let details: VersionDetails = serde_json::from_str(VERSION_JSON_WITH_EXTRAS).unwrap();
+        assert!(details.libraries.is_empty());
+        assert!(details.version_type.is_empty());
+        assert!(details.assets.is_empty());
+        assert_eq!(details.asset_index.total_size, 0);
+        assert!(details.arguments.unwrap().jvm.is_empty());
+    }
+
+    #[test]
+    fn unknown_version_fields_survive_a_re_save() {
+        let details: VersionDetails = serde_json::from_str(VERSION_JSON_WITH_EXTRAS).unwrap();
+        let saved = serde_json::to_value(&details).unwrap();
+        assert_eq!(saved["complianceLevel"], 1);
+        assert_eq!(saved["someFutureField"][1], "b");
+    }
+
+    fn modpack_with_loader(loader_field: &str, loader_version: &str) -> Modpack {
+        let mut pack = serde_json::json!({
+            "id": "pack",
+            "name": "Pack",
+            "description": "",
+            "version": "1.0.0",
+            "minecraftVersion": "1.20.4",
+            "image": "",
+            "mods": [],
+            "lastUpdated": ""
+        });
+        pack[loader_field] = serde_json::Value::from(loader_version);
+        serde_json::from_value(pack).unwrap()
+    }
+
+    #[test]
+    fn modloader_version_ids_match_what_the_installers_write() {
+        assert_eq!(modloader_version_id(&modpack_with_loader("forgeVersion", "49.0.30")).unwrap(), "1.20.4-forge-49.0.30");
+        assert_eq!(modloader_version_id(&modpack_with_loader("forgeVersion", "1.20.4-49.0.30")).unwrap(), "1.20.4-forge-49.0.30");
+        assert_eq!(modloader_version_id(&modpack_with_loader("fabricVersion", "0.15.7")).unwrap(), "fabric-loader-0.15.7-1.20.4");
+        assert_eq!(modloader_version_id(&modpack_with_loader("neoforgeVersion", "1.20.4-20.4.80")).unwrap(), "neoforge-20.4.80");
+    }
+
+    #[test]
+    fn vanilla_modpack_has_no_modloader_version() {
+        assert!(modloader_version_id(&modpack_with_loader("changelog", "")).is_none());
+    }
+
+    fn test_launcher() -> MinecraftLauncher {
+        MinecraftLauncher {
+            minecraft_dir: PathBuf::from("/minecraft"),
+            assets_dir: PathBuf::from("/minecraft/assets"),
+            demo: false,
+            cancel: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    #[test]
+    fn url_only_profile_library_downloads_from_its_maven_repository() {
+        let profile: ModloaderProfile = serde_json::from_str(r#"{
+            "id": "fabric-loader-0.15.7-1.20.4",
+            "inheritsFrom": "1.20.4",
+            "libraries": [
+                { "name": "net.fabricmc:sponge-mixin:0.12.5+mixin.0.8.5", "url": "https://maven.fabricmc.net/" }
+            ]
+        }"#).unwrap();
+        let (path, url) = test_launcher().library_artifact(&profile.libraries[0]).unwrap();
+        assert_eq!(path, "net/fabricmc/sponge-mixin/0.12.5+mixin.0.8.5/sponge-mixin-0.12.5+mixin.0.8.5.jar");
+        assert_eq!(
+            url.as_deref(),
+            Some("https://maven.fabricmc.net/net/fabricmc/sponge-mixin/0.12.5+mixin.0.8.5/sponge-mixin-0.12.5+mixin.0.8.5.jar")
+        );
+    }
+
+    #[test]
+    fn coordinate_only_library_without_url_has_no_download() {
+        let library: Library = serde_json::from_str(r#"{ "name": "net.minecraftforge:forge:1.12.2-14.23.5.2860" }"#).unwrap();
+        let (path, url) = test_launcher().library_artifact(&library).unwrap();
+        assert_eq!(path, "net/minecraftforge/forge/1.12.2-14.23.5.2860/forge-1.12.2-14.23.5.2860.jar");
+        assert!(url.is_none());
+    }
+}
+
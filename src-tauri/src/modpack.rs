@@ -1,7 +1,6 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
-use dirs;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Mod {
@@ -12,6 +11,21 @@ pub struct Mod {
     #[serde(rename = "downloadUrl")]
     pub download_url: String,
     pub hash: Option<String>,
+    /// Algorithm of `hash`: `sha1`, `sha256` or `md5`
+    #[serde(default = "default_hash_format")]
+    pub hashformat: String,
+    #[serde(default)]
+    pub optional: bool,
+    #[serde(rename = "defaultEnabled", default = "default_enabled")]
+    pub default_enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_hash_format() -> String {
+    "sha1".to_string()
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -28,6 +42,9 @@ pub struct Modpack {
     pub fabric_version: Option<String>,
     #[serde(rename = "neoforgeVersion")]
     pub neoforge_version: Option<String>,
+    /// Pins the modloader installer when the latest one breaks a loader/MC combination
+    #[serde(rename = "installerVersion", default)]
+    pub installer_version: Option<String>,
     pub image: String,
     pub mods: Vec<Mod>,
     #[serde(rename = "lastUpdated")]
@@ -35,25 +52,89 @@ pub struct Modpack {
     pub changelog: Option<String>,
 }
 
+impl Modpack {
+    /// A pack may use at most one modloader; picking one of several would
+    /// silently install the wrong one
+    pub fn validate_modloader(&self) -> Result<(), String> {
+        let loaders: Vec<&str> = [
+            ("forgeVersion", &self.forge_version),
+            ("fabricVersion", &self.fabric_version),
+            ("neoforgeVersion", &self.neoforge_version),
+        ]
+        .iter()
+        .filter(|(_, version)| version.is_some())
+        .map(|(field, _)| *field)
+        .collect();
+        if loaders.len() > 1 {
+            return Err(format!(
+                "modpack '{}' sets more than one modloader ({}); only one is allowed",
+                self.id,
+                loaders.join(", ")
+            ));
+        }
+        Ok(())
+    }
+}
+
 fn get_modpacks_path() -> PathBuf {
-    dirs::config_dir()
+    crate::paths::config_dir()
         .expect("Failed to get config directory")
         .join("wise0wl-launcher")
         .join("modpacks.json")
 }
 
-pub fn get_all_modpacks() -> Result<Vec<Modpack>, String> {
+/// Modpacks that parsed, plus one warning per entry that was skipped
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct LoadedModpacks {
+    pub modpacks: Vec<Modpack>,
+    pub warnings: Vec<String>,
+}
+
+/// Validate each entry of a modpacks file on its own so one bad entry doesn't
+/// hide the rest; errors name the entry (by id when it has one) and the field
+fn parse_modpacks(content: &str) -> Result<LoadedModpacks, String> {
+    let json: serde_json::Value = serde_json::from_str(content)
+        .map_err(|e| format!("Failed to parse modpacks: {}", e))?;
+    let entries = json.as_array().ok_or("Failed to parse modpacks: expected a list of modpacks")?;
+
+    let mut loaded = LoadedModpacks::default();
+    for (index, entry) in entries.iter().enumerate() {
+        let label = match entry.get("id").and_then(|id| id.as_str()) {
+            Some(id) => format!("modpack '{}'", id),
+            None => format!("modpack #{}", index + 1),
+        };
+        match serde_json::from_value::<Modpack>(entry.clone()) {
+            Ok(modpack) => match modpack.validate_modloader() {
+                Ok(()) => loaded.modpacks.push(modpack),
+                Err(e) => loaded.warnings.push(format!("Skipping {}: {}", label, e)),
+            },
+            Err(e) => loaded.warnings.push(format!("Skipping {}: {}", label, e)),
+        }
+    }
+    Ok(loaded)
+}
+
+pub fn load_modpacks() -> Result<LoadedModpacks, String> {
     let modpacks_path = get_modpacks_path();
 
     if !modpacks_path.exists() {
-        return Ok(get_default_modpacks());
+        return Ok(LoadedModpacks {
+            modpacks: get_default_modpacks(),
+            warnings: Vec::new(),
+        });
     }
 
-    fs::read_to_string(&modpacks_path)
-        .map_err(|e| format!("Failed to read modpacks file: {}", e))
-        .and_then(|content| {
-            serde_json::from_str(&content).map_err(|e| format!("Failed to parse modpacks: {}", e))
-        })
+    let content = fs::read_to_string(&modpacks_path)
+        .map_err(|e| format!("Failed to read modpacks file: {}", e))?;
+    parse_modpacks(&content)
+}
+
+pub fn get_all_modpacks() -> Result<Vec<Modpack>, String> {
+    let loaded = load_modpacks()?;
+    for warning in &loaded.warnings {
+        println!("Warning: {}", warning);
+    }
+    Ok(loaded.modpacks)
 }
 
 fn get_default_modpacks() -> Vec<Modpack> {
@@ -67,6 +148,7 @@ fn get_default_modpacks() -> Vec<Modpack> {
             forge_version: None,
             fabric_version: None,
             neoforge_version: None,
+            installer_version: None,
             image: "/images/WOLOGO.png".to_string(),
             mods: vec![],
             last_updated: "2024-03-20".to_string(),
@@ -81,6 +163,7 @@ fn get_default_modpacks() -> Vec<Modpack> {
             forge_version: Some("47.2.0".to_string()),
             fabric_version: None,
             neoforge_version: None,
+            installer_version: None,
             image: "/images/WOLOGO.png".to_string(),
             mods: vec![Mod {
                 id: "optifine".to_string(),
@@ -90,6 +173,9 @@ fn get_default_modpacks() -> Vec<Modpack> {
                 download_url: "https://optifine.net/download?f=OptiFine_1.20.1_HD_U_I7.jar"
                     .to_string(),
                 hash: None,
+                hashformat: default_hash_format(),
+                optional: false,
+                default_enabled: true,
             }],
             last_updated: "2024-03-20".to_string(),
             changelog: None,
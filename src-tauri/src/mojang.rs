@@ -0,0 +1,105 @@
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use sha1::{Digest, Sha1};
+
+use crate::http;
+
+pub const VERSION_MANIFEST_URL: &str = "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json";
+
+/// Hosts serving the same launcher metadata, tried in order
+const META_HOSTS: &[&str] = &["piston-meta.mojang.com", "launchermeta.mojang.com"];
+
+/// The URL followed by the same path on every other metadata host
+fn candidate_urls(url: &str) -> Vec<String> {
+    let mut urls = vec![url.to_string()];
+    if let Some(host) = META_HOSTS.iter().find(|h| url.starts_with(&format!("https://{}/", h))) {
+        for alternate in META_HOSTS.iter().filter(|h| *h != host) {
+            urls.push(url.replacen(host, alternate, 1));
+        }
+    }
+    urls
+}
+
+async fn fetch_json<T: DeserializeOwned>(url: &str) -> Result<T, String> {
+    let resp = http::client().get(url).send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?;
+    resp.json().await.map_err(|e| e.to_string())
+}
+
+/// Fetch and parse a Mojang metadata document, falling back to the mirror
+/// hosts when the primary one is unreachable or returns an error
+pub async fn fetch_meta<T: DeserializeOwned>(url: &str, what: &str) -> Result<T, String> {
+    let mut errors = Vec::new();
+    for candidate in candidate_urls(url) {
+        match fetch_json(&candidate).await {
+            Ok(value) => return Ok(value),
+            Err(e) => {
+                println!("Warning: Failed to fetch {} from {}: {}", what, candidate, e);
+                errors.push(e);
+            }
+        }
+    }
+    Err(format!("Failed to fetch {}: {}", what, errors.join("; ")))
+}
+
+#[derive(Debug, Deserialize)]
+struct VersionManifest {
+    versions: Vec<MinecraftVersion>,
+}
+
+/// A version entry of the manifest
+#[derive(Debug, Deserialize)]
+struct MinecraftVersion {
+    id: String,
+    url: String,
+    /// SHA-1 of the version's detail JSON
+    sha1: Option<String>,
+}
+
+async fn fetch_bytes(url: &str) -> Result<Vec<u8>, String> {
+    let resp = http::client().get(url).send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?;
+    resp.bytes().await.map(|b| b.to_vec()).map_err(|e| e.to_string())
+}
+
+/// Fetch the raw version JSON for the given Minecraft version, checked against the
+/// manifest's SHA-1. A mismatching copy (e.g. a corrupt cache on one host) is
+/// discarded and the next host tried.
+pub async fn fetch_version_json_bytes(version: &str) -> Result<Vec<u8>, String> {
+    let manifest: VersionManifest = fetch_meta(VERSION_MANIFEST_URL, "version manifest").await?;
+    let entry = manifest.versions.iter().find(|v| v.id == version)
+        .ok_or(format!("Version {} not found in manifest", version))?;
+
+    let mut errors = Vec::new();
+    for candidate in candidate_urls(&entry.url) {
+        let bytes = match fetch_bytes(&candidate).await {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                println!("Warning: Failed to fetch version details from {}: {}", candidate, e);
+                errors.push(e);
+                continue;
+            }
+        };
+        let actual: String = Sha1::digest(&bytes).iter().map(|b| format!("{:02x}", b)).collect();
+        match &entry.sha1 {
+            Some(expected) if !expected.eq_ignore_ascii_case(&actual) => {
+                let e = format!("SHA-1 mismatch (expected {}, got {})", expected, actual);
+                println!("Warning: Version JSON for {} from {}: {}", version, candidate, e);
+                errors.push(e);
+            }
+            _ => return Ok(bytes),
+        }
+    }
+    Err(format!("Failed to fetch version details for {}: {}", version, errors.join("; ")))
+}
+
+/// Fetch the version JSON for the given Minecraft version from Mojang's manifest
+pub async fn fetch_version_json(version: &str) -> Result<serde_json::Value, String> {
+    let bytes = fetch_version_json_bytes(version).await?;
+    serde_json::from_slice(&bytes)
+        .map_err(|e| format!("Failed to parse version details for {}: {}", version, e))
+}
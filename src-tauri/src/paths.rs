@@ -0,0 +1,45 @@
+use std::path::PathBuf;
+use once_cell::sync::Lazy;
+
+/// Set to anything but "0" (or pass `--portable`) to keep all launcher data beside the executable
+const PORTABLE_ENV: &str = "WISE0WL_PORTABLE";
+
+static PORTABLE_ROOT: Lazy<Option<PathBuf>> = Lazy::new(|| {
+    let from_env = std::env::var(PORTABLE_ENV).is_ok_and(|v| !v.is_empty() && v != "0");
+    let from_args = std::env::args().any(|arg| arg == "--portable");
+    if !from_env && !from_args {
+        return None;
+    }
+    let exe = std::env::current_exe().ok()?;
+    let root = exe.parent()?.join("data");
+    println!("Portable mode: storing launcher data in {}", root.display());
+    Some(root)
+});
+
+/// The `data/` folder beside the executable when running in portable mode
+pub fn portable_root() -> Option<PathBuf> {
+    PORTABLE_ROOT.clone()
+}
+
+pub fn is_portable() -> bool {
+    PORTABLE_ROOT.is_some()
+}
+
+// Drop-in replacements for the `dirs` functions that honour portable mode
+
+pub fn config_dir() -> Option<PathBuf> {
+    portable_root().map(|root| root.join("config")).or_else(dirs::config_dir)
+}
+
+// The portable root is already the `data/` folder
+pub fn data_dir() -> Option<PathBuf> {
+    portable_root().or_else(dirs::data_dir)
+}
+
+pub fn cache_dir() -> Option<PathBuf> {
+    portable_root().map(|root| root.join("cache")).or_else(dirs::cache_dir)
+}
+
+pub fn home_dir() -> Option<PathBuf> {
+    portable_root().or_else(dirs::home_dir)
+}
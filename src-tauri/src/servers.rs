@@ -0,0 +1,369 @@
+use std::fs;
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use tauri::command;
+
+use crate::downloader::get_instance_dir;
+
+/// A multiplayer server entry shipped with a modpack
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ServerEntry {
+    pub name: String,
+    pub ip: String,
+    #[serde(default)]
+    pub accept_textures: Option<bool>,
+}
+
+/// The subset of NBT needed to round-trip `servers.dat` without losing fields
+#[derive(Debug, Clone)]
+enum Tag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<u8>),
+    String(String),
+    List(u8, Vec<Tag>),
+    Compound(Vec<(String, Tag)>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl Tag {
+    fn id(&self) -> u8 {
+        match self {
+            Tag::Byte(_) => 1,
+            Tag::Short(_) => 2,
+            Tag::Int(_) => 3,
+            Tag::Long(_) => 4,
+            Tag::Float(_) => 5,
+            Tag::Double(_) => 6,
+            Tag::ByteArray(_) => 7,
+            Tag::String(_) => 8,
+            Tag::List(_, _) => 9,
+            Tag::Compound(_) => 10,
+            Tag::IntArray(_) => 11,
+            Tag::LongArray(_) => 12,
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&Tag> {
+        match self {
+            Tag::Compound(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Tag::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+struct NbtReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> NbtReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self.pos.checked_add(len).filter(|end| *end <= self.data.len())
+            .ok_or("Unexpected end of NBT data")?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let mut out = [0u8; N];
+        out.copy_from_slice(self.take(N)?);
+        Ok(out)
+    }
+
+    fn length(&mut self) -> Result<usize, String> {
+        let len = i32::from_be_bytes(self.array()?);
+        usize::try_from(len).map_err(|_| format!("Negative NBT length {}", len))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let len = u16::from_be_bytes(self.array()?) as usize;
+        Ok(String::from_utf8_lossy(self.take(len)?).to_string())
+    }
+
+    fn payload(&mut self, id: u8) -> Result<Tag, String> {
+        Ok(match id {
+            1 => Tag::Byte(i8::from_be_bytes(self.array()?)),
+            2 => Tag::Short(i16::from_be_bytes(self.array()?)),
+            3 => Tag::Int(i32::from_be_bytes(self.array()?)),
+            4 => Tag::Long(i64::from_be_bytes(self.array()?)),
+            5 => Tag::Float(f32::from_be_bytes(self.array()?)),
+            6 => Tag::Double(f64::from_be_bytes(self.array()?)),
+            7 => {
+                let len = self.length()?;
+                Tag::ByteArray(self.take(len)?.to_vec())
+            }
+            8 => Tag::String(self.string()?),
+            9 => {
+                let item_id = self.array::<1>()?[0];
+                let len = self.length()?;
+                let mut items = Vec::new();
+                for _ in 0..len {
+                    items.push(self.payload(item_id)?);
+                }
+                Tag::List(item_id, items)
+            }
+            10 => {
+                let mut entries = Vec::new();
+                loop {
+                    let entry_id = self.array::<1>()?[0];
+                    if entry_id == 0 {
+                        break;
+                    }
+                    let name = self.string()?;
+                    entries.push((name, self.payload(entry_id)?));
+                }
+                Tag::Compound(entries)
+            }
+            11 => {
+                let len = self.length()?;
+                let mut values = Vec::new();
+                for _ in 0..len {
+                    values.push(i32::from_be_bytes(self.array()?));
+                }
+                Tag::IntArray(values)
+            }
+            12 => {
+                let len = self.length()?;
+                let mut values = Vec::new();
+                for _ in 0..len {
+                    values.push(i64::from_be_bytes(self.array()?));
+                }
+                Tag::LongArray(values)
+            }
+            other => return Err(format!("Unknown NBT tag type {}", other)),
+        })
+    }
+}
+
+fn write_string(out: &mut Vec<u8>, value: &str) {
+    out.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    out.extend_from_slice(value.as_bytes());
+}
+
+fn write_payload(out: &mut Vec<u8>, tag: &Tag) {
+    match tag {
+        Tag::Byte(v) => out.extend_from_slice(&v.to_be_bytes()),
+        Tag::Short(v) => out.extend_from_slice(&v.to_be_bytes()),
+        Tag::Int(v) => out.extend_from_slice(&v.to_be_bytes()),
+        Tag::Long(v) => out.extend_from_slice(&v.to_be_bytes()),
+        Tag::Float(v) => out.extend_from_slice(&v.to_be_bytes()),
+        Tag::Double(v) => out.extend_from_slice(&v.to_be_bytes()),
+        Tag::ByteArray(bytes) => {
+            out.extend_from_slice(&(bytes.len() as i32).to_be_bytes());
+            out.extend_from_slice(bytes);
+        }
+        Tag::String(s) => write_string(out, s),
+        Tag::List(item_id, items) => {
+            out.push(*item_id);
+            out.extend_from_slice(&(items.len() as i32).to_be_bytes());
+            for item in items {
+                write_payload(out, item);
+            }
+        }
+        Tag::Compound(entries) => {
+            for (name, value) in entries {
+                out.push(value.id());
+                write_string(out, name);
+                write_payload(out, value);
+            }
+            out.push(0);
+        }
+        Tag::IntArray(values) => {
+            out.extend_from_slice(&(values.len() as i32).to_be_bytes());
+            for v in values {
+                out.extend_from_slice(&v.to_be_bytes());
+            }
+        }
+        Tag::LongArray(values) => {
+            out.extend_from_slice(&(values.len() as i32).to_be_bytes());
+            for v in values {
+                out.extend_from_slice(&v.to_be_bytes());
+            }
+        }
+    }
+}
+
+/// Read the root compound of an uncompressed NBT file such as `servers.dat`
+fn read_nbt_file(path: &Path) -> Result<Tag, String> {
+    let data = fs::read(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let mut reader = NbtReader { data: &data, pos: 0 };
+    let root_id = reader.array::<1>()?[0];
+    if root_id != 10 {
+        return Err(format!("{} does not start with an NBT compound", path.display()));
+    }
+    reader.string()?;
+    reader.payload(root_id)
+}
+
+fn write_nbt_file(path: &Path, root: &Tag) -> Result<(), String> {
+    let mut out = vec![root.id()];
+    write_string(&mut out, "");
+    write_payload(&mut out, root);
+    // Write next to the target first so a crash never leaves a truncated server list
+    let tmp_path = path.with_extension("dat.tmp");
+    fs::write(&tmp_path, &out)
+        .map_err(|e| format!("Failed to write {}: {}", tmp_path.display(), e))?;
+    fs::rename(&tmp_path, path)
+        .map_err(|e| format!("Failed to replace {}: {}", path.display(), e))
+}
+
+fn server_tag(server: &ServerEntry) -> Tag {
+    let mut entries = vec![
+        ("name".to_string(), Tag::String(server.name.clone())),
+        ("ip".to_string(), Tag::String(server.ip.clone())),
+    ];
+    if let Some(accept) = server.accept_textures {
+        entries.push(("acceptTextures".to_string(), Tag::Byte(accept as i8)));
+    }
+    Tag::Compound(entries)
+}
+
+fn server_entry(tag: &Tag) -> Option<ServerEntry> {
+    Some(ServerEntry {
+        name: tag.get("name").and_then(Tag::as_str).unwrap_or("").to_string(),
+        ip: tag.get("ip")?.as_str()?.to_string(),
+        accept_textures: match tag.get("acceptTextures") {
+            Some(Tag::Byte(v)) => Some(*v != 0),
+            _ => None,
+        },
+    })
+}
+
+/// Add the modpack's servers to the instance's `servers.dat`. Servers already in
+/// the list (matched by address) are left exactly as the player has them.
+pub(crate) fn merge_servers(game_dir: &Path, servers: &[ServerEntry]) -> Result<usize, String> {
+    if servers.is_empty() {
+        return Ok(0);
+    }
+    let path = game_dir.join("servers.dat");
+    let mut root = if path.exists() {
+        read_nbt_file(&path)?
+    } else {
+        Tag::Compound(Vec::new())
+    };
+    let entries = match &mut root {
+        Tag::Compound(entries) => entries,
+        _ => return Err("servers.dat root is not a compound".to_string()),
+    };
+    if !entries.iter().any(|(k, _)| k == "servers") {
+        entries.push(("servers".to_string(), Tag::List(10, Vec::new())));
+    }
+    let list = entries.iter_mut().find(|(k, _)| k == "servers").map(|(_, v)| v);
+    let items = match list {
+        // An empty list may have been saved with the End type
+        Some(Tag::List(item_id, items)) if *item_id == 10 || items.is_empty() => {
+            *item_id = 10;
+            items
+        }
+        _ => return Err("servers.dat has an invalid server list".to_string()),
+    };
+
+    let mut added = 0;
+    for server in servers {
+        let exists = items
+            .iter()
+            .filter_map(server_entry)
+            .any(|existing| existing.ip.eq_ignore_ascii_case(&server.ip));
+        if !exists {
+            items.push(server_tag(server));
+            added += 1;
+        }
+    }
+    if added > 0 {
+        fs::create_dir_all(game_dir)
+            .map_err(|e| format!("Failed to create game directory: {}", e))?;
+        write_nbt_file(&path, &root)?;
+    }
+    Ok(added)
+}
+
+/// List the multiplayer servers saved in an instance's `servers.dat`
+#[command]
+pub async fn list_servers(modpack_id: String) -> Result<Vec<ServerEntry>, String> {
+    let path = get_instance_dir(&modpack_id).join("servers.dat");
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let root = read_nbt_file(&path)?;
+    let servers = match root.get("servers") {
+        Some(Tag::List(_, items)) => items.iter().filter_map(server_entry).collect(),
+        _ => Vec::new(),
+    };
+    Ok(servers)
+}
+
+const DEFAULT_SERVER_PORT: u16 = 25565;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Split a server address into host and port, defaulting to the Minecraft port
+fn split_address(address: &str) -> Result<(String, u16), String> {
+    let address = address.trim();
+    let (host, port) = if let Some(rest) = address.strip_prefix('[') {
+        // [ipv6]:port
+        let (host, after) = rest.split_once(']')
+            .ok_or(format!("Invalid server address '{}'", address))?;
+        (host, after.strip_prefix(':'))
+    } else if address.matches(':').count() == 1 {
+        let (host, port) = address.split_once(':').unwrap();
+        (host, Some(port))
+    } else {
+        (address, None)
+    };
+    if host.is_empty() {
+        return Err(format!("Invalid server address '{}'", address));
+    }
+    let port = match port {
+        Some(port) => port.parse()
+            .map_err(|_| format!("Invalid port in server address '{}'", address))?,
+        None => DEFAULT_SERVER_PORT,
+    };
+    Ok((host.to_string(), port))
+}
+
+fn try_connect(address: &str) -> Result<SocketAddr, String> {
+    let (host, port) = split_address(address)?;
+    let resolved: Vec<SocketAddr> = (host.as_str(), port)
+        .to_socket_addrs()
+        .map_err(|e| format!("Server unreachable: could not resolve {}: {}", host, e))?
+        .collect();
+    let mut last_error = None;
+    for addr in &resolved {
+        match TcpStream::connect_timeout(addr, CONNECT_TIMEOUT) {
+            Ok(_) => return Ok(*addr),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(match last_error {
+        Some(e) => format!("Server unreachable: {}:{} did not accept a connection: {}", host, port, e),
+        None => format!("Server unreachable: {} has no addresses", host),
+    })
+}
+
+/// Preflight for joining a server straight from the launcher: resolve the address
+/// and open a TCP connection, so an offline server is reported before the game
+/// starts instead of leaving it on the connecting screen. SRV records are not
+/// followed, so addresses relying on them need an explicit port.
+#[command]
+pub async fn check_server_reachable(address: String) -> Result<String, String> {
+    let addr = tauri::async_runtime::spawn_blocking(move || try_connect(&address))
+        .await
+        .map_err(|e| format!("Failed to check server: {}", e))??;
+    Ok(addr.to_string())
+}
@@ -1,7 +1,6 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
-use dirs;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Settings {
@@ -15,6 +14,40 @@ pub struct Settings {
     pub game_resolution: GameResolution,
     #[serde(rename = "gameDirectory")]
     pub game_directory: PathBuf,
+    #[serde(rename = "tolerateAssetFailures", default = "default_tolerate_asset_failures")]
+    pub tolerate_asset_failures: bool,
+    #[serde(rename = "preferRecommendedForge", default = "default_prefer_recommended_forge")]
+    pub prefer_recommended_forge: bool,
+    #[serde(rename = "forceIpv4", default)]
+    pub force_ipv4: bool,
+    #[serde(rename = "customCaCert", default)]
+    pub custom_ca_cert: Option<PathBuf>,
+    #[serde(rename = "verifyJarOnLaunch", default = "default_verify_jar_on_launch")]
+    pub verify_jar_on_launch: bool,
+    #[serde(rename = "gameLanguage", default)]
+    pub game_language: Option<String>,
+    #[serde(rename = "launchTimeoutSecs", default = "default_launch_timeout_secs")]
+    pub launch_timeout_secs: u64,
+    #[serde(rename = "assetsDirectory", default)]
+    pub assets_directory: Option<PathBuf>,
+    /// Holds instances, versions, libraries, assets and managed Java instead of `.minecraft`
+    #[serde(rename = "storageRoot", default)]
+    pub storage_root: Option<PathBuf>,
+    /// For mod development: `-javaagent` jars (with optional `=options`) added to every launch
+    #[serde(rename = "javaAgents", default)]
+    pub java_agents: Option<Vec<String>>,
+    /// For mod development: jars appended to every launch's classpath
+    #[serde(rename = "extraClasspath", default)]
+    pub extra_classpath: Option<Vec<PathBuf>>,
+    /// How many libraries are downloaded at once
+    #[serde(rename = "libraryDownloadBatchSize", default = "default_library_download_batch_size")]
+    pub library_download_batch_size: usize,
+    /// Adoptium vendor of the managed Java
+    #[serde(rename = "javaVendor", default = "default_java_vendor")]
+    pub java_vendor: String,
+    /// Whether the managed Java is a JRE or a full JDK
+    #[serde(rename = "javaImageType", default)]
+    pub java_image_type: crate::java::ImageType,
 }
 
 // Legacy settings struct for backward compatibility
@@ -44,12 +77,53 @@ impl Default for Settings {
                 height: 720,
             },
             game_directory: default_game_directory(),
+            tolerate_asset_failures: default_tolerate_asset_failures(),
+            prefer_recommended_forge: default_prefer_recommended_forge(),
+            force_ipv4: false,
+            custom_ca_cert: None,
+            verify_jar_on_launch: default_verify_jar_on_launch(),
+            game_language: None,
+            launch_timeout_secs: default_launch_timeout_secs(),
+            assets_directory: None,
+            storage_root: None,
+            java_agents: None,
+            extra_classpath: None,
+            library_download_batch_size: default_library_download_batch_size(),
+            java_vendor: default_java_vendor(),
+            java_image_type: crate::java::ImageType::default(),
         }
     }
 }
 
+fn default_tolerate_asset_failures() -> bool {
+    true
+}
+
+fn default_prefer_recommended_forge() -> bool {
+    true
+}
+
+fn default_verify_jar_on_launch() -> bool {
+    true
+}
+
+fn default_launch_timeout_secs() -> u64 {
+    120
+}
+
+fn default_library_download_batch_size() -> usize {
+    8
+}
+
+fn default_java_vendor() -> String {
+    crate::java::DEFAULT_JAVA_VENDOR.to_string()
+}
+
+/// The newest Java installed on this machine, or the bare command when none is found
 fn detect_java_path() -> String {
-    // TODO: Implement proper Java detection
+    if let Some(java) = crate::java::detect_java() {
+        return java.to_string_lossy().to_string();
+    }
     #[cfg(target_os = "windows")]
     return "javaw.exe".to_string();
     #[cfg(not(target_os = "windows"))]
@@ -57,12 +131,19 @@ fn detect_java_path() -> String {
 }
 
 fn default_game_directory() -> PathBuf {
-    dirs::config_dir()
+    crate::paths::config_dir()
         .expect("Failed to get config directory")
         .join(".minecraft-wise0wl")
 }
 
 fn get_settings_path() -> PathBuf {
+    if crate::paths::is_portable() {
+        // Portable mode keeps settings beside the rest of the launcher's config
+        return crate::paths::config_dir()
+            .expect("Failed to get config directory")
+            .join("wise0wl-launcher")
+            .join("settings.json");
+    }
     #[cfg(target_os = "windows")]
     {
         // On Windows: C:\Users\<User>\AppData\Local\Programs\Wise0wl\wise0wl-cmd\settings.json
@@ -77,7 +158,7 @@ fn get_settings_path() -> PathBuf {
     #[cfg(target_os = "macos")]
     {
         // On macOS: ~/Library/Application Support/Wise0wl/wise0wl-cmd/settings.json
-        let home_dir = dirs::home_dir().expect("Failed to get home directory");
+        let home_dir = crate::paths::home_dir().expect("Failed to get home directory");
         home_dir
             .join("Library")
             .join("Application Support")
@@ -88,7 +169,7 @@ fn get_settings_path() -> PathBuf {
     #[cfg(target_os = "linux")]
     {
         // On Linux: ~/.local/share/Wise0wl/wise0wl-cmd/settings.json
-        let home_dir = dirs::home_dir().expect("Failed to get home directory");
+        let home_dir = crate::paths::home_dir().expect("Failed to get home directory");
         home_dir
             .join(".local")
             .join("share")
@@ -98,6 +179,20 @@ fn get_settings_path() -> PathBuf {
     }
 }
 
+/// The configured storage root, if launcher data was relocated
+pub fn storage_root() -> Option<PathBuf> {
+    load_settings().ok().and_then(|s| s.storage_root)
+}
+
+/// Shared asset store used by every instance: the configured directory, or
+/// `assets/` inside the launcher's Minecraft directory
+pub fn assets_directory(minecraft_dir: &std::path::Path) -> PathBuf {
+    load_settings()
+        .ok()
+        .and_then(|s| s.assets_directory)
+        .unwrap_or_else(|| minecraft_dir.join("assets"))
+}
+
 pub fn load_settings() -> Result<Settings, String> {
     let settings_path = get_settings_path();
 
@@ -124,13 +219,39 @@ pub fn load_settings() -> Result<Settings, String> {
             min_memory: legacy_settings.min_memory,
             game_resolution: legacy_settings.game_resolution,
             game_directory: legacy_settings.game_directory,
+            tolerate_asset_failures: default_tolerate_asset_failures(),
+            prefer_recommended_forge: default_prefer_recommended_forge(),
+            force_ipv4: false,
+            custom_ca_cert: None,
+            verify_jar_on_launch: default_verify_jar_on_launch(),
+            game_language: None,
+            launch_timeout_secs: default_launch_timeout_secs(),
+            assets_directory: None,
+            storage_root: None,
+            java_agents: None,
+            extra_classpath: None,
+            library_download_batch_size: default_library_download_batch_size(),
+            java_vendor: default_java_vendor(),
+            java_image_type: crate::java::ImageType::default(),
         };
         // Save in new format for next time
         save_settings(&settings)?;
         return Ok(settings);
     }
 
-    Err("Failed to parse settings: invalid format".to_string())
+    // Neither format parsed: keep the broken file for inspection and start over
+    let backup_path = settings_path.with_extension("json.bak");
+    fs::rename(&settings_path, &backup_path)
+        .map_err(|e| format!("Failed to parse settings and could not back up the invalid file: {}", e))?;
+    println!("Warning: Settings file was invalid, moved it to {} and restored defaults", backup_path.display());
+    reset_settings()
+}
+
+/// Overwrite the settings file with the defaults and return them
+pub fn reset_settings() -> Result<Settings, String> {
+    let settings = Settings::default();
+    save_settings(&settings)?;
+    Ok(settings)
 }
 
 pub fn save_settings(settings: &Settings) -> Result<(), String> {
@@ -145,5 +266,8 @@ pub fn save_settings(settings: &Settings) -> Result<(), String> {
     let content = serde_json::to_string_pretty(settings)
         .map_err(|e| format!("Failed to serialize settings: {}", e))?;
 
-    fs::write(&settings_path, content).map_err(|e| format!("Failed to write settings file: {}", e))
+    // Write a temp file and rename it into place so a crash can't leave a truncated file
+    let tmp_path = settings_path.with_extension("json.tmp");
+    fs::write(&tmp_path, content).map_err(|e| format!("Failed to write settings file: {}", e))?;
+    fs::rename(&tmp_path, &settings_path).map_err(|e| format!("Failed to replace settings file: {}", e))
 }
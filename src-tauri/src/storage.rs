@@ -0,0 +1,188 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::command;
+
+/// A relocation that was started but hasn't finished yet, so it can be resumed
+#[derive(Debug, Serialize, Deserialize)]
+struct PendingRelocation {
+    new_root: PathBuf,
+}
+
+/// One data directory moved by `relocate_data`, counted at its new location
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RelocatedDir {
+    pub name: String,
+    pub path: PathBuf,
+    pub files: u64,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RelocationResult {
+    pub new_root: PathBuf,
+    pub moved: Vec<RelocatedDir>,
+    /// Whether this call finished a relocation an earlier call left partway
+    pub resumed: bool,
+}
+
+fn get_relocation_path() -> PathBuf {
+    crate::paths::config_dir()
+        .expect("Failed to get config directory")
+        .join("wise0wl-launcher")
+        .join("relocation.json")
+}
+
+fn load_pending_relocation() -> Option<PendingRelocation> {
+    fs::read_to_string(get_relocation_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+}
+
+fn save_pending_relocation(pending: &PendingRelocation) -> Result<(), String> {
+    let path = get_relocation_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+    let content = serde_json::to_string_pretty(pending)
+        .map_err(|e| format!("Failed to serialize relocation state: {}", e))?;
+    fs::write(&path, content)
+        .map_err(|e| format!("Failed to write relocation state: {}", e))
+}
+
+/// The data directories the launcher manages and where they currently live
+fn data_dirs() -> Vec<(&'static str, PathBuf)> {
+    let minecraft_dir = crate::downloader::get_minecraft_dir();
+    vec![
+        ("instances", minecraft_dir.join("instances")),
+        ("versions", minecraft_dir.join("versions")),
+        ("libraries", minecraft_dir.join("libraries")),
+        ("assets", crate::settings::assets_directory(&minecraft_dir)),
+        ("runtime", crate::java::runtime_root()),
+    ]
+}
+
+/// Copy a file, checking the copy has the source's size before the source is removed
+fn copy_file_verified(from: &Path, to: &Path) -> Result<(), String> {
+    let expected = fs::metadata(from)
+        .map_err(|e| format!("Failed to read {}: {}", from.display(), e))?
+        .len();
+    let copied = fs::copy(from, to)
+        .map_err(|e| format!("Failed to copy {} to {}: {}", from.display(), to.display(), e))?;
+    if copied != expected {
+        return Err(format!(
+            "Copy of {} is incomplete ({} of {} bytes)",
+            from.display(), copied, expected
+        ));
+    }
+    fs::remove_file(from)
+        .map_err(|e| format!("Failed to remove {}: {}", from.display(), e))
+}
+
+/// Move `from` to `to`, merging into whatever an interrupted earlier move left at
+/// `to`. A rename is tried first; moves across drives fall back to copying.
+fn move_path(from: &Path, to: &Path) -> Result<(), String> {
+    if !to.exists() && fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    if from.is_dir() {
+        fs::create_dir_all(to)
+            .map_err(|e| format!("Failed to create {}: {}", to.display(), e))?;
+        let entries = fs::read_dir(from)
+            .map_err(|e| format!("Failed to read {}: {}", from.display(), e))?;
+        for entry in entries.flatten() {
+            move_path(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        fs::remove_dir(from)
+            .map_err(|e| format!("Failed to remove {}: {}", from.display(), e))
+    } else {
+        copy_file_verified(from, to)
+    }
+}
+
+/// Number of files and total bytes under a directory
+fn dir_stats(dir: &Path) -> (u64, u64) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return (0, 0);
+    };
+    entries.flatten().fold((0, 0), |(files, bytes), entry| {
+        match entry.metadata() {
+            Ok(metadata) if metadata.is_dir() => {
+                let (f, b) = dir_stats(&entry.path());
+                (files + f, bytes + b)
+            }
+            Ok(metadata) => (files + 1, bytes + metadata.len()),
+            Err(_) => (files, bytes),
+        }
+    })
+}
+
+/// Move instances, versions, libraries, assets and managed Java under `new_root`
+/// and point the launcher there. Calling it again after an interruption resumes
+/// the move; the stored storage root only changes once everything has moved.
+#[command]
+pub async fn relocate_data(new_root: PathBuf) -> Result<RelocationResult, String> {
+    let running = crate::minecraft::running_instances();
+    if !running.is_empty() {
+        let ids: Vec<String> = running.into_iter().map(|game| game.modpack_id).collect();
+        return Err(format!("Close the running game first: {}", ids.join(", ")));
+    }
+
+    let dir_check = crate::downloader::check_game_directory(&new_root);
+    if let Some(error) = dir_check.error {
+        return Err(error);
+    }
+    fs::create_dir_all(&new_root)
+        .map_err(|e| format!("Failed to create {}: {}", new_root.display(), e))?;
+    let new_root = fs::canonicalize(&new_root)
+        .map_err(|e| format!("Failed to resolve {}: {}", new_root.display(), e))?;
+    let pending = load_pending_relocation();
+    if let Some(pending) = &pending {
+        if pending.new_root != new_root {
+            return Err(format!(
+                "A move to {} is unfinished; finish it before choosing another location",
+                pending.new_root.display()
+            ));
+        }
+    }
+    let resumed = pending.is_some();
+
+    let dirs = data_dirs();
+    let old_runtime = crate::java::runtime_root();
+    for (name, current) in &dirs {
+        if new_root.starts_with(current) {
+            return Err(format!("{} is inside the current {} directory", new_root.display(), name));
+        }
+    }
+
+    save_pending_relocation(&PendingRelocation { new_root: new_root.clone() })?;
+    let mut moved = Vec::new();
+    for (name, current) in dirs {
+        let target = new_root.join(name);
+        if current.exists() && current != target {
+            println!("Moving {} from {} to {}", name, current.display(), target.display());
+            move_path(&current, &target)?;
+        }
+        // Anything still at the old location means the move didn't complete
+        if current.exists() && current != target {
+            return Err(format!("{} was not fully moved from {}", name, current.display()));
+        }
+        let (files, bytes) = dir_stats(&target);
+        moved.push(RelocatedDir { name: name.to_string(), path: target, files, bytes });
+    }
+
+    let mut settings = crate::settings::load_settings()?;
+    settings.storage_root = Some(new_root.clone());
+    // Assets now live under the new root too
+    settings.assets_directory = None;
+    // A Java path into the managed runtimes must follow them
+    if let Ok(relative) = Path::new(&settings.java_path).strip_prefix(&old_runtime) {
+        settings.java_path = new_root.join("runtime").join(relative).to_string_lossy().to_string();
+        println!("Java path moved to {}", settings.java_path);
+    }
+    crate::settings::save_settings(&settings)?;
+    let _ = fs::remove_file(get_relocation_path());
+    println!("Relocated launcher data to {}", new_root.display());
+    Ok(RelocationResult { new_root, moved, resumed })
+}
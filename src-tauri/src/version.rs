@@ -0,0 +1,158 @@
+use std::cmp::Ordering;
+
+/// Split a version into its release part and an optional pre-release suffix.
+/// A `-` only starts a pre-release when what follows contains letters, so
+/// `1.20.1-47.2.0` stays whole while `20.4.80-beta` is split.
+fn split_prerelease(version: &str) -> (&str, Option<&str>) {
+    for (i, c) in version.char_indices() {
+        if c == '-' || c == '+' {
+            let suffix = &version[i + 1..];
+            if suffix.chars().any(|c| c.is_ascii_alphabetic()) {
+                return (&version[..i], Some(suffix));
+            }
+        }
+    }
+    (version, None)
+}
+
+fn compare_segments(a: &str, b: &str) -> Ordering {
+    let mut a_parts = a.split(['.', '-', '+', '_']);
+    let mut b_parts = b.split(['.', '-', '+', '_']);
+    loop {
+        match (a_parts.next(), b_parts.next()) {
+            (None, None) => return Ordering::Equal,
+            (Some(_), None) => return Ordering::Greater,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(x), Some(y)) => {
+                let ordering = match (x.parse::<u64>(), y.parse::<u64>()) {
+                    (Ok(x), Ok(y)) => x.cmp(&y),
+                    // Numeric segments sort after textual ones (e.g. "1" > "beta")
+                    (Ok(_), Err(_)) => Ordering::Greater,
+                    (Err(_), Ok(_)) => Ordering::Less,
+                    (Err(_), Err(_)) => x.cmp(y),
+                };
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+        }
+    }
+}
+
+/// Compare two version strings segment by segment, treating numeric parts
+/// numerically and ranking a pre-release below the matching release.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let (a_core, a_pre) = split_prerelease(a);
+    let (b_core, b_pre) = split_prerelease(b);
+    compare_segments(a_core, b_core).then_with(|| match (a_pre, b_pre) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(x), Some(y)) => compare_segments(x, y),
+    })
+}
+
+/// Whether a version is a beta/alpha/rc/snapshot rather than a stable release
+pub fn is_prerelease(version: &str) -> bool {
+    let lower = version.to_lowercase();
+    split_prerelease(&lower).1.is_some()
+        || ["alpha", "beta", "rc", "pre", "snapshot"].iter().any(|tag| lower.contains(tag))
+}
+
+/// Pick the highest version from `candidates`, skipping pre-releases unless allowed
+pub fn select_highest<'a, I>(candidates: I, allow_prerelease: bool) -> Option<String>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    candidates
+        .into_iter()
+        .filter(|v| allow_prerelease || !is_prerelease(v))
+        .max_by(|a, b| compare_versions(a, b))
+        .map(|v| v.to_string())
+}
+
+/// Remove a leading "<mc_version>-" from a modloader version, so
+/// "1.20.4-20.4.80" and "20.4.80" both become "20.4.80"
+pub fn strip_mc_prefix<'a>(mc_version: &str, loader_version: &'a str) -> &'a str {
+    loader_version
+        .strip_prefix(mc_version)
+        .and_then(|v| v.strip_prefix('-'))
+        .unwrap_or(loader_version)
+}
+
+/// Maven artifact version of a Forge build, which always carries the MC prefix
+pub fn forge_artifact_version(mc_version: &str, forge_version: &str) -> String {
+    format!("{}-{}", mc_version, strip_mc_prefix(mc_version, forge_version))
+}
+
+/// Installer URL for a Forge build, accepting the version with or without the MC prefix.
+/// A pinned `installer_version` is used as the exact maven artifact version, for
+/// builds whose path doesn't follow the usual pattern (e.g. "1.7.10-10.13.4.1614-1.7.10").
+pub fn forge_installer_url(mc_version: &str, forge_version: &str, installer_version: Option<&str>) -> String {
+    let artifact = installer_version
+        .map(|v| v.to_string())
+        .unwrap_or_else(|| forge_artifact_version(mc_version, forge_version));
+    format!("https://maven.minecraftforge.net/net/minecraftforge/forge/{artifact}/forge-{artifact}-installer.jar")
+}
+
+/// Installer URL for a NeoForge build, accepting the version with or without the MC prefix.
+/// NeoForge for 1.20.1 was still published as the prefixed `forge` artifact; later
+/// releases use the bare `neoforge` version. A pinned `installer_version` is used as
+/// the exact artifact version.
+pub fn neoforge_installer_url(mc_version: &str, neoforge_version: &str, installer_version: Option<&str>) -> String {
+    if let Some(artifact) = installer_version {
+        let group = if artifact.starts_with("1.20.1-") { "forge" } else { "neoforge" };
+        return format!("https://maven.neoforged.net/releases/net/neoforged/{group}/{artifact}/{group}-{artifact}-installer.jar");
+    }
+    let version = strip_mc_prefix(mc_version, neoforge_version);
+    if mc_version == "1.20.1" {
+        format!("https://maven.neoforged.net/releases/net/neoforged/forge/{mc_version}-{version}/forge-{mc_version}-{version}-installer.jar")
+    } else {
+        format!("https://maven.neoforged.net/releases/net/neoforged/neoforge/{version}/neoforge-{version}-installer.jar")
+    }
+}
+
+/// Download URL of a Fabric installer release
+pub fn fabric_installer_url(installer_version: &str) -> String {
+    format!("https://maven.fabricmc.net/net/fabricmc/fabric-installer/{0}/fabric-installer-{0}.jar", installer_version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_mc_prefix_accepts_both_forms() {
+        assert_eq!(strip_mc_prefix("1.20.4", "1.20.4-20.4.80"), "20.4.80");
+        assert_eq!(strip_mc_prefix("1.20.4", "20.4.80"), "20.4.80");
+        // A different MC version isn't mistaken for the prefix
+        assert_eq!(strip_mc_prefix("1.20", "1.20.4-20.4.80"), "1.20.4-20.4.80");
+    }
+
+    #[test]
+    fn forge_artifact_version_always_carries_the_prefix() {
+        assert_eq!(forge_artifact_version("1.20.1", "47.2.0"), "1.20.1-47.2.0");
+        assert_eq!(forge_artifact_version("1.20.1", "1.20.1-47.2.0"), "1.20.1-47.2.0");
+    }
+
+    #[test]
+    fn neoforge_url_is_the_same_for_both_forms() {
+        let expected = "https://maven.neoforged.net/releases/net/neoforged/neoforge/20.4.80/neoforge-20.4.80-installer.jar";
+        assert_eq!(neoforge_installer_url("1.20.4", "20.4.80", None), expected);
+        assert_eq!(neoforge_installer_url("1.20.4", "1.20.4-20.4.80", None), expected);
+    }
+
+    #[test]
+    fn neoforge_1_20_1_uses_the_prefixed_forge_artifact() {
+        let expected = "https://maven.neoforged.net/releases/net/neoforged/forge/1.20.1-47.1.106/forge-1.20.1-47.1.106-installer.jar";
+        assert_eq!(neoforge_installer_url("1.20.1", "47.1.106", None), expected);
+        assert_eq!(neoforge_installer_url("1.20.1", "1.20.1-47.1.106", None), expected);
+    }
+
+    #[test]
+    fn forge_url_is_the_same_for_both_forms() {
+        let expected = "https://maven.minecraftforge.net/net/minecraftforge/forge/1.20.1-47.2.0/forge-1.20.1-47.2.0-installer.jar";
+        assert_eq!(forge_installer_url("1.20.1", "47.2.0", None), expected);
+        assert_eq!(forge_installer_url("1.20.1", "1.20.1-47.2.0", None), expected);
+    }
+}
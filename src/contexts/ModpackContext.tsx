@@ -4,6 +4,7 @@ import { listen } from '@tauri-apps/api/event';
 import { open } from '@tauri-apps/plugin-shell';
 import {
   OnlineModpack,
+  ModpackList,
   Modpack,
   Settings,
   LaunchStatus,
@@ -21,6 +22,7 @@ interface ModpackContextType {
   launchGame: () => Promise<void>;
   launchStatus: LaunchStatus;
   onlineModpacks: OnlineModpack[];
+  onlineModpacksStale: boolean;
   fetchOnlineModpacks: (listUrl: string) => Promise<void>;
   installOnlineModpack: (modpack: OnlineModpack) => Promise<void>;
   userGroups: string[];
@@ -56,6 +58,7 @@ export const ModpackProvider: React.FC<{ children: React.ReactNode }> = ({ child
     message: 'Ready to play',
   });
   const [onlineModpacks, setOnlineModpacks] = useState<OnlineModpack[]>([]);
+  const [onlineModpacksStale, setOnlineModpacksStale] = useState(false);
   const [userGroups, setUserGroups] = useState<string[]>(['default']);
   const [authToken, setAuthToken] = useState<AuthToken | null>(null);
   const [isAuthLoading, setIsAuthLoading] = useState(false);
@@ -197,11 +200,12 @@ export const ModpackProvider: React.FC<{ children: React.ReactNode }> = ({ child
   // Group-aware: Fetch online modpacks for the user's groups
   const fetchOnlineModpacks = async (listUrl: string) => {
     try {
-      const packs = await invoke<OnlineModpack[]>('fetch_modpack_list', {
+      const list = await invoke<ModpackList>('fetch_modpack_list', {
         listUrl,
         userGroups,
       });
-      setOnlineModpacks(packs);
+      setOnlineModpacks(list.modpacks);
+      setOnlineModpacksStale(list.stale);
     } catch (error) {
       console.error('Failed to fetch online modpacks:', error);
     }
@@ -350,6 +354,7 @@ export const ModpackProvider: React.FC<{ children: React.ReactNode }> = ({ child
     launchGame,
     launchStatus,
     onlineModpacks,
+    onlineModpacksStale,
     fetchOnlineModpacks,
     installOnlineModpack,
     userGroups,
@@ -13,6 +13,12 @@ export interface OnlineModpack {
   scopes?: string[];
 }
 
+export interface ModpackList {
+  modpacks: OnlineModpack[];
+  // True when the list host was unreachable and the cached copy was used
+  stale: boolean;
+}
+
 export interface ModFileEntry {
   filename: string;
   url: string;